@@ -17,26 +17,33 @@ pub async fn main() {
     while let Some(tag) = stream.try_next().await.unwrap() {
         match tag.data {
             FlvTagData::Video(video) => {
-                println!(
-                    "{:?} {:?} {} {:?} {} {:?}",
-                    &tag.header,
-                    &video.header,
-                    video.body.pts_offset,
-                    video.body.param_count,
-                    video.body.nalus.len(),
-                    video
-                        .body
-                        .nalus
-                        .iter()
-                        .map(|x| &x[0..4])
-                        .collect::<Vec<_>>()
-                );
+                for track in &video.tracks {
+                    println!(
+                        "{:?} {:?} {} {} {:?} {} {:?}",
+                        &tag.header,
+                        &video.header,
+                        track.track_id,
+                        track.body.pts_offset,
+                        track.body.param_count,
+                        track.body.nalus.len(),
+                        track
+                            .body
+                            .nalus
+                            .iter()
+                            .map(|x| &x[0..x.len().min(4)])
+                            .collect::<Vec<_>>()
+                    );
+                }
             }
 
             FlvTagData::Meta(meta) => {
                 println!("{:#?}", meta);
             }
 
+            FlvTagData::OnMetaData(meta) => {
+                println!("{:#?}", meta);
+            }
+
             _ => (),
         }
     }
@@ -0,0 +1,208 @@
+use std::io::{ErrorKind, Result};
+
+/// MSB-first reader of bit fields from a byte slice.
+#[derive(Debug, Clone)]
+pub(crate) struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    /// Number of bits left.
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        (self.data.len() * 8).saturating_sub(self.pos)
+    }
+
+    #[inline]
+    pub fn read_bit(&mut self) -> Result<bool> {
+        let byte = self
+            .data
+            .get(self.pos / 8)
+            .ok_or(ErrorKind::UnexpectedEof)?;
+
+        let bit = (byte >> (7 - self.pos % 8)) & 1;
+        self.pos += 1;
+
+        Ok(bit > 0)
+    }
+
+    /// Read `count` bits (up to 64) as unsigned integer.
+    pub fn read_bits(&mut self, count: u32) -> Result<u64> {
+        debug_assert!(count <= 64);
+
+        if self.remaining() < count as usize {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+
+        let mut value = 0u64;
+        for _ in 0..count {
+            value = (value << 1) | self.read_bit()? as u64;
+        }
+
+        Ok(value)
+    }
+
+    #[inline]
+    pub fn read_u8(&mut self, count: u32) -> Result<u8> {
+        Ok(self.read_bits(count)? as u8)
+    }
+
+    #[inline]
+    pub fn read_u32(&mut self, count: u32) -> Result<u32> {
+        Ok(self.read_bits(count)? as u32)
+    }
+
+    pub fn skip_bits(&mut self, count: usize) -> Result<()> {
+        if self.remaining() < count {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+
+        self.pos += count;
+
+        Ok(())
+    }
+
+    /// Read exp-Golomb coded unsigned number `ue(v)`.
+    pub fn read_ue(&mut self) -> Result<u32> {
+        let mut leading_zeros = 0;
+
+        while !self.read_bit()? {
+            leading_zeros += 1;
+
+            if leading_zeros > 31 {
+                return Err(std::io::Error::new(
+                    ErrorKind::InvalidData,
+                    "exp-golomb code is too long",
+                ));
+            }
+        }
+
+        Ok(((1u64 << leading_zeros) - 1 + self.read_bits(leading_zeros)?) as u32)
+    }
+
+    /// Read `ue(v)` failing if the value is above `max`.
+    pub fn read_ue_max(&mut self, max: u32) -> Result<u32> {
+        let value = self.read_ue()?;
+
+        if value > max {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidData,
+                format!("parameter set value {} is out of range 0..={}", value, max),
+            ));
+        }
+
+        Ok(value)
+    }
+
+    /// Read exp-Golomb coded signed number `se(v)`.
+    pub fn read_se(&mut self) -> Result<i32> {
+        let value = self.read_ue()? as i64;
+
+        Ok(if value & 1 > 0 {
+            (value + 1) / 2
+        } else {
+            -(value / 2)
+        } as i32)
+    }
+
+    /// Read AV1 variable length unsigned number `uvlc()`.
+    pub fn read_uvlc(&mut self) -> Result<u32> {
+        let mut leading_zeros = 0;
+
+        while !self.read_bit()? {
+            leading_zeros += 1;
+        }
+
+        if leading_zeros >= 32 {
+            return Ok(u32::MAX);
+        }
+
+        Ok(self.read_u32(leading_zeros)? + ((1u64 << leading_zeros) - 1) as u32)
+    }
+}
+
+/// Remove `emulation_prevention_three_byte` from the NAL unit payload.
+pub(crate) fn remove_emulation_prevention(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut zeros = 0;
+
+    for &b in data {
+        if zeros >= 2 && b == 0x03 {
+            zeros = 0;
+            continue;
+        }
+
+        zeros = if b == 0 { zeros + 1 } else { 0 };
+        out.push(b);
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use super::{BitReader, remove_emulation_prevention};
+
+    #[test]
+    fn bits() {
+        let mut r = BitReader::new(&[0b1010_0110, 0xff]);
+
+        assert!(r.read_bit().unwrap());
+        assert_eq!(r.read_u8(3).unwrap(), 0b010);
+        r.skip_bits(2).unwrap();
+        assert_eq!(r.remaining(), 10);
+        assert_eq!(r.read_u32(10).unwrap(), 0b10_1111_1111);
+        assert_eq!(r.read_bit().unwrap_err().kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn uvlc() {
+        let mut r = BitReader::new(&[0b1011_0001, 0b0000_0000]);
+
+        assert_eq!(r.read_uvlc().unwrap(), 0);
+        assert_eq!(r.read_uvlc().unwrap(), 2);
+        assert_eq!(r.read_uvlc().unwrap(), 7);
+    }
+
+    #[test]
+    fn exp_golomb() {
+        // ue: 1 -> 0, 010 -> 1, 011 -> 2, 00100 -> 3, 00111 -> 6, 0001000 -> 7
+        let mut r = BitReader::new(&[0b1010_0110, 0b0100_0011, 0b1000_1000]);
+
+        let values: Vec<_> = (0..6).map(|_| r.read_ue().unwrap()).collect();
+        assert_eq!(values, [0, 1, 2, 3, 6, 7]);
+
+        // se: 1 -> 0, 010 -> 1, 011 -> -1, 00100 -> 2, 00101 -> -2
+        let mut r = BitReader::new(&[0b1010_0110, 0b0100_0010, 0b1000_0000]);
+
+        let values: Vec<_> = (0..5).map(|_| r.read_se().unwrap()).collect();
+        assert_eq!(values, [0, 1, -1, 2, -2]);
+
+        let mut r = BitReader::new(&[0b0010_0000]);
+        assert_eq!(r.read_ue_max(2).unwrap_err().kind(), ErrorKind::InvalidData);
+
+        let mut r = BitReader::new(&[0; 8]);
+        assert_eq!(r.read_ue().unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn emulation_prevention() {
+        assert_eq!(
+            remove_emulation_prevention(&[0x00, 0x00, 0x03, 0x01, 0x00, 0x00, 0x03, 0x00, 0x03]),
+            [0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x03]
+        );
+
+        // only after two zero bytes
+        assert_eq!(
+            remove_emulation_prevention(&[0x00, 0x03, 0x00, 0x01, 0x00, 0x00, 0x03]),
+            [0x00, 0x03, 0x00, 0x01, 0x00, 0x00]
+        );
+    }
+}
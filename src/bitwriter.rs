@@ -0,0 +1,59 @@
+use bytes::Bytes;
+
+/// MSB-first writer of bit fields, the counterpart of `BitReader`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BitWriter {
+    data: Vec<u8>,
+    pos: usize,
+}
+
+impl BitWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of bits written.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.pos
+    }
+
+    #[inline]
+    pub fn write_bit(&mut self, bit: bool) {
+        if self.pos.is_multiple_of(8) {
+            self.data.push(0);
+        }
+
+        if bit {
+            *self.data.last_mut().unwrap() |= 1 << (7 - self.pos % 8);
+        }
+
+        self.pos += 1;
+    }
+
+    /// Write `count` lower bits (up to 64) of `value`.
+    pub fn write_bits(&mut self, value: u64, count: u32) {
+        debug_assert!(count <= 64);
+
+        for i in (0..count).rev() {
+            self.write_bit((value >> i) & 1 > 0);
+        }
+    }
+
+    /// Write the bytes, not necessarily at byte boundary.
+    pub fn write_bytes(&mut self, data: &[u8]) {
+        if self.pos.is_multiple_of(8) {
+            self.data.extend_from_slice(data);
+            self.pos += data.len() * 8;
+        } else {
+            for &byte in data {
+                self.write_bits(byte as u64, 8);
+            }
+        }
+    }
+
+    /// Written bytes, the last one is padded with zero bits.
+    pub fn into_bytes(self) -> Bytes {
+        self.data.into()
+    }
+}
@@ -0,0 +1,413 @@
+use std::{io::ErrorKind, marker::PhantomData, ops::Range};
+
+use bytes::BytesMut;
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::{
+    DEMUX_ALL_TYPES,
+    error::Error,
+    header::FlvHeader,
+    parser::{FlvParser, Parser},
+    tag::{FlvTag, FlvTagHeader, FlvTagType},
+    writer::{FlvWriter, Writer},
+};
+
+/// Size of FLV header without the data offset.
+const HEADER_SIZE: usize = 9;
+
+/// Size of FLV tag header.
+const TAG_HEADER_SIZE: usize = 11;
+
+/// Size of `PreviousTagSize` field.
+const PREV_TAG_SIZE: usize = 4;
+
+/// How far back in milliseconds the timestamp of a resynchronized tag may go.
+const RESYNC_MAX_TS_BACKWARD: u32 = 1_000;
+
+/// How far forward in milliseconds the timestamp of a resynchronized tag may go.
+const RESYNC_MAX_TS_FORWARD: u32 = 10 * 60 * 1_000;
+
+/// Result of tag validation in lenient mode.
+enum Check {
+    Valid,
+    Invalid,
+    NeedMore,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Header,
+
+    /// Skipping the rest of the header up to the data offset.
+    Skip(usize),
+    Tags,
+}
+
+/// FLV stream codec for `FramedRead` and `FramedWrite`.
+///
+/// The decoder consumes FLV header and yields the tags of `tag_types`, the tag data is split
+/// off the read buffer, so the `Bytes` of the parsed tags share it with no copying.
+/// The encoder writes FLV header and tags, each followed by the `PreviousTagSize` field.
+///
+/// In lenient mode a tag not following `PreviousTagSize` of the prior tag is accepted only if
+/// its header looks plausible and its own `PreviousTagSize` matches, otherwise the stream is
+/// scanned forward for such a tag. The tags failing to parse are skipped as well.
+/// Otherwise the stream ending inside the header or a tag fails with `UnexpectedEof`.
+#[derive(Debug)]
+pub struct FlvCodec<E = flowly::Void> {
+    parser: FlvParser,
+    writer: FlvWriter,
+    header: Option<FlvHeader>,
+    state: State,
+    tag_types: u64,
+    lenient: bool,
+    eof: bool,
+
+    /// Stream offset of the read buffer start.
+    offset: u64,
+
+    /// Size and timestamp of the last tag.
+    prev_tag_size: u32,
+    prev_timestamp: Option<u32>,
+
+    /// Stream offset where the current skipped range starts, the ranges do not include
+    /// `PreviousTagSize` preceding the tags.
+    skip_start: Option<u64>,
+    skipped: Vec<Range<u64>>,
+    _error: PhantomData<fn() -> E>,
+}
+
+impl<E> FlvCodec<E> {
+    /// Codec decoding tags of `tag_types`, a bit mask of `1 << FlvTagType`.
+    pub fn new(tag_types: u64) -> Self {
+        Self {
+            parser: FlvParser::default(),
+            writer: FlvWriter::default(),
+            header: None,
+            state: State::Header,
+            tag_types,
+            lenient: false,
+            eof: false,
+            offset: 0,
+            prev_tag_size: 0,
+            prev_timestamp: None,
+            skip_start: None,
+            skipped: Vec::new(),
+            _error: PhantomData,
+        }
+    }
+
+    /// Resynchronize after corrupt or truncated tags instead of failing.
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Codec with the given writer settings.
+    pub fn with_writer(mut self, writer: FlvWriter) -> Self {
+        self.writer = writer;
+        self
+    }
+
+    /// FLV header, available once decoded.
+    pub fn header(&self) -> Option<&FlvHeader> {
+        self.header.as_ref()
+    }
+
+    /// Take the stream byte ranges skipped in lenient mode so far.
+    pub fn take_skipped(&mut self) -> Vec<Range<u64>> {
+        std::mem::take(&mut self.skipped)
+    }
+
+    #[inline]
+    fn advance(&mut self, src: &mut BytesMut, count: usize) -> BytesMut {
+        self.offset += count as u64;
+        src.split_to(count)
+    }
+
+    /// Skip `count` bytes of the buffer starting with `PreviousTagSize`.
+    fn skip(&mut self, src: &mut BytesMut, count: usize) {
+        self.skip_start
+            .get_or_insert(self.offset + PREV_TAG_SIZE as u64);
+        self.advance(src, count);
+    }
+
+    fn end_skip(&mut self, end: u64) {
+        if let Some(start) = self.skip_start.take() {
+            log::warn!("FlvCodec: skipped bytes {}..{}", start, end);
+            self.skipped.push(start..end);
+        }
+    }
+
+    /// Validate the tag at the start of `src` in lenient mode.
+    fn check_tag(&self, src: &[u8], data_size: usize) -> Check {
+        let size = PREV_TAG_SIZE + TAG_HEADER_SIZE + data_size;
+        let prev_tag_size = u32::from_be_bytes([src[0], src[1], src[2], src[3]]);
+
+        let tag = &src[PREV_TAG_SIZE..];
+        let timestamp = (u32::from(tag[7]) << 24) | u32::from_be_bytes([0, tag[4], tag[5], tag[6]]);
+
+        if matches!(FlvTagType::from(tag[0] & 0x1F), FlvTagType::Unknown(_))
+            || tag[0] & 0xC0 != 0
+            || tag[8..11] != [0, 0, 0]
+        {
+            return Check::Invalid;
+        }
+
+        // the tag following the prior one is trusted
+        if self.skip_start.is_none() && prev_tag_size == self.prev_tag_size {
+            return if src.len() < size {
+                Check::NeedMore
+            } else {
+                Check::Valid
+            };
+        }
+
+        if let Some(prev) = self.prev_timestamp
+            && (timestamp < prev.saturating_sub(RESYNC_MAX_TS_BACKWARD)
+                || timestamp > prev.saturating_add(RESYNC_MAX_TS_FORWARD))
+        {
+            return Check::Invalid;
+        }
+
+        // the trailing `PreviousTagSize` is missing at the end of the stream
+        if src.len() < size + PREV_TAG_SIZE {
+            return match (self.eof, src.len() >= size) {
+                (false, _) => Check::NeedMore,
+                (true, true) => Check::Valid,
+                (true, false) => Check::Invalid,
+            };
+        }
+
+        let trailing = &src[size..size + PREV_TAG_SIZE];
+        if u32::from_be_bytes([trailing[0], trailing[1], trailing[2], trailing[3]])
+            != (TAG_HEADER_SIZE + data_size) as u32
+        {
+            return Check::Invalid;
+        }
+
+        Check::Valid
+    }
+}
+
+impl<E> Default for FlvCodec<E> {
+    fn default() -> Self {
+        Self::new(DEMUX_ALL_TYPES)
+    }
+}
+
+impl<E> Decoder for FlvCodec<E> {
+    type Item = FlvTag;
+    type Error = Error<E>;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        loop {
+            match self.state {
+                State::Header => {
+                    if src.len() < HEADER_SIZE {
+                        return Ok(None);
+                    }
+
+                    let mut buf = self.advance(src, HEADER_SIZE);
+                    let header: FlvHeader = self.parser.parse(&mut buf)?;
+
+                    self.state = State::Skip(header.remaining as usize);
+                    self.header = Some(header);
+                }
+                State::Skip(count) => {
+                    let n = count.min(src.len());
+                    self.advance(src, n);
+
+                    if n < count {
+                        self.state = State::Skip(count - n);
+                        return Ok(None);
+                    }
+
+                    self.state = State::Tags;
+                }
+                State::Tags => {
+                    if src.len() < PREV_TAG_SIZE + TAG_HEADER_SIZE {
+                        src.reserve(PREV_TAG_SIZE + TAG_HEADER_SIZE - src.len());
+                        return Ok(None);
+                    }
+
+                    let data_size = u32::from_be_bytes([
+                        0,
+                        src[PREV_TAG_SIZE + 1],
+                        src[PREV_TAG_SIZE + 2],
+                        src[PREV_TAG_SIZE + 3],
+                    ]) as usize;
+
+                    let size = PREV_TAG_SIZE + TAG_HEADER_SIZE + data_size;
+
+                    if self.lenient {
+                        match self.check_tag(src, data_size) {
+                            Check::Valid => self.end_skip(self.offset + PREV_TAG_SIZE as u64),
+                            Check::Invalid => {
+                                self.skip(src, 1);
+                                continue;
+                            }
+                            Check::NeedMore => {
+                                src.reserve(size + PREV_TAG_SIZE - src.len());
+                                return Ok(None);
+                            }
+                        }
+                    } else if src.len() < size {
+                        src.reserve(size - src.len());
+                        return Ok(None);
+                    }
+
+                    self.advance(src, PREV_TAG_SIZE);
+                    let start = self.offset;
+
+                    let mut buf = self.advance(src, TAG_HEADER_SIZE);
+                    let header: FlvTagHeader = self.parser.parse(&mut buf)?;
+                    let mut data = self.advance(src, data_size).freeze();
+
+                    self.prev_tag_size = (TAG_HEADER_SIZE + data_size) as u32;
+                    self.prev_timestamp = Some(header.timestamp);
+
+                    if self.tag_types & (1u64 << u8::from(header.tag_type)) == 0 {
+                        continue;
+                    }
+
+                    match self.parser.parse_flv_data(&mut data, header.tag_type) {
+                        Ok(data) => return Ok(Some(FlvTag { data, header })),
+                        Err(_) if self.lenient => {
+                            log::warn!("FlvCodec: skipped invalid tag {}..{}", start, self.offset);
+                            self.skipped.push(start..self.offset);
+                        }
+                        Err(err) => return Err(err),
+                    }
+                }
+            }
+        }
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        self.eof = true;
+
+        if let Some(tag) = self.decode(src)? {
+            return Ok(Some(tag));
+        }
+
+        if !self.lenient && self.state != State::Tags {
+            return Err(Error::IoError(std::io::Error::new(
+                ErrorKind::UnexpectedEof,
+                "truncated flv header",
+            )));
+        }
+
+        // the last `PreviousTagSize` or a truncated tag
+        if src.len() > PREV_TAG_SIZE {
+            if !self.lenient {
+                return Err(Error::IoError(std::io::Error::new(
+                    ErrorKind::UnexpectedEof,
+                    format!("{} bytes of truncated tag at the end", src.len()),
+                )));
+            }
+
+            self.skip(src, src.len());
+            self.end_skip(self.offset);
+        }
+
+        self.advance(src, src.len());
+
+        Ok(None)
+    }
+}
+
+impl<E> Encoder<FlvHeader> for FlvCodec<E> {
+    type Error = Error<E>;
+
+    fn encode(&mut self, item: FlvHeader, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        Writer::<E, _>::write(&mut self.writer, dst, &item)
+    }
+}
+
+impl<E> Encoder<FlvTag> for FlvCodec<E> {
+    type Error = Error<E>;
+
+    fn encode(&mut self, item: FlvTag, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        Writer::<E, _>::write(&mut self.writer, dst, &item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use bytes::{BufMut, BytesMut};
+    use tokio_util::codec::Decoder;
+
+    use super::FlvCodec;
+    use crate::error::Error;
+
+    const HEADER: [u8; 13] = [b'F', b'L', b'V', 1, 0b100, 0, 0, 0, 9, 0, 0, 0, 0];
+
+    fn stream(tail: &[u8]) -> BytesMut {
+        let mut buf = BytesMut::from(&HEADER[..]);
+        buf.put_slice(&[8, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0xaf, 0, 0x12, 0x10]);
+        buf.put_slice(tail);
+        buf
+    }
+
+    /// Decode all tags, returns the number of tags and the final result.
+    fn decode_all(codec: &mut FlvCodec, mut buf: BytesMut) -> (usize, Result<(), Error>) {
+        let mut count = 0;
+
+        loop {
+            match codec.decode_eof(&mut buf) {
+                Ok(Some(_)) => count += 1,
+                Ok(None) => return (count, Ok(())),
+                Err(err) => return (count, Err(err)),
+            }
+        }
+    }
+
+    fn is_unexpected_eof(res: Result<(), Error>) -> bool {
+        matches!(res, Err(Error::IoError(err)) if err.kind() == ErrorKind::UnexpectedEof)
+    }
+
+    #[test]
+    fn complete_stream() {
+        let (count, res) = decode_all(&mut FlvCodec::default(), stream(&[0, 0, 0, 15]));
+        assert_eq!(count, 1);
+        assert!(res.is_ok());
+
+        // the last `PreviousTagSize` is often missing
+        let (count, res) = decode_all(&mut FlvCodec::default(), stream(&[]));
+        assert_eq!(count, 1);
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn truncated_tag() {
+        let tail = [0, 0, 0, 15, 8, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0xaf];
+
+        let (count, res) = decode_all(&mut FlvCodec::default(), stream(&tail));
+        assert_eq!(count, 1);
+        assert!(is_unexpected_eof(res));
+
+        let mut codec = FlvCodec::default().lenient(true);
+        let (count, res) = decode_all(&mut codec, stream(&tail));
+        assert_eq!(count, 1);
+        assert!(res.is_ok());
+        assert_eq!(codec.take_skipped(), vec![(32..44)]);
+    }
+
+    #[test]
+    fn truncated_header() {
+        let (_, res) = decode_all(&mut FlvCodec::default(), BytesMut::from(&HEADER[..5]));
+        assert!(is_unexpected_eof(res));
+
+        let (_, res) = decode_all(&mut FlvCodec::default(), BytesMut::new());
+        assert!(is_unexpected_eof(res));
+
+        // data offset beyond the end of the stream
+        let mut buf = BytesMut::from(&HEADER[..]);
+        buf[8] = 20;
+        let (_, res) = decode_all(&mut FlvCodec::default(), buf);
+        assert!(is_unexpected_eof(res));
+    }
+}
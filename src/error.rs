@@ -9,6 +9,18 @@ pub enum Error<E = flowly::Void> {
     #[error("flv error: invalid signature")]
     InvalidSignature,
 
+    #[error("flv error: unsupported meta value type {0}")]
+    UnsupportedMetaValue(u8),
+
+    #[error("flv error: invalid avc configuration version {0}")]
+    InvalidAvcConfigVersion(u8),
+
+    #[error("flv error: invalid avc bit depth {0}")]
+    InvalidAvcBitDepth(u8),
+
+    #[error("flv error: unsupported codec {0}")]
+    UnsupportedCodec(flowly::Fourcc),
+
     #[error(transparent)]
     Other(E),
 }
@@ -17,7 +29,7 @@ impl<E: std::error::Error + Send + Sync + 'static> From<Error<E>> for std::io::E
     fn from(value: Error<E>) -> Self {
         match value {
             Error::IoError(error) => error,
-            err => std::io::Error::new(std::io::ErrorKind::Other, err),
+            err => std::io::Error::other(err),
         }
     }
 }
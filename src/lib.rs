@@ -1,20 +1,38 @@
-use std::pin::pin;
-
-use bytes::Bytes;
+use std::{
+    ops::Range,
+    pin::pin,
+    sync::{Arc, Mutex},
+};
+
+use bytes::{Bytes, BytesMut};
+use codec::FlvCodec;
 use error::Error;
 use flowly::{Fourcc, Frame, FrameFlags, Service};
-use futures::{Stream, TryStreamExt};
+use futures::{Stream, StreamExt, TryStreamExt};
 use header::FlvHeader;
-use parser::{FlvParser, Parser};
-use tag::{FlvTag, FlvTagHeader, FlvTagType};
-use tokio::io::{AsyncRead, AsyncReadExt, BufReader};
-use tokio_util::io::StreamReader;
-
+use tag::{
+    FlvTag, FlvTagData, FlvTagHeader, FlvTagType,
+    audio::AudioPacketType,
+    video::{
+        AvMultitrackType, VideoCommand, VideoFrameType, VideoPacketType, VideoTag, VideoTagBody,
+        VideoTagHeader, VideoTrack, color_info::VideoColorInfo,
+    },
+};
+use tokio::io::AsyncRead;
+use tokio_util::{codec::FramedRead, io::StreamReader};
+use writer::{FlvWriter, Writer};
+
+mod bitreader;
+mod bitwriter;
+
+pub mod codec;
 pub mod error;
 pub mod header;
 pub mod parser;
 pub mod reader;
+pub mod sync;
 pub mod tag;
+pub mod writer;
 
 pub const DEMUX_ALL_TYPES: u64 = -1i64 as u64;
 
@@ -30,6 +48,20 @@ pub struct FlvFrame {
     codec: Fourcc,
     params_count: u32,
     payload: Vec<Bytes>,
+    color_info: Option<Box<VideoColorInfo>>,
+    video_command: Option<VideoCommand>,
+}
+
+impl FlvFrame {
+    /// Video color info of the metadata frame.
+    pub fn color_info(&self) -> Option<&VideoColorInfo> {
+        self.color_info.as_deref()
+    }
+
+    /// Command of the video command frame, e.g. to flush decoders on seek boundaries.
+    pub fn video_command(&self) -> Option<VideoCommand> {
+        self.video_command
+    }
 }
 
 impl Frame for FlvFrame {
@@ -77,13 +109,45 @@ pub fn demux_flv_stream<R: AsyncRead>(
     reader: R,
     tag_types: u64,
 ) -> impl Stream<Item = Result<FlvTag, error::Error>> {
-    demux_flv_stream_inner(reader, tag_types)
+    FramedRead::new(reader, FlvCodec::new(tag_types))
+}
+
+/// Demux FLV tags of `tag_types` from `reader` resynchronizing after corrupt tags, see
+/// `FlvCodec` for the lenient mode.
+///
+/// The byte ranges skipped are taken with `decoder_mut().take_skipped()`.
+#[inline]
+pub fn demux_flv_stream_lenient<R: AsyncRead>(
+    reader: R,
+    tag_types: u64,
+) -> FramedRead<R, FlvCodec> {
+    FramedRead::new(reader, FlvCodec::new(tag_types).lenient(true))
+}
+
+/// Stream byte ranges skipped by lenient `FlvDemuxer`, shared with the demuxing stream.
+#[derive(Debug, Clone, Default)]
+pub struct SkippedRanges(Arc<Mutex<Vec<Range<u64>>>>);
+
+impl SkippedRanges {
+    /// Take the ranges skipped so far, the ranges preceding a frame are available once the
+    /// frame is yielded.
+    pub fn take(&self) -> Vec<Range<u64>> {
+        std::mem::take(&mut self.0.lock().unwrap())
+    }
+
+    fn extend(&self, ranges: Vec<Range<u64>>) {
+        if !ranges.is_empty() {
+            self.0.lock().unwrap().extend(ranges);
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct FlvDemuxer {
     flags_filter: FrameFlags,
     tracks_filter: u64,
+    lenient: bool,
+    skipped: SkippedRanges,
 }
 
 impl FlvDemuxer {
@@ -91,6 +155,123 @@ impl FlvDemuxer {
         Self {
             flags_filter,
             tracks_filter,
+            lenient: false,
+            skipped: SkippedRanges::default(),
+        }
+    }
+
+    /// Resynchronize after corrupt or truncated tags instead of failing, see `FlvCodec`.
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Handle to the stream byte ranges skipped in lenient mode.
+    pub fn skipped(&self) -> SkippedRanges {
+        self.skipped.clone()
+    }
+
+    #[inline]
+    fn is_track_enabled(&self, track_id: u8) -> bool {
+        track_id >= 64 || (self.tracks_filter >> track_id) & 1 > 0
+    }
+
+    /// Convert FLV tag into frames of the enabled tracks.
+    fn tag_frames(&self, tag: FlvTag) -> Vec<FlvFrame> {
+        let dts = tag.header.timestamp as u64 * 1000;
+
+        match tag.data {
+            FlvTagData::Video(vtag) => {
+                let dts = dts + vtag.header.dts_offset_ns as u64 / 1000;
+
+                // command frames have no tracks, delivered as metadata frame of track 0
+                if let Some(command) = vtag.header.video_command {
+                    if !self.flags_filter.contains(FrameFlags::METADATA_STREAM)
+                        || !self.is_track_enabled(0)
+                    {
+                        return Vec::new();
+                    }
+
+                    return vec![FlvFrame {
+                        dts,
+                        track_id: 0,
+                        flags: FrameFlags::METADATA_STREAM,
+                        pts_offset: 0,
+                        codec: vtag.header.fourcc,
+                        params_count: 0,
+                        payload: Vec::new(),
+                        color_info: None,
+                        video_command: Some(command),
+                    }];
+                }
+
+                vtag.tracks
+                    .into_iter()
+                    .filter(|track| self.is_track_enabled(track.track_id))
+                    .filter_map(|track| {
+                        let mut flags = match track.pkt_type {
+                            VideoPacketType::SequenceStart
+                            | VideoPacketType::CodedFrames
+                            | VideoPacketType::CodedFramesX => FrameFlags::VIDEO_STREAM,
+
+                            // color info is delivered as metadata frame of the track
+                            VideoPacketType::Metadata => FrameFlags::METADATA_STREAM,
+                            _ => return None,
+                        };
+
+                        if !self.flags_filter.intersects(flags) {
+                            return None;
+                        }
+
+                        flags.set(
+                            FrameFlags::KEYFRAME,
+                            track.frame_type == VideoFrameType::Key,
+                        );
+                        flags.set(FrameFlags::HAS_PARAMS, track.body.param_count > 0);
+
+                        Some(FlvFrame {
+                            dts,
+                            track_id: track.track_id as _,
+                            flags,
+                            pts_offset: track.body.pts_offset * 1000,
+                            codec: track.fourcc,
+                            params_count: track.body.param_count,
+                            payload: track.body.nalus,
+                            color_info: track.body.color_info.map(Box::new),
+                            video_command: None,
+                        })
+                    })
+                    .collect()
+            }
+            FlvTagData::Audio(atag) => {
+                let header = atag.header;
+                let (params_count, flags) = match header.pkt_type {
+                    // AudioSpecificConfig of AAC or codec specific sequence header
+                    AudioPacketType::SequenceStart => (1, FrameFlags::HAS_PARAMS),
+                    AudioPacketType::CodedFrames => (0, FrameFlags::empty()),
+                    _ => return Vec::new(),
+                };
+
+                // every audio frame can be decoded independently
+                let flags = flags | FrameFlags::AUDIO_STREAM | FrameFlags::KEYFRAME;
+
+                atag.tracks
+                    .into_iter()
+                    .filter(|track| self.is_track_enabled(track.track_id))
+                    .map(|track| FlvFrame {
+                        dts: dts + header.dts_offset_ns as u64 / 1000,
+                        track_id: track.track_id as _,
+                        flags,
+                        pts_offset: 0,
+                        codec: track.fourcc,
+                        params_count,
+                        payload: vec![track.body.data],
+                        color_info: None,
+                        video_command: None,
+                    })
+                    .collect()
+            }
+            FlvTagData::Meta(_) | FlvTagData::OnMetaData(_) | FlvTagData::Unknown => Vec::new(),
         }
     }
 }
@@ -100,6 +281,8 @@ impl Default for FlvDemuxer {
         Self {
             flags_filter: FrameFlags::VIDEO_STREAM,
             tracks_filter: !0,
+            lenient: false,
+            skipped: SkippedRanges::default(),
         }
     }
 }
@@ -119,7 +302,11 @@ impl<F: AsRef<[u8]> + Send + 'static, E: std::error::Error + Send + Sync + 'stat
             tag_type_filter |= 0b1 << u8::from(FlvTagType::Audio);
         }
 
-        if self.flags_filter.contains(FrameFlags::VIDEO_STREAM) {
+        // video metadata frames are carried by video tags
+        if self
+            .flags_filter
+            .intersects(FrameFlags::VIDEO_STREAM | FrameFlags::METADATA_STREAM)
+        {
             tag_type_filter |= 0b1 << u8::from(FlvTagType::Video);
         }
 
@@ -127,82 +314,621 @@ impl<F: AsRef<[u8]> + Send + 'static, E: std::error::Error + Send + Sync + 'stat
             tag_type_filter |= 0b1 << u8::from(FlvTagType::Metadata);
         }
 
-        let reader = StreamReader::new(input.map_ok(std::io::Cursor::new).map_err(Error::Other));
+        let reader = StreamReader::new(Box::pin(
+            input.map_ok(std::io::Cursor::new).map_err(Error::Other),
+        ));
 
-        demux_flv_stream_inner(reader, tag_type_filter).try_filter_map(move |tag| async move {
-            Ok(match tag.data {
-                tag::FlvTagData::Video(vtag) => {
-                    if vtag.track_id < 64 && (self.tracks_filter << vtag.track_id) == 0 {
-                        None
-                    } else {
-                        Some(FlvFrame {
-                            dts: tag.header.timestamp as u64 * 1000,
-                            track_id: vtag.track_id as _,
-                            flags: FrameFlags::empty(),
-                            pts_offset: vtag.body.pts_offset * 1000,
-                            codec: vtag.header.fourcc,
-                            params_count: vtag.body.param_count,
-                            payload: vtag.body.nalus,
-                        })
+        let codec = FlvCodec::new(tag_type_filter).lenient(self.lenient);
+        let mut framed = FramedRead::new(reader, codec);
+
+        async_stream::stream! {
+            while let Some(tag) = framed.next().await {
+                self.skipped.extend(framed.decoder_mut().take_skipped());
+
+                match tag {
+                    Ok(tag) => {
+                        for frame in self.tag_frames(tag) {
+                            yield Ok(frame);
+                        }
                     }
+                    Err(err) => yield Err(err),
                 }
-                tag::FlvTagData::Audio(_) => None,
-                tag::FlvTagData::Meta(_) => None,
-                tag::FlvTagData::Unknown => None,
-            })
-        })
+            }
+
+            // ranges skipped at the end of the stream
+            self.skipped.extend(framed.decoder_mut().take_skipped());
+        }
     }
 }
 
-fn demux_flv_stream_inner<E, R: AsyncRead>(
-    reader: R,
-    tag_types: u64,
-) -> impl Stream<Item = Result<FlvTag, error::Error<E>>> {
-    async_stream::stream! {
-        let mut buff = vec![0u8; 1024];
-        let mut reader = pin!(BufReader::new(reader));
-        let mut parser = FlvParser::default();
-
-        // reading flv header
-        reader.read_exact(&mut buff[0..9]).await?;
-        let header: FlvHeader = parser.parse(&mut &buff[0..9])?;
-
-        // skipping offset if present
-        if header.remaining > 0 {
-            if buff.len() < header.remaining as usize {
-                buff.resize(header.remaining as usize, 0);
-            }
+/// Muxer of video frames into FLV stream.
+///
+/// Only AVC and HEVC video is supported, the header announces video without audio and
+/// frames of other codecs are reported with `Error::UnsupportedCodec` and dropped.
+///
+/// The sequence header is written from the parameters of the first frame carrying them and
+/// again each time they change. Frames preceding the first parameters are dropped silently as
+/// the decoder can not start without them.
+#[derive(Debug, Default)]
+pub struct FlvMuxer {
+    writer: FlvWriter,
+}
+
+impl FlvMuxer {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-            reader.read_exact(&mut buff[0..header.remaining as usize]).await?;
+    fn video_tag(frame: &impl Frame, pkt_type: VideoPacketType, nalus: Vec<Bytes>) -> FlvTag {
+        let is_sequence_start = pkt_type == VideoPacketType::SequenceStart;
+        let codec = frame.codec();
+        let frame_type = if is_sequence_start || frame.is_keyframe() {
+            VideoFrameType::Key
+        } else {
+            VideoFrameType::Inter
+        };
+
+        // HEVC is carried with Enhanced RTMP header, AVC uses legacy one
+        let enhanced = codec != Fourcc::VIDEO_AVC;
+
+        FlvTag {
+            header: FlvTagHeader {
+                tag_type: FlvTagType::Video,
+                data_size: 0,
+                timestamp: (frame.dts() / 1000) as u32,
+                stream_id: 0,
+            },
+            data: FlvTagData::Video(VideoTag {
+                header: VideoTagHeader {
+                    frame_type,
+                    pkt_type,
+                    multitrack: false,
+                    fourcc: codec,
+                    has_body: true,
+                    multitrack_type: AvMultitrackType::OneTrack,
+
+                    // sub-millisecond part of the timestamp, legacy header has no room for it
+                    dts_offset_ns: if enhanced {
+                        (frame.dts() % 1000) as u32 * 1000
+                    } else {
+                        0
+                    },
+                    video_command: None,
+                    enhanced,
+                },
+                tracks: vec![VideoTrack {
+                    track_id: 0,
+                    fourcc: codec,
+                    pkt_type,
+                    frame_type,
+                    body: VideoTagBody {
+                        pts_offset: if is_sequence_start {
+                            0
+                        } else {
+                            ((frame.pts() - frame.dts() as i64) / 1000) as i32
+                        },
+                        param_count: if is_sequence_start {
+                            nalus.len() as u32
+                        } else {
+                            0
+                        },
+                        nalus,
+                        color_info: None,
+                        config: None,
+                    },
+                }],
+            }),
         }
+    }
+}
+
+impl<F: Frame + Send + 'static, E: std::error::Error + Send + Sync + 'static> Service<Result<F, E>>
+    for FlvMuxer
+{
+    type Out = Result<Bytes, Error<E>>;
 
-        while let Ok(_) = reader.read_u32().await {
-            match reader.read_exact(&mut buff[0..11]).await {
-                Ok(_) => (),
-                Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => break,
-                Err(err) => yield Err(err.into())
+    fn handle(
+        mut self,
+        input: impl Stream<Item = Result<F, E>> + Send,
+    ) -> impl Stream<Item = Self::Out> + Send {
+        async_stream::stream! {
+            let mut input = pin!(input);
+            let mut buf = BytesMut::new();
+            let mut params: Vec<Bytes> = Vec::new();
+
+            let header = FlvHeader {
+                version: 1,
+                is_audio_present: false,
+                is_video_present: true,
+                remaining: 0,
             };
 
-            let header: FlvTagHeader = parser.parse(&mut &buff[0..11])?;
-            if buff.len() < header.data_size as usize {
-                buff.resize(header.data_size as usize, 0);
-            }
+            Writer::<E, _>::write(&mut self.writer, &mut buf, &header)?;
+            yield Ok(buf.split().freeze());
 
-            match reader.read_exact(&mut buff[0..header.data_size as usize]).await {
-                Ok(_) => (),
-                Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => break,
-                Err(err) => yield Err(err.into())
-            }
+            while let Some(frame) = input.next().await {
+                let frame = match frame {
+                    Ok(frame) => frame,
+                    Err(err) => {
+                        yield Err(Error::Other(err));
+                        continue;
+                    }
+                };
 
-            if tag_types & (1u64 << u8::from(header.tag_type)) > 0 {
-                let mut tag_data = Bytes::from(buff[0..header.data_size as usize].to_vec());
+                if !matches!(frame.codec(), Fourcc::VIDEO_AVC | Fourcc::VIDEO_HEVC) {
+                    yield Err(Error::UnsupportedCodec(frame.codec()));
+                    continue;
+                }
 
-                yield Ok(FlvTag {
-                    data: parser.parse_flv_data(&mut tag_data, header.tag_type)?,
-                    header,
-                });
+                // sending sequence header each time the parameters are changed
+                if frame.params().next().is_some()
+                    && !frame.params().eq(params.iter().map(|x| x.as_ref()))
+                {
+                    params = frame.params().map(Bytes::copy_from_slice).collect();
+
+                    let tag = Self::video_tag(&frame, VideoPacketType::SequenceStart, params.clone());
+                    Writer::<E, _>::write(&mut self.writer, &mut buf, &tag)?;
+                }
+
+                // decoder can not start without parameters
+                if params.is_empty() {
+                    continue;
+                }
+
+                let nalus = frame.units().map(Bytes::copy_from_slice).collect();
+                let tag = Self::video_tag(&frame, VideoPacketType::CodedFrames, nalus);
+                Writer::<E, _>::write(&mut self.writer, &mut buf, &tag)?;
+
+                yield Ok(buf.split().freeze());
             }
         }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{BufMut, Bytes, BytesMut};
+    use flowly::{Fourcc, Frame, FrameFlags, Service};
+    use futures::{StreamExt, TryStreamExt};
+
+    use super::{DEMUX_ALL_TYPES, FlvDemuxer, FlvFrame, FlvMuxer, demux_flv_stream_lenient};
+    use crate::{
+        parser::{FlvParser, Parser},
+        tag::video::{VideoCommand, VideoFrameType, VideoTagHeader},
+    };
+
+    fn put_tag(buf: &mut BytesMut, tag_type: u8, timestamp: u32, data: &[u8]) {
+        buf.put_u8(tag_type);
+        buf.put_uint(data.len() as u64, 3);
+        buf.put_uint(timestamp as u64, 3);
+        buf.put_u8(0);
+        buf.put_uint(0, 3);
+        buf.put_slice(data);
+        buf.put_u32(data.len() as u32 + 11);
+    }
+
+    async fn demux(demuxer: FlvDemuxer, file: Bytes) -> Vec<FlvFrame> {
+        let input = futures::stream::iter([Ok::<_, std::io::Error>(file)]);
+        demuxer.handle(input).try_collect().await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn demuxer_audio_frames() {
+        let mut file = BytesMut::new();
+        file.put_slice(&[b'F', b'L', b'V', 1, 0b100, 0, 0, 0, 9, 0, 0, 0, 0]);
+        put_tag(&mut file, 8, 0, &[0xaf, 0, 0x12, 0x10]);
+        put_tag(&mut file, 8, 23, &[0xaf, 1, 0x21, 0x10, 0x04]);
+
+        let mut multitrack = BytesMut::new();
+        multitrack.put_slice(&[0x90 | 5, (1 << 4) | 1]);
+        multitrack.put_slice(b"Opus");
+        multitrack.put_slice(&[0, 0, 0, 1, 0xaa, 1, 0, 0, 1, 0xbb]);
+        put_tag(&mut file, 8, 46, &multitrack);
+        let file = file.freeze();
+
+        let frames = demux(FlvDemuxer::new(FrameFlags::AUDIO_STREAM, !0), file.clone()).await;
+        assert_eq!(frames.len(), 4);
+
+        assert_eq!(frames[0].codec(), Fourcc::AUDIO_AAC);
+        assert_eq!(frames[0].dts(), 0);
+        assert!(frames[0].params().eq([[0x12, 0x10].as_slice()]));
+        assert_eq!(frames[0].units().count(), 0);
+
+        assert_eq!(frames[1].dts(), 23_000);
+        assert_eq!(frames[1].params().count(), 0);
+        assert!(frames[1].units().eq([[0x21, 0x10, 0x04].as_slice()]));
+
+        for (frame, (track, data)) in frames[2..].iter().zip([(0, 0xaa), (1, 0xbb)]) {
+            assert_eq!(frame.codec(), Fourcc::AUDIO_OPUS);
+            assert_eq!(frame.track(), track);
+            assert_eq!(frame.dts(), 46_000);
+            assert!(frame.units().eq([[data].as_slice()]));
+        }
+
+        let frames = demux(FlvDemuxer::new(FrameFlags::AUDIO_STREAM, 0b1), file.clone()).await;
+        assert_eq!(frames.len(), 3);
+        assert!(frames.iter().all(|frame| frame.track() == 0));
+
+        assert!(demux(FlvDemuxer::default(), file).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn demuxer_frame_flags() {
+        let mut file = BytesMut::new();
+        file.put_slice(&[b'F', b'L', b'V', 1, 0b101, 0, 0, 0, 9, 0, 0, 0, 0]);
+
+        // AVC sequence header with one SPS and one PPS, key frame and inter frame
+        put_tag(
+            &mut file,
+            9,
+            0,
+            &[
+                0x17, 0, 0, 0, 0, 1, 0x42, 0, 0x1e, 0xff, 0xe1, 0, 4, 0x67, 0x42, 0, 0x1e, 1, 0, 2,
+                0x68, 0xce,
+            ],
+        );
+        put_tag(
+            &mut file,
+            9,
+            0,
+            &[0x17, 1, 0, 0, 40, 0, 0, 0, 2, 0x65, 0x88],
+        );
+        put_tag(
+            &mut file,
+            9,
+            40,
+            &[0x27, 1, 0, 0, 0, 0, 0, 0, 2, 0x41, 0x9a],
+        );
+        put_tag(&mut file, 8, 40, &[0xaf, 0, 0x12, 0x10]);
+        put_tag(&mut file, 8, 40, &[0xaf, 1, 0x21, 0x10]);
+
+        let flags = FrameFlags::VIDEO_STREAM | FrameFlags::AUDIO_STREAM;
+        let frames = demux(FlvDemuxer::new(flags, !0), file.freeze()).await;
+
+        let video = FrameFlags::VIDEO_STREAM;
+        let audio = FrameFlags::AUDIO_STREAM | FrameFlags::KEYFRAME;
+        let flags: Vec<_> = frames.iter().map(|frame| frame.flags()).collect();
+        assert_eq!(
+            flags,
+            [
+                video | FrameFlags::KEYFRAME | FrameFlags::HAS_PARAMS,
+                video | FrameFlags::KEYFRAME,
+                video,
+                audio | FrameFlags::HAS_PARAMS,
+                audio,
+            ]
+        );
+
+        assert_eq!(frames[0].params().count(), 2);
+        assert_eq!(frames[1].pts(), 40_000);
+        assert_eq!(frames[2].dts(), 40_000);
+    }
+
+    #[test]
+    fn video_frame_type() {
+        for (byte, frame_type) in [
+            (0x17, VideoFrameType::Key),
+            (0x27, VideoFrameType::Inter),
+            (0x57, VideoFrameType::Command),
+            (0x80 | (2 << 4) | 1, VideoFrameType::Inter),
+        ] {
+            let mut data = Bytes::from(vec![byte, b'a', b'v', b'c', b'1']);
+            let header: VideoTagHeader =
+                Parser::<flowly::Void, _>::parse(&mut FlvParser::default(), &mut data).unwrap();
+
+            assert_eq!(header.frame_type, frame_type, "{byte:#x}");
+        }
+    }
+
+    #[tokio::test]
+    async fn demuxer_mod_ex_timestamp() {
+        let mut file = BytesMut::new();
+        file.put_slice(&[b'F', b'L', b'V', 1, 0b1, 0, 0, 0, 9, 0, 0, 0, 0]);
+
+        let mut video = BytesMut::new();
+        video.put_slice(&[0x80 | (1 << 4) | 7, 2]);
+        video.put_uint(500_000, 3);
+        video.put_u8(1);
+        video.put_slice(b"vp09");
+        video.put_slice(&[0x82, 0x49, 0x83, 0x42, 0x00, 0x4f, 0xf0, 0x2c, 0xf0]);
+        put_tag(&mut file, 9, 40, &video);
+
+        let frames = demux(FlvDemuxer::default(), file.freeze()).await;
+
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].dts(), 40_500);
+        assert!(frames[0].flags().contains(FrameFlags::KEYFRAME));
+    }
+
+    #[tokio::test]
+    async fn demuxer_command_frames() {
+        let mut file = BytesMut::new();
+        file.put_slice(&[b'F', b'L', b'V', 1, 0b1, 0, 0, 0, 9, 0, 0, 0, 0]);
+        put_tag(&mut file, 9, 0, &[(5 << 4) | 7, 0]);
+
+        let mut video = BytesMut::new();
+        video.put_slice(&[0x80 | (1 << 4) | 1]);
+        video.put_slice(b"vp09");
+        video.put_slice(&[0x82, 0x49, 0x83, 0x42, 0x00, 0x4f, 0xf0, 0x2c, 0xf0]);
+        put_tag(&mut file, 9, 40, &video);
+        let file = file.freeze();
+
+        let frames = demux(FlvDemuxer::default(), file.clone()).await;
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].video_command(), None);
+
+        let flags = FrameFlags::VIDEO_STREAM | FrameFlags::METADATA_STREAM;
+        let frames = demux(FlvDemuxer::new(flags, !0), file.clone()).await;
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].video_command(), Some(VideoCommand::StartSeek));
+        assert!(frames[0].flags().contains(FrameFlags::METADATA_STREAM));
+        assert_eq!(frames[0].track(), 0);
+        assert!(frames[0].units().next().is_none());
+
+        let frames = demux(FlvDemuxer::new(flags, !1), file).await;
+        assert!(frames.iter().all(|f| f.video_command().is_none()));
+    }
+
+    /// AAC stream with 5 bytes of garbage at 32..37 between the first two tags.
+    fn corrupted() -> Bytes {
+        let mut file = BytesMut::new();
+        file.put_slice(&[b'F', b'L', b'V', 1, 0b100, 0, 0, 0, 9, 0, 0, 0, 0]);
+        put_tag(&mut file, 8, 0, &[0xaf, 0, 0x12, 0x10]);
+        file.put_slice(&[0x12, 0x34, 0x56, 0x78, 0x9a]);
+        put_tag(&mut file, 8, 23, &[0xaf, 1, 0x21, 0x10, 0x04, 0x60]);
+        put_tag(&mut file, 8, 46, &[0xaf, 1, 0x21, 0x10, 0x05, 0x20]);
+
+        file.freeze()
+    }
+
+    #[tokio::test]
+    async fn demux_flv_stream_lenient_skips() {
+        let file = corrupted();
 
+        let mut stream = demux_flv_stream_lenient(&file[..], DEMUX_ALL_TYPES);
+        let mut timestamps = Vec::new();
+
+        while let Some(tag) = stream.try_next().await.unwrap() {
+            timestamps.push(tag.header.timestamp);
+        }
+
+        assert_eq!(timestamps, [0, 23, 46]);
+        assert_eq!(stream.decoder_mut().take_skipped(), vec![(32..37)]);
+    }
+
+    #[tokio::test]
+    async fn demuxer_reports_skipped() {
+        let demuxer = FlvDemuxer::new(FrameFlags::AUDIO_STREAM, !0).lenient(true);
+        let skipped = demuxer.skipped();
+
+        let input = futures::stream::iter([Ok::<_, std::io::Error>(corrupted())]);
+        let mut frames = std::pin::pin!(demuxer.handle(input));
+
+        let frame = frames.next().await.unwrap().unwrap();
+        assert_eq!(frame.dts(), 0);
+        assert!(skipped.take().is_empty());
+
+        let frame = frames.next().await.unwrap().unwrap();
+        assert_eq!(frame.dts(), 23_000);
+        assert_eq!(skipped.take(), vec![(32..37)]);
+
+        assert_eq!(frames.count().await, 1);
+        assert!(skipped.take().is_empty());
+    }
+
+    /// AVC parameter sets of 1080p and 720p streams.
+    const AVC_SPS_1080P: &[u8] = &[
+        0x67, 0x64, 0x00, 0x2a, 0xac, 0xd9, 0x40, 0x78, 0x02, 0x27, 0xe5, 0xc0, 0x44, 0x00, 0x00,
+        0x03, 0x00, 0x04, 0x00, 0x00, 0x03, 0x00, 0x78, 0x3c, 0x60, 0xc6, 0x58,
+    ];
+    const AVC_SPS_720P: &[u8] = &[
+        0x67, 0x64, 0x00, 0x1f, 0xac, 0xd9, 0x40, 0x50, 0x05, 0xbb, 0x01, 0x10, 0x00, 0x00, 0x03,
+        0x00, 0x10, 0x00, 0x00, 0x03, 0x03, 0x20, 0xf1, 0x83, 0x19, 0x60, 0x00,
+    ];
+    const AVC_PPS: &[u8] = &[0x68, 0xeb, 0xe3, 0xcb, 0x22, 0xc0];
+
+    /// HEVC parameter sets of 720p stream with 8 and 10 bit SPS.
+    const HEVC_VPS: &[u8] = &[
+        0x40, 0x01, 0x0c, 0x01, 0xff, 0xff, 0x01, 0x60, 0x00, 0x00, 0x03, 0x00, 0xb0, 0x00, 0x00,
+        0x03, 0x00, 0x00, 0x03, 0x00, 0x5d, 0xac, 0x09,
+    ];
+    const HEVC_SPS: &[u8] = &[
+        0x42, 0x01, 0x01, 0x01, 0x60, 0x00, 0x00, 0x03, 0x00, 0xb0, 0x00, 0x00, 0x03, 0x00, 0x00,
+        0x03, 0x00, 0x5d, 0xa0, 0x02, 0x80, 0x80, 0x2d, 0x16, 0x59, 0x59, 0xa4, 0x93, 0x2b, 0x9a,
+        0x80, 0x80, 0x80, 0x82, 0x00, 0x00, 0x03, 0x00, 0x02, 0x00, 0x00, 0x03, 0x00, 0x32, 0x10,
+    ];
+    const HEVC_SPS_10BIT: &[u8] = &[
+        0x42, 0x01, 0x01, 0x01, 0x60, 0x00, 0x00, 0x03, 0x00, 0xb0, 0x00, 0x00, 0x03, 0x00, 0x00,
+        0x03, 0x00, 0x5d, 0xa0, 0x02, 0x80, 0x80, 0x2d, 0x13, 0x65, 0x95, 0x9a, 0x49, 0x32, 0xb9,
+        0xa8, 0x08, 0x08, 0x08, 0x20, 0x00, 0x00, 0x03, 0x00, 0x20, 0x00, 0x00, 0x03, 0x03, 0x21,
+    ];
+    const HEVC_PPS: &[u8] = &[0x44, 0x01, 0xc1, 0x72, 0xb4, 0x62, 0x40];
+
+    fn video_frame(
+        codec: Fourcc,
+        dts: u64,
+        pts_offset: i32,
+        params: &[&'static [u8]],
+        units: &[&'static [u8]],
+    ) -> FlvFrame {
+        let mut flags = FrameFlags::VIDEO_STREAM;
+        flags.set(FrameFlags::KEYFRAME, !params.is_empty());
+        flags.set(FrameFlags::HAS_PARAMS, !params.is_empty());
+
+        FlvFrame {
+            dts,
+            track_id: 0,
+            flags,
+            pts_offset,
+            codec,
+            params_count: params.len() as u32,
+            payload: params
+                .iter()
+                .chain(units)
+                .copied()
+                .map(Bytes::from_static)
+                .collect(),
+            color_info: None,
+            video_command: None,
+        }
+    }
+
+    /// Mux the frames and demux the produced stream.
+    async fn mux_demux(frames: Vec<FlvFrame>) -> Vec<FlvFrame> {
+        let input = futures::stream::iter(frames.into_iter().map(Ok::<_, std::io::Error>));
+        let chunks: Vec<Bytes> = FlvMuxer::new().handle(input).try_collect().await.unwrap();
+
+        demux(FlvDemuxer::default(), chunks.concat().into()).await
+    }
+
+    /// `(dts, pts, keyframe, params, units)` of a frame.
+    type Summary<'a> = (u64, i64, bool, Vec<&'a [u8]>, Vec<&'a [u8]>);
+
+    fn summary(frames: &[FlvFrame]) -> Vec<Summary<'_>> {
+        frames
+            .iter()
+            .map(|x| {
+                let params = x.params().collect();
+                let units = x.units().collect();
+
+                (x.dts(), x.pts(), x.is_keyframe(), params, units)
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn muxer_avc() {
+        let (idr, p): (&[u8], &[u8]) = (&[0x65, 0x88, 0x84], &[0x41, 0x9a, 0x02]);
+        let params = [AVC_SPS_1080P, AVC_PPS];
+        let changed = [AVC_SPS_720P, AVC_PPS];
+
+        let frames = mux_demux(vec![
+            // dropped, there are no parameters yet
+            video_frame(Fourcc::VIDEO_AVC, 0, 0, &[], &[p]),
+            video_frame(Fourcc::VIDEO_AVC, 33_000, 66_000, &params, &[idr]),
+            video_frame(Fourcc::VIDEO_AVC, 66_000, -33_000, &[], &[p]),
+            video_frame(Fourcc::VIDEO_AVC, 100_000, 33_000, &changed, &[idr]),
+            video_frame(Fourcc::VIDEO_AVC, 133_000, 0, &changed, &[idr]),
+        ])
+        .await;
+
+        assert_eq!(
+            summary(&frames),
+            [
+                (33_000, 33_000, true, params.to_vec(), vec![]),
+                (33_000, 99_000, true, vec![], vec![idr]),
+                (66_000, 33_000, false, vec![], vec![p]),
+                (100_000, 100_000, true, changed.to_vec(), vec![]),
+                (100_000, 133_000, true, vec![], vec![idr]),
+                (133_000, 133_000, true, vec![], vec![idr]),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn muxer_hevc() {
+        let (idr, trail): (&[u8], &[u8]) = (&[0x26, 0x01, 0xaf], &[0x02, 0x01, 0xd0]);
+        let params = [HEVC_VPS, HEVC_SPS, HEVC_PPS];
+        let changed = [HEVC_VPS, HEVC_SPS_10BIT, HEVC_PPS];
+
+        // sub-millisecond part of dts is kept by Enhanced RTMP header
+        let frames = mux_demux(vec![
+            video_frame(Fourcc::VIDEO_HEVC, 0, 0, &[], &[trail]),
+            video_frame(Fourcc::VIDEO_HEVC, 33_367, 66_000, &params, &[idr]),
+            video_frame(Fourcc::VIDEO_HEVC, 66_733, -33_000, &[], &[trail]),
+            video_frame(Fourcc::VIDEO_HEVC, 100_100, 0, &changed, &[idr]),
+        ])
+        .await;
+
+        assert_eq!(
+            summary(&frames),
+            [
+                (33_367, 33_367, true, params.to_vec(), vec![]),
+                (33_367, 99_367, true, vec![], vec![idr]),
+                (66_733, 33_733, false, vec![], vec![trail]),
+                (100_100, 100_100, true, changed.to_vec(), vec![]),
+                (100_100, 100_100, true, vec![], vec![idr]),
+            ]
+        );
+        assert!(frames.iter().all(|x| x.codec() == Fourcc::VIDEO_HEVC));
+    }
+
+    #[tokio::test]
+    async fn muxer_unsupported_codec() {
+        let input = futures::stream::iter([Ok::<_, std::io::Error>(video_frame(
+            Fourcc::VIDEO_VP9,
+            0,
+            0,
+            &[],
+            &[&[0x82, 0x49, 0x83]],
+        ))]);
+        let out: Vec<_> = FlvMuxer::new().handle(input).collect().await;
+
+        assert_eq!(out.len(), 2);
+        assert!(matches!(
+            out[1],
+            Err(crate::error::Error::UnsupportedCodec(Fourcc::VIDEO_VP9))
+        ));
+    }
+
+    #[tokio::test]
+    async fn demuxer_invalid_avc_record() {
+        let mut record = vec![1, 0x64, 0x00, 0x2a, 0xff, 0xe1];
+        record.put_u16(AVC_SPS_1080P.len() as u16);
+        record.put_slice(AVC_SPS_1080P);
+        record.put_u8(1);
+        record.put_u16(AVC_PPS.len() as u16);
+        record.put_slice(AVC_PPS);
+
+        let mut file = BytesMut::new();
+        file.put_slice(&[b'F', b'L', b'V', 1, 0b001, 0, 0, 0, 9, 0, 0, 0, 0]);
+
+        // unsupported version and bit depth of 15 in the high profile extension
+        let mut invalid_version = record.clone();
+        invalid_version[0] = 2;
+        let mut invalid_bit_depth = record.clone();
+        invalid_bit_depth.extend_from_slice(&[0xfd, 0xff, 0xf8, 0x00]);
+
+        for (timestamp, record) in [(0, invalid_version), (0, invalid_bit_depth), (33, record)] {
+            put_tag(
+                &mut file,
+                9,
+                timestamp,
+                &[&[0x17, 0, 0, 0, 0][..], &record].concat(),
+            );
+        }
+        put_tag(
+            &mut file,
+            9,
+            33,
+            &[0x17, 1, 0, 0, 0, 0, 0, 0, 3, 0x65, 0x88, 0x84],
+        );
+
+        let frames = demux(FlvDemuxer::default(), file.freeze()).await;
+        let frames: Vec<_> = frames
+            .iter()
+            .map(|x| {
+                let params: Vec<_> = x.params().collect();
+                let units: Vec<_> = x.units().collect();
+
+                (
+                    x.dts(),
+                    x.flags().contains(FrameFlags::HAS_PARAMS),
+                    params,
+                    units,
+                )
+            })
+            .collect();
+
+        let idr: &[u8] = &[0x65, 0x88, 0x84];
+        assert_eq!(
+            frames,
+            [
+                (0, false, vec![], vec![]),
+                (0, false, vec![], vec![]),
+                (33_000, true, vec![AVC_SPS_1080P, AVC_PPS], vec![]),
+                (33_000, false, vec![], vec![idr]),
+            ]
+        );
     }
 }
@@ -1,5 +1,13 @@
+use std::collections::HashMap;
+
 use crate::{
-    error::Error, header::FlvHeader, reader::FlvReader, tag::video::mpeg4_avc::Mpeg4AvcParser,
+    error::Error,
+    header::FlvHeader,
+    reader::FlvReader,
+    tag::{
+        audio::aac::AacParser,
+        video::{av1::Av1Parser, hevc::HevcParser, mpeg4_avc::Mpeg4AvcParser, vp9::Vp9Parser},
+    },
 };
 
 mod tag;
@@ -12,7 +20,16 @@ pub trait Parser<E, T> {
 
 #[derive(Debug, Default, Clone)]
 pub struct FlvParser {
-    mpeg4_avc_parser: Option<Mpeg4AvcParser>,
+    /// Video codec parsers by track id, the NALU length size is set per track.
+    mpeg4_avc_parser: HashMap<u8, Mpeg4AvcParser>,
+    hevc_parser: HashMap<u8, HevcParser>,
+    av1_parser: HashMap<u8, Av1Parser>,
+    vp9_parser: HashMap<u8, Vp9Parser>,
+    /// AAC parsers by track id keeping the last `AudioSpecificConfig` of the track.
+    aac_parser: HashMap<u8, AacParser>,
+
+    /// Nesting depth of the script data value being parsed.
+    meta_depth: usize,
 }
 
 impl<E> Parser<E, FlvHeader> for FlvParser {
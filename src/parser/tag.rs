@@ -6,7 +6,10 @@ mod video;
 use crate::{
     error::Error,
     reader::FlvReader,
-    tag::{FlvTag, FlvTagData, FlvTagHeader, FlvTagType},
+    tag::{
+        FlvTag, FlvTagData, FlvTagHeader, FlvTagType,
+        meta::{MetaDataValue, MetaTag, OnMetaData, on_metadata::ON_METADATA},
+    },
 };
 
 use super::{FlvParser, Parser};
@@ -53,7 +56,7 @@ impl<E> Parser<E, FlvTag> for FlvParser {
             data: match header.tag_type {
                 FlvTagType::Audio => FlvTagData::Audio(self.parse(reader)?),
                 FlvTagType::Video => FlvTagData::Video(self.parse(reader)?),
-                FlvTagType::Metadata => FlvTagData::Meta(self.parse(reader)?),
+                FlvTagType::Metadata => self.parse_meta_data(reader)?,
                 FlvTagType::Unknown(..) => FlvTagData::Unknown,
             },
         })
@@ -70,8 +73,25 @@ impl FlvParser {
         Ok(match tag_type {
             FlvTagType::Audio => FlvTagData::Audio(self.parse(reader)?),
             FlvTagType::Video => FlvTagData::Video(self.parse(reader)?),
-            FlvTagType::Metadata => FlvTagData::Meta(self.parse(reader)?),
+            FlvTagType::Metadata => self.parse_meta_data(reader)?,
             FlvTagType::Unknown(..) => FlvTagData::Unknown,
         })
     }
+
+    /// Parse script tag data, `onMetaData` is converted into `OnMetaData`.
+    fn parse_meta_data<E>(&mut self, reader: &mut impl FlvReader) -> Result<FlvTagData, Error<E>> {
+        let tag: MetaTag = self.parse(reader)?;
+
+        Ok(match tag.value {
+            value @ (MetaDataValue::Object(_) | MetaDataValue::ECMAArray(..))
+                if tag.name == ON_METADATA =>
+            {
+                let mut meta = OnMetaData::from_value(value).unwrap_or_default();
+                meta.wrapper = tag.wrapper;
+
+                FlvTagData::OnMetaData(Box::new(meta))
+            }
+            _ => FlvTagData::Meta(tag),
+        })
+    }
 }
@@ -1,9 +1,15 @@
+use flowly::Fourcc;
+
 use crate::{
     error::Error,
     parser::{FlvParser, Parser},
     reader::FlvReader,
-    tag::audio::{
-        AudioTag, AudioTagBody, AudioTagHeader, SoundFormat, SoundRate, SoundSize, SoundType,
+    tag::{
+        audio::{
+            AACPacketType, AudioChannelOrder, AudioMultichannelConfig, AudioPacketType, AudioTag,
+            AudioTagBody, AudioTagHeader, AudioTrack, SoundFormat, SoundRate, SoundSize, SoundType,
+        },
+        video::{AvMultitrackType, PacketExData},
     },
 };
 
@@ -11,25 +17,126 @@ impl<E> Parser<E, AudioTagHeader> for FlvParser {
     type Error = Error<E>;
 
     /// Parse audio tag data header.
+    ///
+    /// For legacy AAC the `AACPacketType` byte is consumed as well.
     fn parse(&mut self, reader: &mut impl FlvReader) -> Result<AudioTagHeader, Self::Error> {
         let header = reader.read_u8()?;
+        let sound_format = SoundFormat::from(header >> 4);
+
+        let mut fourcc = Fourcc::from(sound_format);
+        let mut multitrack = false;
+        let mut multitrack_type = AvMultitrackType::OneTrack;
+        let mut dts_offset_ns = 0;
+        let mut pkt_type = AudioPacketType::CodedFrames;
+
+        match sound_format {
+            SoundFormat::ExHeader => {
+                pkt_type = AudioPacketType::from(header & 0x0F);
+
+                while let AudioPacketType::ModEx = pkt_type {
+                    let ex_data: PacketExData<AudioPacketType> = self.parse(reader)?;
+
+                    dts_offset_ns = ex_data.dts_offset_ns;
+                    pkt_type = ex_data.pkt_type;
+                }
+
+                if pkt_type == AudioPacketType::Multitrack {
+                    multitrack = true;
+
+                    // Fetch AudioPacketType for all audio tracks in the audio message.
+                    // This fetch MUST not result in a AudioPacketType.Multitrack
+                    let byte = reader.read_u8()?;
+                    multitrack_type = AvMultitrackType::from(byte >> 4);
+                    pkt_type = AudioPacketType::from(byte & 0x0F);
+
+                    if multitrack_type != AvMultitrackType::ManyTracksManyCodecs {
+                        fourcc = Fourcc::from(reader.read_u32()?);
+                    }
+                } else {
+                    fourcc = Fourcc::from(reader.read_u32()?);
+                }
+            }
+            SoundFormat::AAC => {
+                pkt_type = match AACPacketType::from(reader.read_u8()?) {
+                    AACPacketType::SequenceHeader => AudioPacketType::SequenceStart,
+                    AACPacketType::Raw => AudioPacketType::CodedFrames,
+                };
+            }
+            _ => (),
+        }
 
         Ok(AudioTagHeader {
-            sound_format: SoundFormat::from(header >> 4),
+            sound_format,
             sound_rate: SoundRate::from((header >> 2) & 0b11),
             sound_size: SoundSize::from((header >> 1) & 1),
             sound_type: SoundType::from(header & 1),
+            pkt_type,
+            fourcc,
+            multitrack,
+            multitrack_type,
+            dts_offset_ns,
         })
     }
 }
 
-impl<E> Parser<E, AudioTagBody> for FlvParser {
-    type Error = Error<E>;
+impl FlvParser {
+    /// Parse audio tag data body of the `pkt_type` packet of `fourcc` codec on the track.
+    fn parse_audio_body<E>(
+        &mut self,
+        reader: &mut impl FlvReader,
+        track_id: u8,
+        fourcc: Fourcc,
+        pkt_type: AudioPacketType,
+    ) -> Result<AudioTagBody, Error<E>> {
+        let mut multichannel_config = None;
+        let mut aac_config = None;
+
+        if pkt_type == AudioPacketType::MultichannelConfig {
+            let order_type = reader.read_u8()?;
+            let channel_count = reader.read_u8()?;
+
+            let channel_order = match order_type {
+                0 => AudioChannelOrder::Unspecified,
+                1 => AudioChannelOrder::Native(reader.read_u32()?),
+                2 => AudioChannelOrder::Custom(
+                    reader.read_to_bytes(channel_count as usize)?.to_vec(),
+                ),
+                t => AudioChannelOrder::Unknown(t),
+            };
+
+            multichannel_config = Some(AudioMultichannelConfig {
+                channel_count,
+                channel_order,
+            });
+        }
+
+        let data = reader.read_to_end()?;
+
+        if fourcc == Fourcc::AUDIO_AAC {
+            let parser = self.aac_parser.entry(track_id).or_default();
+
+            aac_config = match pkt_type {
+                AudioPacketType::SequenceStart => {
+                    // a broken config should not fail the whole stream, frames pass through
+                    // without it
+                    match Parser::<flowly::Void, _>::parse(parser, &mut data.clone()) {
+                        Ok(config) => Some(config),
+                        Err(err) => {
+                            log::warn!("invalid aac sequence header: {}", err);
+                            parser.config = None;
+                            None
+                        }
+                    }
+                }
+                AudioPacketType::CodedFrames => parser.config,
+                _ => None,
+            };
+        }
 
-    /// Parse audio tag data body.
-    fn parse(&mut self, reader: &mut impl FlvReader) -> Result<AudioTagBody, Self::Error> {
         Ok(AudioTagBody {
-            data: reader.read_to_end()?,
+            data,
+            multichannel_config,
+            aac_config,
         })
     }
 }
@@ -39,8 +146,108 @@ impl<E> Parser<E, AudioTag> for FlvParser {
     /// Parse audio tag data.
     fn parse(&mut self, reader: &mut impl FlvReader) -> Result<AudioTag, Self::Error> {
         let header: AudioTagHeader = self.parse(reader)?;
-        let body = self.parse(reader)?;
+        let mut tracks = Vec::new();
+
+        if !header.multitrack {
+            tracks.push(AudioTrack {
+                track_id: 0,
+                fourcc: header.fourcc,
+                body: self.parse_audio_body(reader, 0, header.fourcc, header.pkt_type)?,
+            });
+
+            return Ok(AudioTag { header, tracks });
+        }
+
+        loop {
+            let mut fourcc = header.fourcc;
+
+            if header.multitrack_type == AvMultitrackType::ManyTracksManyCodecs {
+                // Each track has a codec assigned to it. Fetch the FOURCC for the next track.
+                fourcc = Fourcc::from(reader.read_u32()?);
+            }
+
+            let track_id = reader.read_u8()?;
+
+            let body = if header.multitrack_type != AvMultitrackType::OneTrack {
+                // The `sizeOfAudioTrack` specifies the size in bytes of the
+                // current track, the next track starts right after it.
+                let size = reader.read_u24()?;
+                let mut data = reader.read_to_bytes(size as usize)?;
+
+                self.parse_audio_body(&mut data, track_id, fourcc, header.pkt_type)?
+            } else {
+                self.parse_audio_body(reader, track_id, fourcc, header.pkt_type)?
+            };
+
+            tracks.push(AudioTrack {
+                track_id,
+                fourcc,
+                body,
+            });
+
+            if header.multitrack_type == AvMultitrackType::OneTrack || reader.available() == 0 {
+                break;
+            }
+        }
+
+        Ok(AudioTag { header, tracks })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use crate::{
+        parser::{FlvParser, Parser},
+        tag::audio::AudioTag,
+    };
+
+    fn parse(parser: &mut FlvParser, data: &'static [u8]) -> AudioTag {
+        Parser::<flowly::Void, _>::parse(parser, &mut Bytes::from_static(data)).unwrap()
+    }
+
+    #[test]
+    fn invalid_aac_sequence_header() {
+        let mut parser = FlvParser::default();
+
+        let tag = parse(&mut parser, &[0xaf, 0, 0x12, 0x10]);
+        let config = tag.tracks[0].body.aac_config.unwrap();
+        assert_eq!(config.output_sampling_frequency(), 44100);
+        assert_eq!(config.channel_count(), 2);
+
+        let tag = parse(&mut parser, &[0xaf, 0, 0x12]);
+        assert_eq!(tag.tracks[0].body.aac_config, None);
+        assert_eq!(tag.tracks[0].body.data, &[0x12][..]);
+
+        let tag = parse(&mut parser, &[0xaf, 1, 0x21, 0x10]);
+        assert_eq!(tag.tracks[0].body.aac_config, None);
+    }
+
+    #[test]
+    fn aac_config_per_track() {
+        let mut parser = FlvParser::default();
+
+        // two tracks of 44.1 and 48 kHz
+        let tag = parse(
+            &mut parser,
+            &[
+                0x95, 0x10, b'm', b'p', b'4', b'a', 0, 0, 0, 2, 0x12, 0x10, 1, 0, 0, 2, 0x11, 0x90,
+            ],
+        );
+        assert_eq!(tag.tracks.len(), 2);
 
-        Ok(AudioTag { header, body })
+        let tag = parse(
+            &mut parser,
+            &[
+                0x95, 0x11, b'm', b'p', b'4', b'a', 0, 0, 0, 2, 0x21, 0x10, 1, 0, 0, 2, 0x21, 0x10,
+            ],
+        );
+        let rates: Vec<_> = tag
+            .tracks
+            .iter()
+            .map(|x| x.body.aac_config.unwrap().output_sampling_frequency())
+            .collect();
+        assert_eq!(rates, [44100, 48000]);
     }
 }
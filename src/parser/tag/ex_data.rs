@@ -2,13 +2,13 @@ use crate::{
     error::Error,
     parser::{FlvParser, Parser},
     reader::FlvReader,
-    tag::video::{PacketExData, VideoPacketModExType, VideoPacketType},
+    tag::video::{PacketExData, VideoPacketModExType},
 };
 
-impl<E> Parser<E, PacketExData> for FlvParser {
+impl<E, T: From<u8>> Parser<E, PacketExData<T>> for FlvParser {
     type Error = Error<E>;
 
-    fn parse(&mut self, reader: &mut impl FlvReader) -> Result<PacketExData, Self::Error> {
+    fn parse(&mut self, reader: &mut impl FlvReader) -> Result<PacketExData<T>, Self::Error> {
         // Determine the size of the packet ModEx data (ranging from 1 to 256 bytes)
         let mut ex_size = reader.read_u8()? as u16 + 1;
 
@@ -23,9 +23,9 @@ impl<E> Parser<E, PacketExData> for FlvParser {
         let ex_hdr = reader.read_u8()?;
 
         // fetch the VideoPacketOptionType
-        let ex_type = VideoPacketModExType::from(ex_hdr & 0xf0);
+        let ex_type = VideoPacketModExType::from(ex_hdr >> 4);
 
-        // fetch videoPacketType
+        // fetch videoPacketType or audioPacketType
         let pkt_type = ex_hdr & 0x0f;
 
         let mut dts_offset_ns = 0;
@@ -47,7 +47,7 @@ impl<E> Parser<E, PacketExData> for FlvParser {
             if ex_size != 3 {
                 log::warn!("Invalid ModEx size for Type TimestampOffsetNano!");
             } else {
-                dts_offset_ns = (&mut ex_data).read_u24()?;
+                dts_offset_ns = ex_data.read_u24()?;
             }
         } else {
             log::info!("Unknown ModEx type: {:?}", ex_type);
@@ -56,7 +56,7 @@ impl<E> Parser<E, PacketExData> for FlvParser {
         Ok(PacketExData {
             dts_offset_ns,
             ex_data,
-            pkt_type: VideoPacketType::from(pkt_type),
+            pkt_type: T::from(pkt_type),
             ex_type,
         })
     }
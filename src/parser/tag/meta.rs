@@ -1,6 +1,5 @@
-use std::collections::HashMap;
-
 use bytes::Bytes;
+use indexmap::IndexMap;
 
 use crate::{
     error::Error,
@@ -10,6 +9,10 @@ use crate::{
 };
 
 const OBJECT_END_MARKER: [u8; 3] = [0x00, 0x00, 0x09];
+const SET_DATA_FRAME: &[u8] = b"@setDataFrame";
+
+/// Maximum nesting depth of objects and arrays.
+const MAX_META_DEPTH: usize = 64;
 
 impl<E> Parser<E, MetaTag> for FlvParser {
     type Error = Error<E>;
@@ -17,10 +20,21 @@ impl<E> Parser<E, MetaTag> for FlvParser {
     /// Parse script tag data.
     fn parse(&mut self, reader: &mut impl FlvReader) -> Result<MetaTag, Self::Error> {
         let _tag = reader.read_u8()?;
-        let name = self.parse_meta_string(reader)?;
+        let mut name = self.parse_meta_string(reader)?;
+        let mut wrapper = None;
+
+        // `@setDataFrame` wraps the actual name of the data frame (usually `onMetaData`)
+        if name == SET_DATA_FRAME && reader.available() > 0 && reader.peek(0..1)? == [2] {
+            let _tag = reader.read_u8()?;
+            wrapper = Some(std::mem::replace(
+                &mut name,
+                self.parse_meta_string(reader)?,
+            ));
+        }
 
         // AMF arguments or object properties.
         Ok(MetaTag {
+            wrapper,
             name,
             value: self.parse(reader)?,
         })
@@ -32,19 +46,24 @@ impl FlvParser {
     fn parse_meta_string<E>(&mut self, reader: &mut impl FlvReader) -> Result<Bytes, Error<E>> {
         let len = reader.read_u16()? as usize;
 
-        Ok(reader.read_to_bytes(len as usize)?)
+        Ok(reader.read_to_bytes(len)?)
     }
 
     fn parse_meta_object<E>(
         &mut self,
         reader: &mut impl FlvReader,
-    ) -> Result<HashMap<Bytes, MetaDataValue>, Error<E>> {
-        let mut props = HashMap::new();
+    ) -> Result<IndexMap<Bytes, MetaDataValue>, Error<E>> {
+        let mut props = IndexMap::new();
 
         while reader.available() >= 3 && reader.peek(0..3)? != OBJECT_END_MARKER {
             props.insert(self.parse_meta_string(reader)?, self.parse(reader)?);
         }
 
+        // skipping object end marker
+        if reader.available() >= 3 {
+            reader.read_to_slice(&mut [0u8; 3])?;
+        }
+
         Ok(props)
     }
 }
@@ -54,6 +73,25 @@ impl<E> Parser<E, MetaDataValue> for FlvParser {
 
     /// Parse script tag data value.
     fn parse(&mut self, reader: &mut impl FlvReader) -> Result<MetaDataValue, Self::Error> {
+        if self.meta_depth >= MAX_META_DEPTH {
+            return Err(Error::ParseMetaError(format!(
+                "nesting deeper than {MAX_META_DEPTH}"
+            )));
+        }
+
+        self.meta_depth += 1;
+        let value = self.parse_meta_value(reader);
+        self.meta_depth -= 1;
+
+        value
+    }
+}
+
+impl FlvParser {
+    fn parse_meta_value<E>(
+        &mut self,
+        reader: &mut impl FlvReader,
+    ) -> Result<MetaDataValue, Error<E>> {
         Ok(match reader.read_u8()? {
             0 => MetaDataValue::Number(reader.read_f64()?),
             1 => MetaDataValue::Boolean(reader.read_u8()? != 0),
@@ -63,12 +101,13 @@ impl<E> Parser<E, MetaDataValue> for FlvParser {
             6 => MetaDataValue::Undefined,
             7 => MetaDataValue::Reference(reader.read_u16()?),
             8 => {
-                let _len = reader.read_u32()?;
-                MetaDataValue::ECMAArray(self.parse_meta_object(reader)?)
+                let len = reader.read_u32()?;
+                MetaDataValue::ECMAArray(self.parse_meta_object(reader)?, len)
             }
             10 => {
                 let len = reader.read_u32()?;
-                let mut arr = Vec::with_capacity(len as usize);
+                // every value takes at least one byte
+                let mut arr = Vec::with_capacity((len as usize).min(reader.available()));
                 for _ in 0..len {
                     arr.push(self.parse(reader)?);
                 }
@@ -1,13 +1,21 @@
+use bytes::Bytes;
 use flowly::Fourcc;
 
 use crate::{
     error::Error,
     parser::{FlvParser, Parser},
     reader::FlvReader,
-    tag::video::{
-        AvMultitrackType, AvcPacketType, CodecID, PacketExData, VideoFrameType, VideoPacketType,
-        VideoTag, VideoTagBody, VideoTagHeader,
-        mpeg4_avc::{Mpeg4AvcNALUSeq, Mpeg4AvcRecord},
+    tag::{
+        meta::MetaDataValue,
+        video::{
+            AvMultitrackType, AvcPacketType, CodecID, PacketExData, VideoCommand, VideoFrameType,
+            VideoPacketType, VideoTag, VideoTagBody, VideoTagHeader, VideoTrack,
+            av1::{Av1CodecConfigurationRecord, Av1TemporalUnit},
+            color_info::{COLOR_INFO, VideoColorInfo},
+            hevc::HevcDecoderConfigurationRecord,
+            mpeg4_avc::{Mpeg4AvcNALUSeq, Mpeg4AvcRecord},
+            vp9::VpCodecConfigurationRecord,
+        },
     },
 };
 
@@ -16,25 +24,29 @@ impl<E> Parser<E, VideoTagHeader> for FlvParser {
 
     fn parse(&mut self, reader: &mut impl FlvReader) -> Result<VideoTagHeader, Self::Error> {
         let header = reader.read_u8()?;
-        let frame_type = VideoFrameType::from(header & 0x70 >> 4);
+        let frame_type = VideoFrameType::from((header >> 4) & 0x07);
         let enhanced = (header >> 7) & 1 > 0;
 
         let mut fourcc = Fourcc::default();
         let mut multitrack = false;
         let mut has_body = true;
         let mut multitrack_type = AvMultitrackType::OneTrack;
-        let mut _video_command = None;
-        let mut pkt_type = VideoPacketType::from(header & 0x0F);
+        let mut video_command = None;
+        let mut pkt_type = VideoPacketType::CodedFrames;
+        let mut dts_offset_ns = 0;
 
         if enhanced {
+            pkt_type = VideoPacketType::from(header & 0x0F);
+
             while let VideoPacketType::ModEx = pkt_type {
                 let ex_data: PacketExData = self.parse(reader)?;
 
+                dts_offset_ns = ex_data.dts_offset_ns;
                 pkt_type = ex_data.pkt_type;
             }
 
             if pkt_type != VideoPacketType::Metadata && frame_type == VideoFrameType::Command {
-                _video_command = Some(reader.read_u8()?);
+                video_command = Some(VideoCommand::from(reader.read_u8()?));
 
                 //   ExVideoTagBody has no payload if we got here.
                 //   Set boolean to not try to process the video body.
@@ -43,7 +55,9 @@ impl<E> Parser<E, VideoTagHeader> for FlvParser {
                 multitrack = true;
                 // Fetch VideoPacketType for all video tracks in the video message.
                 // This fetch MUST not result in a VideoPacketType.Multitrack
-                multitrack_type = AvMultitrackType::from(reader.read_u8()?);
+                let byte = reader.read_u8()?;
+                multitrack_type = AvMultitrackType::from(byte >> 4);
+                pkt_type = VideoPacketType::from(byte & 0x0F);
 
                 if multitrack_type != AvMultitrackType::ManyTracksManyCodecs {
                     fourcc = Fourcc::from(reader.read_u32()?);
@@ -52,17 +66,13 @@ impl<E> Parser<E, VideoTagHeader> for FlvParser {
                 fourcc = Fourcc::from(reader.read_u32()?);
             }
         } else {
-            fourcc = match CodecID::from(header & 0x0F) {
-                CodecID::SorensonH263 => Fourcc::from_static("M263"),
-                CodecID::Screen1 => Fourcc::from_static("SCR1"),
-                CodecID::VP6 => Fourcc::from_static("VP06"),
-                CodecID::VP6Alpha => Fourcc::from_static("VP6a"),
-                CodecID::Screen2 => Fourcc::from_static("SCR2"),
-                CodecID::AVC => Fourcc::VIDEO_AVC,
-                CodecID::RealH263 => Fourcc::from_static("H263"),
-                CodecID::Hevc => Fourcc::VIDEO_HEVC,
-                CodecID::Unknown(_) => Fourcc::default(),
-            };
+            fourcc = Fourcc::from(CodecID::from(header & 0x0F));
+
+            if frame_type == VideoFrameType::Command {
+                // the command byte follows the codec info byte instead of video payload
+                video_command = Some(VideoCommand::from(reader.read_u8()?));
+                has_body = false;
+            }
         }
 
         Ok(VideoTagHeader {
@@ -72,6 +82,8 @@ impl<E> Parser<E, VideoTagHeader> for FlvParser {
             multitrack_type,
             fourcc,
             frame_type,
+            dts_offset_ns,
+            video_command,
             enhanced,
         })
     }
@@ -82,15 +94,30 @@ impl<E> Parser<E, VideoTag> for FlvParser {
 
     fn parse(&mut self, reader: &mut impl FlvReader) -> Result<VideoTag, Self::Error> {
         let mut header: VideoTagHeader = self.parse(reader)?;
-        let mut track_id = 0;
-        let mut pts_offset = 0;
-        let mut param_count = 0;
-        let mut nalus = Vec::new();
+        let mut tracks = Vec::new();
+
+        if !header.has_body {
+            return Ok(VideoTag { header, tracks });
+        }
+
+        if !header.enhanced {
+            tracks.push(self.parse_legacy_video_track(reader, &mut header)?);
+
+            return Ok(VideoTag { header, tracks });
+        }
+
+        if !header.multitrack {
+            tracks.push(self.parse_video_track(reader, 0, header.fourcc, &header)?);
+
+            return Ok(VideoTag { header, tracks });
+        }
+
+        loop {
+            let mut fourcc = header.fourcc;
 
-        if header.enhanced {
             if header.multitrack_type == AvMultitrackType::ManyTracksManyCodecs {
                 // Each track has a codec assigned to it. Fetch the FOURCC for the next track.
-                header.fourcc = Fourcc::from(reader.read_u32()?)
+                fourcc = Fourcc::from(reader.read_u32()?);
             }
 
             // Track Ordering:
@@ -104,9 +131,9 @@ impl<E> Parser<E, VideoTag> for FlvParser {
             // resolution, default angle, and language. This recommendation
             // serves as a guideline intended to standardize track numbering
             // across various applications.
-            track_id = reader.read_u8()?;
+            let track_id = reader.read_u8()?;
 
-            if header.multitrack_type != AvMultitrackType::OneTrack {
+            let track = if header.multitrack_type != AvMultitrackType::OneTrack {
                 // The `sizeOfVideoTrack` specifies the size in bytes of the
                 // current track that is being processed. This size starts
                 // counting immediately after the position where the `sizeOfVideoTrack`
@@ -114,103 +141,296 @@ impl<E> Parser<E, VideoTag> for FlvParser {
                 // next video track in a multitrack system. The data pointer is
                 // positioned immediately after this field. Depending on the MultiTrack
                 // type, the offset points to either a `fourCc` or a `trackId.`
-                let _size_of_video_track = reader.read_u24()?;
+                let size = reader.read_u24()?;
+                let mut data = reader.read_to_bytes(size as usize)?;
+
+                self.parse_video_track(&mut data, track_id, fourcc, &header)?
+            } else {
+                self.parse_video_track(reader, track_id, fourcc, &header)?
+            };
+
+            tracks.push(track);
+
+            if header.multitrack_type == AvMultitrackType::OneTrack || reader.available() == 0 {
+                break;
             }
+        }
 
-            match header.pkt_type {
-                VideoPacketType::SequenceStart => {
-                    // if self.mpeg4_avc_parser.is_none() {
-                    //     self.mpeg4_avc_parser = Some(Default::default());
-                    // }
+        Ok(VideoTag { header, tracks })
+    }
+}
 
-                    // let x: Mpeg4AvcRecord =
-                    //     self.mpeg4_avc_parser.as_mut().unwrap().parse(reader)?;
+impl FlvParser {
+    /// Parse Enhanced RTMP video tag body of the track with `fourcc` codec.
+    fn parse_video_track<E>(
+        &mut self,
+        reader: &mut impl FlvReader,
+        track_id: u8,
+        fourcc: Fourcc,
+        header: &VideoTagHeader,
+    ) -> Result<VideoTrack, Error<E>> {
+        let mut frame_type = header.frame_type;
+        let mut pts_offset = 0;
+        let mut param_count = 0;
+        let mut nalus = Vec::new();
+        let mut color_info = None;
+        let mut config = None;
 
-                    // params = Some([x.sps, x.pps].concat());
-                }
-                VideoPacketType::CodedFrames => {
-                    match header.fourcc {
-                        Fourcc::VIDEO_AVC => {
-                            // See ISO/IEC 14496-12:2015, 8.6.1 for the description of the composition
-                            // time offset. The offset in an FLV file is always in milliseconds
-                            pts_offset = reader.read_i24()? as i32;
-
-                            // let x: Mpeg4AvcNALUSeq =
-                            //     self.mpeg4_avc_parser.as_mut().unwrap().parse(reader)?;
-
-                            // nalus = x.nalus;
-                            nalus = vec![reader.read_to_end()?];
-                        }
-                        Fourcc::VIDEO_HEVC => {
-                            // body contains a configuration record to start the sequence.
-                            // See ISO/IEC 14496-15:2022, 8.3.3.2 for the description of
-                            // HEVCDecoderConfigurationRecord
-                            pts_offset = reader.read_i24()? as i32;
-                            // let x: Mpeg4AvcNALUSeq =
-                            //     self.mpeg4_avc_parser.as_mut().unwrap().parse(reader)?;
-
-                            // nalus = x.nalus;
-                            nalus = vec![reader.read_to_end()?];
-                        }
-                        _ => {
-                            nalus = vec![reader.read_to_end()?];
-                        }
-                    }
-                }
-                VideoPacketType::SequenceEnd => {}
-                VideoPacketType::CodedFramesX => {
-                    nalus = vec![reader.read_to_end()?];
+        match header.pkt_type {
+            VideoPacketType::SequenceStart => {
+                nalus = self.parse_video_config(reader, track_id, fourcc, &mut config)?;
+                param_count = nalus.len() as u32;
+            }
+            VideoPacketType::CodedFrames => {
+                if matches!(fourcc, Fourcc::VIDEO_AVC | Fourcc::VIDEO_HEVC) {
+                    // See ISO/IEC 14496-12:2015, 8.6.1 for the description of the composition
+                    // time offset. The offset in an FLV file is always in milliseconds
+                    pts_offset = reader.read_i24()?;
                 }
-                VideoPacketType::Metadata => {}
-                VideoPacketType::MPEG2TSSequenceStart => {}
-                VideoPacketType::Multitrack => {}
-                VideoPacketType::ModEx => {}
-                VideoPacketType::Unknown(_) => {}
+
+                nalus = self.parse_video_nalus(reader, track_id, fourcc, &mut frame_type)?;
             }
-        } else {
-            match header.fourcc {
-                Fourcc::VIDEO_AVC | Fourcc::VIDEO_HEVC => {
-                    if self.mpeg4_avc_parser.is_none() {
-                        self.mpeg4_avc_parser = Some(Default::default());
-                    }
+            VideoPacketType::CodedFramesX => {
+                nalus = self.parse_video_nalus(reader, track_id, fourcc, &mut frame_type)?;
+            }
+            VideoPacketType::SequenceEnd => {}
+            VideoPacketType::Metadata => {
+                let data = reader.read_to_end()?;
+                color_info = self.parse_video_metadata(&mut data.clone())?;
+                nalus.push(data);
+            }
+            VideoPacketType::MPEG2TSSequenceStart => {}
+            VideoPacketType::Multitrack => {}
+            VideoPacketType::ModEx => {}
+            VideoPacketType::Unknown(_) => {}
+        }
 
-                    let packet_type = AvcPacketType::from(reader.read_u8()?);
-                    pts_offset = reader.read_i24()? as i32;
+        Ok(VideoTrack {
+            track_id,
+            fourcc,
+            pkt_type: header.pkt_type,
+            frame_type,
+            body: VideoTagBody {
+                pts_offset,
+                param_count,
+                nalus,
+                color_info,
+                config,
+            },
+        })
+    }
 
-                    match packet_type {
-                        AvcPacketType::SequenceHeader => {
-                            let mut x: Mpeg4AvcRecord =
-                                self.mpeg4_avc_parser.as_mut().unwrap().parse(reader)?;
+    /// Parse legacy video tag body, the packet type of the header is set from `AVCPacketType`.
+    fn parse_legacy_video_track<E>(
+        &mut self,
+        reader: &mut impl FlvReader,
+        header: &mut VideoTagHeader,
+    ) -> Result<VideoTrack, Error<E>> {
+        let mut frame_type = header.frame_type;
+        let mut pts_offset = 0;
+        let mut param_count = 0;
+        let mut nalus = Vec::new();
+        let mut config = None;
 
-                            param_count += x.sps.len() as u32;
-                            nalus.extend(x.sps.drain(..));
+        match header.fourcc {
+            Fourcc::VIDEO_AVC | Fourcc::VIDEO_HEVC => {
+                let packet_type = AvcPacketType::from(reader.read_u8()?);
+                pts_offset = reader.read_i24()?;
 
-                            param_count += x.pps.len() as u32;
-                            nalus.extend(x.pps.drain(..));
-                        }
-                        AvcPacketType::NALU => {
-                            let x: Mpeg4AvcNALUSeq =
-                                self.mpeg4_avc_parser.as_mut().unwrap().parse(reader)?;
+                header.pkt_type = match packet_type {
+                    AvcPacketType::SequenceHeader => VideoPacketType::SequenceStart,
+                    AvcPacketType::EndOfSequence => VideoPacketType::SequenceEnd,
+                    _ => VideoPacketType::CodedFrames,
+                };
 
-                            nalus = x.nalus;
-                        }
-                        _ => {}
+                match packet_type {
+                    AvcPacketType::SequenceHeader => {
+                        nalus = self.parse_video_config(reader, 0, header.fourcc, &mut config)?;
+                        param_count = nalus.len() as u32;
                     }
-                }
-                _ => {
-                    nalus.push(reader.read_to_end()?);
+                    AvcPacketType::NALU => {
+                        nalus =
+                            self.parse_video_nalus(reader, 0, header.fourcc, &mut frame_type)?;
+                    }
+                    _ => {}
                 }
             }
+            _ => {
+                nalus.push(reader.read_to_end()?);
+            }
         }
 
-        Ok(VideoTag {
-            header,
-            track_id,
+        Ok(VideoTrack {
+            track_id: 0,
+            fourcc: header.fourcc,
+            pkt_type: header.pkt_type,
+            frame_type,
             body: VideoTagBody {
                 pts_offset,
                 param_count,
                 nalus,
+                color_info: None,
+                config,
             },
         })
     }
 }
+
+impl FlvParser {
+    /// Parse AMF encoded `[name, value]` pairs of `Metadata` packet, `colorInfo` is the only
+    /// one defined.
+    fn parse_video_metadata<E>(
+        &mut self,
+        reader: &mut impl FlvReader,
+    ) -> Result<Option<VideoColorInfo>, Error<E>> {
+        let mut color_info = None;
+
+        while reader.available() > 0 {
+            let name: MetaDataValue = self.parse(reader)?;
+            let value: MetaDataValue = self.parse(reader)?;
+
+            match name {
+                MetaDataValue::String(name) if name == COLOR_INFO => {
+                    color_info = VideoColorInfo::from_value(value);
+                }
+                _ => log::debug!("unknown video metadata: {:?}", name),
+            }
+        }
+
+        Ok(color_info)
+    }
+
+    /// Parse decoder configuration record into the parameter sets, the AVC/HEVC record is
+    /// stored to `config` as is.
+    ///
+    /// An invalid AVC/HEVC record does not fail the tag, it is reported with no parameter sets.
+    ///
+    /// The record of unknown codecs is returned as is.
+    fn parse_video_config<E>(
+        &mut self,
+        reader: &mut impl FlvReader,
+        track_id: u8,
+        fourcc: Fourcc,
+        config: &mut Option<Bytes>,
+    ) -> Result<Vec<Bytes>, Error<E>> {
+        Ok(match fourcc {
+            Fourcc::VIDEO_AVC => {
+                let data = reader.read_to_end()?;
+                let parser = self.mpeg4_avc_parser.entry(track_id).or_default();
+                let res: Result<Mpeg4AvcRecord, _> =
+                    Parser::<flowly::Void, _>::parse(parser, &mut data.clone());
+
+                *config = Some(data);
+
+                match res {
+                    Ok(x) => x.params().cloned().collect(),
+                    Err(err) => {
+                        log::warn!("invalid avc configuration record: {}", err);
+                        Vec::new()
+                    }
+                }
+            }
+            Fourcc::VIDEO_HEVC => {
+                // See ISO/IEC 14496-15:2022, 8.3.3.2 for the description of
+                // HEVCDecoderConfigurationRecord
+                let data = reader.read_to_end()?;
+                let parser = self.hevc_parser.entry(track_id).or_default();
+                let res: Result<HevcDecoderConfigurationRecord, _> =
+                    Parser::<flowly::Void, _>::parse(parser, &mut data.clone());
+
+                *config = Some(data);
+
+                match res {
+                    Ok(x) => x.nalus().cloned().collect(),
+                    Err(err) => {
+                        log::warn!("invalid hevc configuration record: {}", err);
+                        Vec::new()
+                    }
+                }
+            }
+            Fourcc::VIDEO_AV1 => {
+                let x: Av1CodecConfigurationRecord =
+                    self.av1_parser.entry(track_id).or_default().parse(reader)?;
+
+                x.config_obus
+            }
+            Fourcc::VIDEO_VP9 => {
+                // the record is kept as is, VP9 has no parameter sets
+                let data = reader.read_to_end()?;
+                let _: VpCodecConfigurationRecord = self
+                    .vp9_parser
+                    .entry(track_id)
+                    .or_default()
+                    .parse(&mut data.clone())?;
+
+                vec![data]
+            }
+            _ => vec![reader.read_to_end()?],
+        })
+    }
+
+    /// Split coded frames into NAL units using the length size of the last configuration record
+    /// of the track, or into OBUs for AV1. The `frame_type` is corrected by the frame header for AV1 and VP9.
+    ///
+    /// The frames of unknown codecs are returned as is.
+    fn parse_video_nalus<E>(
+        &mut self,
+        reader: &mut impl FlvReader,
+        track_id: u8,
+        fourcc: Fourcc,
+        frame_type: &mut VideoFrameType,
+    ) -> Result<Vec<Bytes>, Error<E>> {
+        let x: Mpeg4AvcNALUSeq = match fourcc {
+            Fourcc::VIDEO_AVC => self
+                .mpeg4_avc_parser
+                .entry(track_id)
+                .or_default()
+                .parse(reader)?,
+            Fourcc::VIDEO_HEVC => self
+                .hevc_parser
+                .entry(track_id)
+                .or_default()
+                .parse(reader)?,
+            Fourcc::VIDEO_AV1 => {
+                let x: Av1TemporalUnit =
+                    self.av1_parser.entry(track_id).or_default().parse(reader)?;
+
+                // the frame type of the tag header is not reliable for AV1
+                if x.keyframe {
+                    *frame_type = VideoFrameType::Key;
+                }
+
+                return Ok(x.obus);
+            }
+            Fourcc::VIDEO_VP9 => {
+                let data = reader.read_to_end()?;
+                let frame_header = self
+                    .vp9_parser
+                    .entry(track_id)
+                    .or_default()
+                    .parse_frame_header(&data);
+
+                if let Some(x) = frame_header
+                    && !x.show_existing_frame
+                    && matches!(
+                        frame_type,
+                        VideoFrameType::Key
+                            | VideoFrameType::Inter
+                            | VideoFrameType::DisposableInter
+                    )
+                {
+                    if x.keyframe {
+                        *frame_type = VideoFrameType::Key;
+                    } else if *frame_type == VideoFrameType::Key {
+                        *frame_type = VideoFrameType::Inter;
+                    }
+                }
+
+                return Ok(vec![data]);
+            }
+            _ => return Ok(vec![reader.read_to_end()?]),
+        };
+
+        Ok(x.nalus)
+    }
+}
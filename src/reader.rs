@@ -36,7 +36,9 @@ impl<T: Buf> FlvReader for T {
 
     #[inline]
     fn peek<R: RangeBounds<usize>>(&self, range: R) -> std::io::Result<&[u8]> {
-        Ok(&self.chunk()[(range.start_bound().cloned(), range.end_bound().cloned())])
+        self.chunk()
+            .get((range.start_bound().cloned(), range.end_bound().cloned()))
+            .ok_or_else(|| std::io::ErrorKind::UnexpectedEof.into())
     }
 
     #[inline]
@@ -72,6 +74,10 @@ impl<T: Buf> FlvReader for T {
 
     #[inline]
     fn read_to_bytes(&mut self, count: usize) -> std::io::Result<Bytes> {
+        if self.remaining() < count {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+
         Ok(self.copy_to_bytes(count))
     }
 
@@ -1,2 +1,169 @@
 pub mod de;
 pub mod de_ref;
+pub mod ser;
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use bytes::Bytes;
+    use serde::Deserialize;
+
+    use crate::{
+        error::Error,
+        tag::meta::{MetaDataDate, MetaDataValue},
+    };
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Codec {
+        Avc,
+        Other(u8),
+        Sized { width: u32 },
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Meta {
+        duration: f64,
+        width: u32,
+        stereo: bool,
+        encoder: String,
+        framerate: Option<f64>,
+        created: f64,
+        unset: (),
+        keyframes: Vec<u32>,
+        codec: Codec,
+        other: Codec,
+        sized: Codec,
+    }
+
+    fn key(key: &'static str) -> Bytes {
+        Bytes::from_static(key.as_bytes())
+    }
+
+    fn object(props: impl IntoIterator<Item = (&'static str, MetaDataValue)>) -> MetaDataValue {
+        MetaDataValue::Object(props.into_iter().map(|(k, v)| (key(k), v)).collect())
+    }
+
+    fn on_metadata() -> MetaDataValue {
+        let props = [
+            ("duration", MetaDataValue::Number(12.5)),
+            ("width", MetaDataValue::Number(1280.0)),
+            ("stereo", MetaDataValue::Boolean(true)),
+            ("encoder", MetaDataValue::String(key("Lavf"))),
+            ("framerate", MetaDataValue::Null),
+            (
+                "created",
+                MetaDataValue::Date(MetaDataDate {
+                    date_time: 1.7e12,
+                    local_date_time_offset: 0,
+                }),
+            ),
+            ("unset", MetaDataValue::Undefined),
+            (
+                "keyframes",
+                MetaDataValue::StrictArray(vec![MetaDataValue::Number(0.0)]),
+            ),
+            ("codec", MetaDataValue::String(key("Avc"))),
+            ("other", object([("Other", MetaDataValue::Number(3.0))])),
+            (
+                "sized",
+                object([("Sized", object([("width", MetaDataValue::Number(640.0))]))]),
+            ),
+            ("unknown", MetaDataValue::Reference(1)),
+        ];
+
+        MetaDataValue::ECMAArray(props.into_iter().map(|(k, v)| (key(k), v)).collect(), 0)
+    }
+
+    fn expected() -> Meta {
+        Meta {
+            duration: 12.5,
+            width: 1280,
+            stereo: true,
+            encoder: "Lavf".into(),
+            framerate: None,
+            created: 1.7e12,
+            unset: (),
+            keyframes: vec![0],
+            codec: Codec::Avc,
+            other: Codec::Other(3),
+            sized: Codec::Sized { width: 640 },
+        }
+    }
+
+    #[test]
+    fn ecma_array_into_struct() {
+        let value = on_metadata();
+
+        assert_eq!(Meta::deserialize(&value).unwrap(), expected());
+        assert_eq!(Meta::deserialize(value).unwrap(), expected());
+    }
+
+    #[test]
+    fn invalid_type() {
+        let value = MetaDataValue::StrictArray(vec![]);
+        assert!(matches!(
+            Meta::deserialize(&value),
+            Err(Error::ParseMetaError(_))
+        ));
+        assert!(matches!(
+            Meta::deserialize(value),
+            Err(Error::ParseMetaError(_))
+        ));
+
+        let value = object([("Unknown", MetaDataValue::Null)]);
+        assert!(matches!(
+            Codec::deserialize(&value),
+            Err(Error::ParseMetaError(_))
+        ));
+        assert!(matches!(
+            Codec::deserialize(value),
+            Err(Error::ParseMetaError(_))
+        ));
+
+        let value = MetaDataValue::Reference(1);
+        assert!(matches!(
+            <()>::deserialize(&value),
+            Err(Error::ParseMetaError(_))
+        ));
+        assert!(matches!(
+            <()>::deserialize(value),
+            Err(Error::ParseMetaError(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let invalid = Bytes::from_static(&[b'x', 0xff, 0xfe]);
+
+        for value in [
+            MetaDataValue::String(invalid.clone()),
+            MetaDataValue::LongString(invalid.clone()),
+        ] {
+            assert!(matches!(
+                <&str>::deserialize(&value),
+                Err(Error::ParseMetaError(_))
+            ));
+            assert!(matches!(
+                String::deserialize(&value),
+                Err(Error::ParseMetaError(_))
+            ));
+            assert!(matches!(
+                String::deserialize(value.clone()),
+                Err(Error::ParseMetaError(_))
+            ));
+
+            // reported as bytes in the invalid type error
+            assert!(matches!(
+                u32::deserialize(value),
+                Err(Error::ParseMetaError(_))
+            ));
+        }
+
+        let value = object([("encoder", MetaDataValue::String(invalid))]);
+        assert!(matches!(
+            HashMap::<String, String>::deserialize(value),
+            Err(Error::ParseMetaError(_))
+        ));
+    }
+}
@@ -1,4 +1,8 @@
-use serde::de::{DeserializeSeed, SeqAccess, Visitor};
+use bytes::Bytes;
+use indexmap::IndexMap;
+use serde::de::{
+    DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess, Visitor,
+};
 
 use crate::{error::Error, tag::meta::MetaDataValue};
 
@@ -54,6 +58,167 @@ where
     }
 }
 
+/// Key or string value as `String`, invalid UTF-8 is reported with `Error::ParseMetaError`.
+fn bytes_to_string(data: Bytes) -> Result<String, Error> {
+    String::from_utf8(data.to_vec()).map_err(serde::de::Error::custom)
+}
+
+struct MapDeserializer {
+    iter: indexmap::map::IntoIter<Bytes, MetaDataValue>,
+    value: Option<MetaDataValue>,
+}
+
+impl MapDeserializer {
+    fn new(map: IndexMap<Bytes, MetaDataValue>) -> Self {
+        MapDeserializer {
+            iter: map.into_iter(),
+            value: None,
+        }
+    }
+}
+
+impl<'de> MapAccess<'de> for MapDeserializer {
+    type Error = Error;
+
+    fn next_key_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+
+                let key: serde::de::value::StringDeserializer<Error> =
+                    bytes_to_string(key)?.into_deserializer();
+
+                seed.deserialize(key).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<T>(&mut self, seed: T) -> Result<T::Value, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.value.take() {
+            Some(value) => seed.deserialize(value),
+            None => Err(serde::de::Error::custom("value is missing")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        match self.iter.size_hint() {
+            (lower, Some(upper)) if lower == upper => Some(upper),
+            _ => None,
+        }
+    }
+}
+
+fn visit_object<'de, V>(
+    object: IndexMap<Bytes, MetaDataValue>,
+    visitor: V,
+) -> Result<V::Value, Error>
+where
+    V: Visitor<'de>,
+{
+    let len = object.len();
+    let mut deserializer = MapDeserializer::new(object);
+    let map = visitor.visit_map(&mut deserializer)?;
+    let remaining = deserializer.iter.len();
+
+    if remaining == 0 {
+        Ok(map)
+    } else {
+        Err(serde::de::Error::invalid_length(
+            len,
+            &"fewer elements in map",
+        ))
+    }
+}
+
+struct EnumDeserializer {
+    variant: String,
+    value: Option<MetaDataValue>,
+}
+
+impl<'de> EnumAccess<'de> for EnumDeserializer {
+    type Error = Error;
+    type Variant = VariantDeserializer;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, VariantDeserializer), Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant: serde::de::value::StringDeserializer<Error> = self.variant.into_deserializer();
+
+        let visitor = VariantDeserializer { value: self.value };
+        seed.deserialize(variant).map(|v| (v, visitor))
+    }
+}
+
+struct VariantDeserializer {
+    value: Option<MetaDataValue>,
+}
+
+impl<'de> VariantAccess<'de> for VariantDeserializer {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        match self.value {
+            Some(value) => serde::de::Deserialize::deserialize(value),
+            None => Ok(()),
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.value {
+            Some(value) => seed.deserialize(value),
+            None => Err(serde::de::Error::invalid_type(
+                serde::de::Unexpected::UnitVariant,
+                &"newtype variant",
+            )),
+        }
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Some(MetaDataValue::StrictArray(v)) => visit_array(v, visitor),
+            Some(other) => Err(other.invalid_type(&"tuple variant")),
+            None => Err(serde::de::Error::invalid_type(
+                serde::de::Unexpected::UnitVariant,
+                &"tuple variant",
+            )),
+        }
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Some(MetaDataValue::Object(v)) | Some(MetaDataValue::ECMAArray(v, _)) => {
+                visit_object(v, visitor)
+            }
+            Some(other) => Err(other.invalid_type(&"struct variant")),
+            None => Err(serde::de::Error::invalid_type(
+                serde::de::Unexpected::UnitVariant,
+                &"struct variant",
+            )),
+        }
+    }
+}
+
 impl<'de> serde::Deserializer<'de> for MetaDataValue {
     type Error = Error;
 
@@ -65,21 +230,14 @@ impl<'de> serde::Deserializer<'de> for MetaDataValue {
             MetaDataValue::Null => visitor.visit_unit(),
             MetaDataValue::Boolean(v) => visitor.visit_bool(v),
             MetaDataValue::Number(n) => visitor.visit_f64(n),
-            MetaDataValue::String(v) => {
-                visitor.visit_string(unsafe { String::from_utf8_unchecked(v.to_vec()) })
-            }
+            MetaDataValue::String(v) => visitor.visit_string(bytes_to_string(v)?),
             MetaDataValue::StrictArray(v) => visit_array(v, visitor),
-            // MetaDataValue::Object(v) => v.deserialize_any(visitor),
-            // MetaDataValue::ECMAArray(items) => v.deserialize_any(visitor),
-            MetaDataValue::Date(_) => todo!(),
-            MetaDataValue::LongString(v) => {
-                visitor.visit_string(unsafe { String::from_utf8_unchecked(v.to_vec()) })
-            }
-
-            MetaDataValue::Reference(_) => unimplemented!(),
-            MetaDataValue::MovieClip => unimplemented!(),
-            MetaDataValue::Undefined => unimplemented!(),
-            _ => unimplemented!(),
+            MetaDataValue::Object(v) => visit_object(v, visitor),
+            MetaDataValue::ECMAArray(v, _) => visit_object(v, visitor),
+            MetaDataValue::Date(v) => visitor.visit_f64(v.date_time),
+            MetaDataValue::LongString(v) => visitor.visit_string(bytes_to_string(v)?),
+            MetaDataValue::Undefined => visitor.visit_unit(),
+            _ => Err(self.invalid_type(&visitor)),
         }
     }
 
@@ -103,23 +261,41 @@ impl<'de> serde::Deserializer<'de> for MetaDataValue {
     where
         V: Visitor<'de>,
     {
-        let _ = visitor;
-        let _ = variants;
-        let _ = name;
-        unimplemented!()
-        // match self {
-        //     MetaDataValue::Object(value) => value.deserialize_enum(name, variants, visitor),
-        //     MetaDataValue::ECMAArray(value) => value.deserialize_enum(name, variants, visitor),
-        //     MetaDataValue::String(variant) => visitor.visit_enum(EnumRefDeserializer {
-        //         variant,
-        //         value: None,
-        //     }),
-
-        //     other => Err(serde::de::Error::invalid_type(
-        //         other.unexpected(),
-        //         &"string or map",
-        //     )),
-        // }
+        let _ = (name, variants);
+
+        match self {
+            MetaDataValue::Object(value) | MetaDataValue::ECMAArray(value, _) => {
+                let mut iter = value.into_iter();
+                let (variant, value) = match iter.next() {
+                    Some(v) => v,
+                    None => {
+                        return Err(serde::de::Error::invalid_value(
+                            serde::de::Unexpected::Map,
+                            &"map with a single key",
+                        ));
+                    }
+                };
+
+                // enums are encoded as maps with a single key:value pair
+                if iter.next().is_some() {
+                    return Err(serde::de::Error::invalid_value(
+                        serde::de::Unexpected::Map,
+                        &"map with a single key",
+                    ));
+                }
+
+                visitor.visit_enum(EnumDeserializer {
+                    variant: bytes_to_string(variant)?,
+                    value: Some(value),
+                })
+            }
+            MetaDataValue::String(variant) | MetaDataValue::LongString(variant) => visitor
+                .visit_enum(EnumDeserializer {
+                    variant: bytes_to_string(variant)?,
+                    value: None,
+                }),
+            other => Err(other.invalid_type(&"string or map")),
+        }
     }
 
     #[inline]
@@ -164,12 +340,8 @@ impl<'de> serde::Deserializer<'de> for MetaDataValue {
         V: Visitor<'de>,
     {
         match self {
-            MetaDataValue::String(v) => {
-                visitor.visit_string(unsafe { String::from_utf8_unchecked(v.to_vec()) })
-            }
-            MetaDataValue::LongString(v) => {
-                visitor.visit_string(unsafe { String::from_utf8_unchecked(v.to_vec()) })
-            }
+            MetaDataValue::String(v) => visitor.visit_string(bytes_to_string(v)?),
+            MetaDataValue::LongString(v) => visitor.visit_string(bytes_to_string(v)?),
             _ => Err(self.invalid_type(&visitor)),
         }
     }
@@ -186,12 +358,8 @@ impl<'de> serde::Deserializer<'de> for MetaDataValue {
         V: Visitor<'de>,
     {
         match self {
-            MetaDataValue::String(v) => {
-                visitor.visit_string(unsafe { String::from_utf8_unchecked(v.to_vec()) })
-            }
-            MetaDataValue::LongString(v) => {
-                visitor.visit_string(unsafe { String::from_utf8_unchecked(v.to_vec()) })
-            }
+            MetaDataValue::String(v) => visitor.visit_string(bytes_to_string(v)?),
+            MetaDataValue::LongString(v) => visitor.visit_string(bytes_to_string(v)?),
             MetaDataValue::StrictArray(v) => visit_array(v, visitor),
             _ => Err(self.invalid_type(&visitor)),
         }
@@ -248,13 +416,11 @@ impl<'de> serde::Deserializer<'de> for MetaDataValue {
     where
         V: Visitor<'de>,
     {
-        let _ = visitor;
-        unimplemented!()
-        // match self {
-        //     MetaDataValue::Object(v) => v.deserialize_any(visitor),
-        //     MetaDataValue::ECMAArray(v) => v.deserialize_any(visitor),
-        //     _ => Err(self.invalid_type(&visitor)),
-        // }
+        match self {
+            MetaDataValue::Object(v) => visit_object(v, visitor),
+            MetaDataValue::ECMAArray(v, _) => visit_object(v, visitor),
+            _ => Err(self.invalid_type(&visitor)),
+        }
     }
 
     fn deserialize_struct<V>(
@@ -266,14 +432,12 @@ impl<'de> serde::Deserializer<'de> for MetaDataValue {
     where
         V: Visitor<'de>,
     {
-        let _ = visitor;
-        unimplemented!()
-        // match self {
-        //     MetaDataValue::StrictArray(v) => visit_array_ref(v, visitor),
-        //     MetaDataValue::Object(v) => v.deserialize_any(visitor),
-        //     MetaDataValue::ECMAArray(v) => v.deserialize_any(visitor),
-        //     _ => Err(self.invalid_type(&visitor)),
-        // }
+        match self {
+            MetaDataValue::StrictArray(v) => visit_array(v, visitor),
+            MetaDataValue::Object(v) => visit_object(v, visitor),
+            MetaDataValue::ECMAArray(v, _) => visit_object(v, visitor),
+            _ => Err(self.invalid_type(&visitor)),
+        }
     }
 
     fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value, Error>
@@ -326,6 +490,7 @@ impl<'de> serde::Deserializer<'de> for MetaDataValue {
     {
         match self {
             MetaDataValue::Number(v) => visitor.visit_i64(v as _),
+            MetaDataValue::Date(v) => visitor.visit_i64(v.date_time as _),
             _ => Err(self.invalid_type(&visitor)),
         }
     }
@@ -366,6 +531,7 @@ impl<'de> serde::Deserializer<'de> for MetaDataValue {
     {
         match self {
             MetaDataValue::Number(v) => visitor.visit_u64(v as _),
+            MetaDataValue::Date(v) => visitor.visit_u64(v.date_time as _),
             _ => Err(self.invalid_type(&visitor)),
         }
     }
@@ -386,6 +552,7 @@ impl<'de> serde::Deserializer<'de> for MetaDataValue {
     {
         match self {
             MetaDataValue::Number(v) => visitor.visit_f64(v as _),
+            MetaDataValue::Date(v) => visitor.visit_f64(v.date_time as _),
             _ => Err(self.invalid_type(&visitor)),
         }
     }
@@ -1,4 +1,8 @@
-use serde::de::Visitor;
+use bytes::Bytes;
+use indexmap::IndexMap;
+use serde::de::{
+    DeserializeSeed, EnumAccess, MapAccess, VariantAccess, Visitor, value::BorrowedStrDeserializer,
+};
 
 use crate::{error::Error, tag::meta::MetaDataValue};
 
@@ -41,10 +45,7 @@ where
 {
     let len = array.len();
     let mut deserializer = SeqRefDeserializer::new(array);
-    let seq = match visitor.visit_seq(&mut deserializer) {
-        Ok(val) => val,
-        Err(err) => return Err(err),
-    };
+    let seq = visitor.visit_seq(&mut deserializer)?;
 
     let remaining = deserializer.iter.len();
 
@@ -58,6 +59,167 @@ where
     }
 }
 
+/// Key or string value as `&str`, invalid UTF-8 is reported with `Error::ParseMetaError`.
+fn bytes_to_str(data: &Bytes) -> Result<&str, Error> {
+    str::from_utf8(data).map_err(serde::de::Error::custom)
+}
+
+struct MapRefDeserializer<'de> {
+    iter: indexmap::map::Iter<'de, Bytes, MetaDataValue>,
+    value: Option<&'de MetaDataValue>,
+}
+
+impl<'de> MapRefDeserializer<'de> {
+    fn new(map: &'de IndexMap<Bytes, MetaDataValue>) -> Self {
+        MapRefDeserializer {
+            iter: map.iter(),
+            value: None,
+        }
+    }
+}
+
+impl<'de> MapAccess<'de> for MapRefDeserializer<'de> {
+    type Error = Error;
+
+    fn next_key_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+
+                seed.deserialize(BorrowedStrDeserializer::new(bytes_to_str(key)?))
+                    .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<T>(&mut self, seed: T) -> Result<T::Value, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.value.take() {
+            Some(value) => seed.deserialize(value),
+            None => Err(serde::de::Error::custom("value is missing")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        match self.iter.size_hint() {
+            (lower, Some(upper)) if lower == upper => Some(upper),
+
+            _ => None,
+        }
+    }
+}
+
+fn visit_object_ref<'de, V>(
+    object: &'de IndexMap<Bytes, MetaDataValue>,
+    visitor: V,
+) -> Result<V::Value, Error>
+where
+    V: Visitor<'de>,
+{
+    let len = object.len();
+    let mut deserializer = MapRefDeserializer::new(object);
+    let map = visitor.visit_map(&mut deserializer)?;
+
+    let remaining = deserializer.iter.len();
+
+    if remaining == 0 {
+        Ok(map)
+    } else {
+        Err(serde::de::Error::invalid_length(
+            len,
+            &"fewer elements in map",
+        ))
+    }
+}
+
+struct EnumRefDeserializer<'de> {
+    variant: &'de str,
+    value: Option<&'de MetaDataValue>,
+}
+
+impl<'de> EnumAccess<'de> for EnumRefDeserializer<'de> {
+    type Error = Error;
+    type Variant = VariantRefDeserializer<'de>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let visitor = VariantRefDeserializer { value: self.value };
+
+        seed.deserialize(BorrowedStrDeserializer::new(self.variant))
+            .map(|v| (v, visitor))
+    }
+}
+
+struct VariantRefDeserializer<'de> {
+    value: Option<&'de MetaDataValue>,
+}
+
+impl<'de> VariantAccess<'de> for VariantRefDeserializer<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        match self.value {
+            Some(value) => serde::de::Deserialize::deserialize(value),
+            None => Ok(()),
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.value {
+            Some(value) => seed.deserialize(value),
+            None => Err(serde::de::Error::invalid_type(
+                serde::de::Unexpected::UnitVariant,
+                &"newtype variant",
+            )),
+        }
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Some(MetaDataValue::StrictArray(v)) => visit_array_ref(v, visitor),
+            Some(other) => Err(other.invalid_type(&"tuple variant")),
+            None => Err(serde::de::Error::invalid_type(
+                serde::de::Unexpected::UnitVariant,
+                &"tuple variant",
+            )),
+        }
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Some(MetaDataValue::Object(v)) | Some(MetaDataValue::ECMAArray(v, _)) => {
+                visit_object_ref(v, visitor)
+            }
+            Some(other) => Err(other.invalid_type(&"struct variant")),
+            None => Err(serde::de::Error::invalid_type(
+                serde::de::Unexpected::UnitVariant,
+                &"struct variant",
+            )),
+        }
+    }
+}
+
 impl<'de> serde::Deserializer<'de> for &'de MetaDataValue {
     type Error = Error;
 
@@ -69,21 +231,14 @@ impl<'de> serde::Deserializer<'de> for &'de MetaDataValue {
             MetaDataValue::Null => visitor.visit_unit(),
             MetaDataValue::Boolean(v) => visitor.visit_bool(*v),
             MetaDataValue::Number(n) => visitor.visit_f64(*n),
-            MetaDataValue::String(v) => {
-                visitor.visit_borrowed_str(unsafe { str::from_utf8_unchecked(v) })
-            }
+            MetaDataValue::String(v) => visitor.visit_borrowed_str(bytes_to_str(v)?),
             MetaDataValue::StrictArray(v) => visit_array_ref(v, visitor),
-            // MetaDataValue::Object(v) => v.deserialize_any(visitor),
-            // MetaDataValue::ECMAArray(items) => v.deserialize_any(visitor),
-            MetaDataValue::Date(_) => todo!(),
-            MetaDataValue::LongString(v) => {
-                visitor.visit_borrowed_str(unsafe { str::from_utf8_unchecked(v) })
-            }
-
-            MetaDataValue::Reference(_) => unimplemented!(),
-            MetaDataValue::MovieClip => unimplemented!(),
-            MetaDataValue::Undefined => unimplemented!(),
-            _ => unimplemented!(),
+            MetaDataValue::Object(v) => visit_object_ref(v, visitor),
+            MetaDataValue::ECMAArray(v, _) => visit_object_ref(v, visitor),
+            MetaDataValue::Date(v) => visitor.visit_f64(v.date_time),
+            MetaDataValue::LongString(v) => visitor.visit_borrowed_str(bytes_to_str(v)?),
+            MetaDataValue::Undefined => visitor.visit_unit(),
+            _ => Err(self.invalid_type(&visitor)),
         }
     }
 
@@ -107,23 +262,41 @@ impl<'de> serde::Deserializer<'de> for &'de MetaDataValue {
     where
         V: Visitor<'de>,
     {
-        let _ = visitor;
-        let _ = variants;
-        let _ = name;
-        unimplemented!()
-        // match self {
-        //     MetaDataValue::Object(value) => value.deserialize_enum(name, variants, visitor),
-        //     MetaDataValue::ECMAArray(value) => value.deserialize_enum(name, variants, visitor),
-        //     MetaDataValue::String(variant) => visitor.visit_enum(EnumRefDeserializer {
-        //         variant,
-        //         value: None,
-        //     }),
-
-        //     other => Err(serde::de::Error::invalid_type(
-        //         other.unexpected(),
-        //         &"string or map",
-        //     )),
-        // }
+        let _ = (name, variants);
+
+        match self {
+            MetaDataValue::Object(value) | MetaDataValue::ECMAArray(value, _) => {
+                let mut iter = value.iter();
+                let (variant, value) = match iter.next() {
+                    Some(v) => v,
+                    None => {
+                        return Err(serde::de::Error::invalid_value(
+                            serde::de::Unexpected::Map,
+                            &"map with a single key",
+                        ));
+                    }
+                };
+
+                // enums are encoded as maps with a single key:value pair
+                if iter.next().is_some() {
+                    return Err(serde::de::Error::invalid_value(
+                        serde::de::Unexpected::Map,
+                        &"map with a single key",
+                    ));
+                }
+
+                visitor.visit_enum(EnumRefDeserializer {
+                    variant: bytes_to_str(variant)?,
+                    value: Some(value),
+                })
+            }
+            MetaDataValue::String(variant) | MetaDataValue::LongString(variant) => visitor
+                .visit_enum(EnumRefDeserializer {
+                    variant: bytes_to_str(variant)?,
+                    value: None,
+                }),
+            other => Err(other.invalid_type(&"string or map")),
+        }
     }
 
     #[inline]
@@ -161,12 +334,8 @@ impl<'de> serde::Deserializer<'de> for &'de MetaDataValue {
         V: Visitor<'de>,
     {
         match self {
-            MetaDataValue::String(v) => {
-                visitor.visit_borrowed_str(unsafe { str::from_utf8_unchecked(v) })
-            }
-            MetaDataValue::LongString(v) => {
-                visitor.visit_borrowed_str(unsafe { str::from_utf8_unchecked(v) })
-            }
+            MetaDataValue::String(v) => visitor.visit_borrowed_str(bytes_to_str(v)?),
+            MetaDataValue::LongString(v) => visitor.visit_borrowed_str(bytes_to_str(v)?),
             _ => Err(self.invalid_type(&visitor)),
         }
     }
@@ -183,12 +352,8 @@ impl<'de> serde::Deserializer<'de> for &'de MetaDataValue {
         V: Visitor<'de>,
     {
         match self {
-            MetaDataValue::String(v) => {
-                visitor.visit_borrowed_str(unsafe { str::from_utf8_unchecked(v) })
-            }
-            MetaDataValue::LongString(v) => {
-                visitor.visit_borrowed_str(unsafe { str::from_utf8_unchecked(v) })
-            }
+            MetaDataValue::String(v) => visitor.visit_borrowed_str(bytes_to_str(v)?),
+            MetaDataValue::LongString(v) => visitor.visit_borrowed_str(bytes_to_str(v)?),
             MetaDataValue::StrictArray(v) => visit_array_ref(v, visitor),
             _ => Err(self.invalid_type(&visitor)),
         }
@@ -252,13 +417,11 @@ impl<'de> serde::Deserializer<'de> for &'de MetaDataValue {
     where
         V: Visitor<'de>,
     {
-        let _ = visitor;
-        unimplemented!()
-        // match self {
-        //     MetaDataValue::Object(v) => v.deserialize_any(visitor),
-        //     MetaDataValue::ECMAArray(v) => v.deserialize_any(visitor),
-        //     _ => Err(self.invalid_type(&visitor)),
-        // }
+        match self {
+            MetaDataValue::Object(v) => visit_object_ref(v, visitor),
+            MetaDataValue::ECMAArray(v, _) => visit_object_ref(v, visitor),
+            _ => Err(self.invalid_type(&visitor)),
+        }
     }
 
     fn deserialize_struct<V>(
@@ -270,14 +433,12 @@ impl<'de> serde::Deserializer<'de> for &'de MetaDataValue {
     where
         V: Visitor<'de>,
     {
-        let _ = visitor;
-        unimplemented!()
-        // match self {
-        //     MetaDataValue::StrictArray(v) => visit_array_ref(v, visitor),
-        //     MetaDataValue::Object(v) => v.deserialize_any(visitor),
-        //     MetaDataValue::ECMAArray(v) => v.deserialize_any(visitor),
-        //     _ => Err(self.invalid_type(&visitor)),
-        // }
+        match self {
+            MetaDataValue::StrictArray(v) => visit_array_ref(v, visitor),
+            MetaDataValue::Object(v) => visit_object_ref(v, visitor),
+            MetaDataValue::ECMAArray(v, _) => visit_object_ref(v, visitor),
+            _ => Err(self.invalid_type(&visitor)),
+        }
     }
 
     fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value, Error>
@@ -330,6 +491,7 @@ impl<'de> serde::Deserializer<'de> for &'de MetaDataValue {
     {
         match self {
             MetaDataValue::Number(v) => visitor.visit_i64(*v as _),
+            MetaDataValue::Date(v) => visitor.visit_i64(v.date_time as _),
             _ => Err(self.invalid_type(&visitor)),
         }
     }
@@ -370,6 +532,7 @@ impl<'de> serde::Deserializer<'de> for &'de MetaDataValue {
     {
         match self {
             MetaDataValue::Number(v) => visitor.visit_u64(*v as _),
+            MetaDataValue::Date(v) => visitor.visit_u64(v.date_time as _),
             _ => Err(self.invalid_type(&visitor)),
         }
     }
@@ -390,6 +553,7 @@ impl<'de> serde::Deserializer<'de> for &'de MetaDataValue {
     {
         match self {
             MetaDataValue::Number(v) => visitor.visit_f64(*v as _),
+            MetaDataValue::Date(v) => visitor.visit_f64(v.date_time as _),
             _ => Err(self.invalid_type(&visitor)),
         }
     }
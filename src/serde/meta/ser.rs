@@ -1,3 +1,30 @@
+use std::fmt::Display;
+
+use bytes::Bytes;
+use indexmap::IndexMap;
+use serde::{Serialize, ser::Impossible};
+
+use crate::{error::Error, tag::meta::MetaDataValue};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Convert a `T` into `MetaDataValue`.
+///
+/// Structs and maps are encoded as `Object`, except the top-level one
+/// which becomes an `ECMAArray` as used by `onMetaData`.
+pub fn to_meta_value<T>(value: &T) -> Result<MetaDataValue>
+where
+    T: ?Sized + Serialize,
+{
+    Ok(match value.serialize(Serializer)? {
+        MetaDataValue::Object(map) => {
+            let len = map.len() as u32;
+            MetaDataValue::ECMAArray(map, len)
+        }
+        value => value,
+    })
+}
+
 pub struct Serializer;
 
 impl serde::Serializer for Serializer {
@@ -13,94 +40,95 @@ impl serde::Serializer for Serializer {
 
     #[inline]
     fn serialize_bool(self, value: bool) -> Result<MetaDataValue> {
-        Ok(MetaDataValue::Bool(value))
+        Ok(MetaDataValue::Boolean(value))
     }
 
     #[inline]
     fn serialize_i8(self, value: i8) -> Result<MetaDataValue> {
-        self.serialize_i64(value as i64)
+        self.serialize_f64(value as f64)
     }
 
     #[inline]
     fn serialize_i16(self, value: i16) -> Result<MetaDataValue> {
-        self.serialize_i64(value as i64)
+        self.serialize_f64(value as f64)
     }
 
     #[inline]
     fn serialize_i32(self, value: i32) -> Result<MetaDataValue> {
-        self.serialize_i64(value as i64)
+        self.serialize_f64(value as f64)
     }
 
     #[inline]
     fn serialize_i64(self, value: i64) -> Result<MetaDataValue> {
-        Ok(Value::Number(value.into()))
+        self.serialize_f64(value as f64)
     }
 
+    #[inline]
     fn serialize_i128(self, value: i128) -> Result<MetaDataValue> {
-        Ok(Value::Number(value.into()))
+        self.serialize_f64(value as f64)
     }
 
     #[inline]
-
     fn serialize_u8(self, value: u8) -> Result<MetaDataValue> {
-        self.serialize_u64(value as u64)
+        self.serialize_f64(value as f64)
     }
 
     #[inline]
-
     fn serialize_u16(self, value: u16) -> Result<MetaDataValue> {
-        self.serialize_u64(value as u64)
+        self.serialize_f64(value as f64)
     }
 
     #[inline]
-
     fn serialize_u32(self, value: u32) -> Result<MetaDataValue> {
-        self.serialize_u64(value as u64)
+        self.serialize_f64(value as f64)
     }
 
     #[inline]
-
     fn serialize_u64(self, value: u64) -> Result<MetaDataValue> {
-        Ok(Value::Number(value.into()))
+        self.serialize_f64(value as f64)
     }
 
+    #[inline]
     fn serialize_u128(self, value: u128) -> Result<MetaDataValue> {
-        Ok(Value::Number(value.into()))
+        self.serialize_f64(value as f64)
     }
 
     #[inline]
     fn serialize_f32(self, float: f32) -> Result<MetaDataValue> {
-        Ok(Value::from(float))
+        self.serialize_f64(float as f64)
     }
 
     #[inline]
     fn serialize_f64(self, float: f64) -> Result<MetaDataValue> {
-        Ok(Value::from(float))
+        Ok(MetaDataValue::Number(float))
     }
 
     #[inline]
     fn serialize_char(self, value: char) -> Result<MetaDataValue> {
-        let mut s = String::new();
-
-        s.push(value);
+        let mut buf = [0u8; 4];
 
-        Ok(Value::String(s))
+        self.serialize_str(value.encode_utf8(&mut buf))
     }
 
     #[inline]
     fn serialize_str(self, value: &str) -> Result<MetaDataValue> {
-        Ok(Value::String(value.to_owned()))
+        Ok(MetaDataValue::String(Bytes::copy_from_slice(
+            value.as_bytes(),
+        )))
     }
 
     fn serialize_bytes(self, value: &[u8]) -> Result<MetaDataValue> {
-        let vec = value.iter().map(|&b| Value::Number(b.into())).collect();
+        let vec = value
+            .iter()
+            .map(|&b| MetaDataValue::Number(b as f64))
+            .collect();
 
-        Ok(Value::Array(vec))
+        Ok(MetaDataValue::StrictArray(vec))
     }
 
     #[inline]
     fn serialize_unit(self) -> Result<MetaDataValue> {
-        Ok(Value::Null)
+        Ok(MetaDataValue::Null)
     }
 
     #[inline]
@@ -136,9 +164,13 @@ impl serde::Serializer for Serializer {
     where
         T: ?Sized + Serialize,
     {
-        let mut values = Map::new();
-        values.insert(String::from(variant), tri!(to_value(value)));
-        Ok(Value::Object(values))
+        let mut values = IndexMap::new();
+        values.insert(
+            Bytes::from_static(variant.as_bytes()),
+            value.serialize(self)?,
+        );
+
+        Ok(MetaDataValue::Object(values))
     }
 
     #[inline]
@@ -166,9 +198,7 @@ impl serde::Serializer for Serializer {
 
     fn serialize_tuple_struct(
         self,
-
         _name: &'static str,
-
         len: usize,
     ) -> Result<Self::SerializeTupleStruct> {
         self.serialize_seq(Some(len))
@@ -182,28 +212,20 @@ impl serde::Serializer for Serializer {
         len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
         Ok(SerializeTupleVariant {
-            name: String::from(variant),
+            name: Bytes::from_static(variant.as_bytes()),
             vec: Vec::with_capacity(len),
         })
     }
 
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
-        Ok(SerializeMap::Map {
-            map: Map::with_capacity(len.unwrap_or(0)),
+        Ok(SerializeMap {
+            map: IndexMap::with_capacity(len.unwrap_or(0)),
             next_key: None,
         })
     }
 
-    fn serialize_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
-        match name {
-            #[cfg(feature = "arbitrary_precision")]
-            crate::number::TOKEN => Ok(SerializeMap::Number { out_value: None }),
-
-            #[cfg(feature = "raw_value")]
-            crate::raw::TOKEN => Ok(SerializeMap::RawValue { out_value: None }),
-
-            _ => self.serialize_map(Some(len)),
-        }
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
+        self.serialize_map(Some(len))
     }
 
     fn serialize_struct_variant(
@@ -211,11 +233,11 @@ impl serde::Serializer for Serializer {
         _name: &'static str,
         _variant_index: u32,
         variant: &'static str,
-        _len: usize,
+        len: usize,
     ) -> Result<Self::SerializeStructVariant> {
         Ok(SerializeStructVariant {
-            name: String::from(variant),
-            map: Map::new(),
+            name: Bytes::from_static(variant.as_bytes()),
+            map: IndexMap::with_capacity(len),
         })
     }
 
@@ -223,6 +245,460 @@ impl serde::Serializer for Serializer {
     where
         T: ?Sized + Display,
     {
-        Ok(Value::String(value.to_string()))
+        Ok(MetaDataValue::String(Bytes::from(value.to_string())))
+    }
+}
+
+pub struct SerializeVec {
+    vec: Vec<MetaDataValue>,
+}
+
+impl serde::ser::SerializeSeq for SerializeVec {
+    type Ok = MetaDataValue;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.vec.push(value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<MetaDataValue> {
+        Ok(MetaDataValue::StrictArray(self.vec))
+    }
+}
+
+impl serde::ser::SerializeTuple for SerializeVec {
+    type Ok = MetaDataValue;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        serde::ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<MetaDataValue> {
+        serde::ser::SerializeSeq::end(self)
+    }
+}
+
+impl serde::ser::SerializeTupleStruct for SerializeVec {
+    type Ok = MetaDataValue;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        serde::ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<MetaDataValue> {
+        serde::ser::SerializeSeq::end(self)
+    }
+}
+
+pub struct SerializeTupleVariant {
+    name: Bytes,
+    vec: Vec<MetaDataValue>,
+}
+
+impl serde::ser::SerializeTupleVariant for SerializeTupleVariant {
+    type Ok = MetaDataValue;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.vec.push(value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<MetaDataValue> {
+        let mut object = IndexMap::new();
+        object.insert(self.name, MetaDataValue::StrictArray(self.vec));
+
+        Ok(MetaDataValue::Object(object))
+    }
+}
+
+pub struct SerializeMap {
+    map: IndexMap<Bytes, MetaDataValue>,
+    next_key: Option<Bytes>,
+}
+
+impl serde::ser::SerializeMap for SerializeMap {
+    type Ok = MetaDataValue;
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.next_key = Some(key.serialize(MapKeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let key = self.next_key.take().ok_or_else(|| {
+            Error::ParseMetaError("serialize_value called before serialize_key".into())
+        })?;
+
+        self.map.insert(key, value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<MetaDataValue> {
+        Ok(MetaDataValue::Object(self.map))
+    }
+}
+
+impl serde::ser::SerializeStruct for SerializeMap {
+    type Ok = MetaDataValue;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.map.insert(
+            Bytes::from_static(key.as_bytes()),
+            value.serialize(Serializer)?,
+        );
+        Ok(())
+    }
+
+    fn end(self) -> Result<MetaDataValue> {
+        serde::ser::SerializeMap::end(self)
+    }
+}
+
+pub struct SerializeStructVariant {
+    name: Bytes,
+    map: IndexMap<Bytes, MetaDataValue>,
+}
+
+impl serde::ser::SerializeStructVariant for SerializeStructVariant {
+    type Ok = MetaDataValue;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.map.insert(
+            Bytes::from_static(key.as_bytes()),
+            value.serialize(Serializer)?,
+        );
+        Ok(())
+    }
+
+    fn end(self) -> Result<MetaDataValue> {
+        let mut object = IndexMap::new();
+        object.insert(self.name, MetaDataValue::Object(self.map));
+
+        Ok(MetaDataValue::Object(object))
+    }
+}
+
+/// Serializer of object property names, AMF0 allows only strings there.
+struct MapKeySerializer;
+
+fn key_must_be_a_string() -> Error {
+    Error::ParseMetaError("key must be a string".into())
+}
+
+impl serde::Serializer for MapKeySerializer {
+    type Ok = Bytes;
+    type Error = Error;
+    type SerializeSeq = Impossible<Bytes, Error>;
+    type SerializeTuple = Impossible<Bytes, Error>;
+    type SerializeTupleStruct = Impossible<Bytes, Error>;
+    type SerializeTupleVariant = Impossible<Bytes, Error>;
+    type SerializeMap = Impossible<Bytes, Error>;
+    type SerializeStruct = Impossible<Bytes, Error>;
+    type SerializeStructVariant = Impossible<Bytes, Error>;
+
+    #[inline]
+    fn serialize_str(self, value: &str) -> Result<Bytes> {
+        Ok(Bytes::copy_from_slice(value.as_bytes()))
+    }
+
+    #[inline]
+    fn serialize_char(self, value: char) -> Result<Bytes> {
+        Ok(Bytes::from(value.to_string()))
+    }
+
+    #[inline]
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Bytes> {
+        Ok(Bytes::from_static(variant.as_bytes()))
+    }
+
+    #[inline]
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<Bytes>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_bool(self, value: bool) -> Result<Bytes> {
+        self.collect_str(&value)
+    }
+
+    fn serialize_i8(self, value: i8) -> Result<Bytes> {
+        self.collect_str(&value)
+    }
+
+    fn serialize_i16(self, value: i16) -> Result<Bytes> {
+        self.collect_str(&value)
+    }
+
+    fn serialize_i32(self, value: i32) -> Result<Bytes> {
+        self.collect_str(&value)
+    }
+
+    fn serialize_i64(self, value: i64) -> Result<Bytes> {
+        self.collect_str(&value)
+    }
+
+    fn serialize_u8(self, value: u8) -> Result<Bytes> {
+        self.collect_str(&value)
+    }
+
+    fn serialize_u16(self, value: u16) -> Result<Bytes> {
+        self.collect_str(&value)
+    }
+
+    fn serialize_u32(self, value: u32) -> Result<Bytes> {
+        self.collect_str(&value)
+    }
+
+    fn serialize_u64(self, value: u64) -> Result<Bytes> {
+        self.collect_str(&value)
+    }
+
+    fn serialize_f32(self, _value: f32) -> Result<Bytes> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_f64(self, _value: f64) -> Result<Bytes> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_bytes(self, _value: &[u8]) -> Result<Bytes> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_unit(self) -> Result<Bytes> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Bytes> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Bytes>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_none(self) -> Result<Bytes> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_some<T>(self, _value: &T) -> Result<Bytes>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(key_must_be_a_string())
+    }
+
+    fn collect_str<T>(self, value: &T) -> Result<Bytes>
+    where
+        T: ?Sized + Display,
+    {
+        Ok(Bytes::from(value.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use bytes::Bytes;
+    use indexmap::IndexMap;
+    use serde::Serialize;
+
+    use super::to_meta_value;
+    use crate::{error::Error, tag::meta::MetaDataValue};
+
+    #[derive(Serialize)]
+    struct Resolution {
+        width: u32,
+        height: u32,
+    }
+
+    #[derive(Serialize)]
+    enum Codec {
+        Avc,
+        Other(u8),
+    }
+
+    #[derive(Serialize)]
+    struct Meta {
+        duration: f64,
+        stereo: bool,
+        encoder: String,
+        framerate: Option<f64>,
+        keyframes: Vec<i64>,
+        resolution: Resolution,
+        codec: Codec,
+        other: Codec,
+    }
+
+    fn key(key: &'static str) -> Bytes {
+        Bytes::from_static(key.as_bytes())
+    }
+
+    #[test]
+    fn struct_to_ecma_array() {
+        let value = to_meta_value(&Meta {
+            duration: 12.5,
+            stereo: true,
+            encoder: "Lavf".into(),
+            framerate: None,
+            keyframes: vec![0, -40],
+            resolution: Resolution {
+                width: 1280,
+                height: 720,
+            },
+            codec: Codec::Avc,
+            other: Codec::Other(3),
+        })
+        .unwrap();
+
+        let mut resolution = IndexMap::new();
+        resolution.insert(key("width"), MetaDataValue::Number(1280.0));
+        resolution.insert(key("height"), MetaDataValue::Number(720.0));
+
+        let mut other = IndexMap::new();
+        other.insert(key("Other"), MetaDataValue::Number(3.0));
+
+        let mut props = IndexMap::new();
+        props.insert(key("duration"), MetaDataValue::Number(12.5));
+        props.insert(key("stereo"), MetaDataValue::Boolean(true));
+        props.insert(key("encoder"), MetaDataValue::String(key("Lavf")));
+        props.insert(key("framerate"), MetaDataValue::Null);
+        props.insert(
+            key("keyframes"),
+            MetaDataValue::StrictArray(vec![
+                MetaDataValue::Number(0.0),
+                MetaDataValue::Number(-40.0),
+            ]),
+        );
+        props.insert(key("resolution"), MetaDataValue::Object(resolution));
+        props.insert(key("codec"), MetaDataValue::String(key("Avc")));
+        props.insert(key("other"), MetaDataValue::Object(other));
+
+        assert_eq!(value, MetaDataValue::ECMAArray(props, 8));
+    }
+
+    #[test]
+    fn scalars() {
+        assert_eq!(to_meta_value(&7u8).unwrap(), MetaDataValue::Number(7.0));
+        assert_eq!(
+            to_meta_value(&Some(true)).unwrap(),
+            MetaDataValue::Boolean(true)
+        );
+        assert_eq!(to_meta_value(&()).unwrap(), MetaDataValue::Null);
+        assert_eq!(
+            to_meta_value(&'x').unwrap(),
+            MetaDataValue::String(key("x"))
+        );
+    }
+
+    #[test]
+    fn map_keys() {
+        let map = BTreeMap::from([("a", 1), ("b", 2)]);
+        let MetaDataValue::ECMAArray(props, 2) = to_meta_value(&map).unwrap() else {
+            panic!("expected ECMAArray");
+        };
+        assert_eq!(props.keys().collect::<Vec<_>>(), [&key("a"), &key("b")]);
+
+        // numbers are stringified, compound keys are rejected
+        let map = BTreeMap::from([(1, 1)]);
+        let MetaDataValue::ECMAArray(props, 1) = to_meta_value(&map).unwrap() else {
+            panic!("expected ECMAArray");
+        };
+        assert!(props.contains_key(b"1".as_slice()));
+
+        let map = BTreeMap::from([((1, 2), 1)]);
+        assert!(matches!(to_meta_value(&map), Err(Error::ParseMetaError(_))));
     }
 }
@@ -4,6 +4,8 @@ use crate::{error::Error, tag::meta::MetaDataValue};
 
 pub mod meta;
 
+pub use meta::ser::to_meta_value;
+
 impl MetaDataValue {
     #[cold]
     fn invalid_type<E>(&self, exp: &dyn serde::de::Expected) -> E
@@ -14,21 +16,25 @@ impl MetaDataValue {
     }
 
     #[cold]
-    fn unexpected(&self) -> serde::de::Unexpected {
+    fn unexpected(&self) -> serde::de::Unexpected<'_> {
         use serde::de::Unexpected;
         match self {
             MetaDataValue::Null => Unexpected::Unit,
             MetaDataValue::Boolean(b) => Unexpected::Bool(*b),
             MetaDataValue::Number(n) => Unexpected::Float(*n),
-            MetaDataValue::String(s) => Unexpected::Str(unsafe { str::from_utf8_unchecked(s) }),
+            MetaDataValue::String(s) => {
+                str::from_utf8(s).map_or(Unexpected::Bytes(s), Unexpected::Str)
+            }
             MetaDataValue::StrictArray(_) => Unexpected::Seq,
             MetaDataValue::Object(_) => Unexpected::Map,
             MetaDataValue::MovieClip => Unexpected::Other("movie"),
             MetaDataValue::Undefined => Unexpected::Other("undefined"),
-            MetaDataValue::Reference(_) => todo!(),
-            MetaDataValue::ECMAArray(_) => Unexpected::Map,
+            MetaDataValue::Reference(_) => Unexpected::Other("reference"),
+            MetaDataValue::ECMAArray(..) => Unexpected::Map,
             MetaDataValue::Date(_) => Unexpected::Other("date"),
-            MetaDataValue::LongString(s) => Unexpected::Str(unsafe { str::from_utf8_unchecked(s) }),
+            MetaDataValue::LongString(s) => {
+                str::from_utf8(s).map_or(Unexpected::Bytes(s), Unexpected::Str)
+            }
             MetaDataValue::Unknown(_) => Unexpected::Other("unknown"),
         }
     }
@@ -46,6 +52,13 @@ impl<'a> fmt::Display for MetaDataUnexpected<'a> {
     }
 }
 
+impl serde::ser::Error for Error {
+    #[cold]
+    fn custom<T: fmt::Display>(msg: T) -> Error {
+        Error::ParseMetaError(msg.to_string())
+    }
+}
+
 impl serde::de::Error for Error {
     #[cold]
     fn custom<T: fmt::Display>(msg: T) -> Error {
@@ -0,0 +1,288 @@
+use std::{
+    io::{ErrorKind, Read},
+    ops::Range,
+};
+
+use bytes::BytesMut;
+use tokio_util::codec::Decoder;
+
+use crate::{codec::FlvCodec, error::Error, header::FlvHeader, tag::FlvTag};
+
+/// Size of the chunks read by `FlvTagIter`.
+const READ_CHUNK_SIZE: usize = 8 * 1024;
+
+/// Push based FLV parser, not requiring any async runtime.
+///
+/// Chunks of arbitrary size are passed to `feed`, the complete tags are taken by iterating
+/// the parser. Iteration stops on the first error.
+#[derive(Debug)]
+pub struct FlvStreamParser<E = flowly::Void> {
+    codec: FlvCodec<E>,
+    buf: BytesMut,
+    eof: bool,
+    failed: bool,
+}
+
+impl<E> FlvStreamParser<E> {
+    /// Parser yielding the tags of `tag_types`, a bit mask of `1 << FlvTagType`.
+    pub fn new(tag_types: u64) -> Self {
+        Self {
+            codec: FlvCodec::new(tag_types),
+            buf: BytesMut::new(),
+            eof: false,
+            failed: false,
+        }
+    }
+
+    /// Resynchronize after corrupt or truncated tags instead of failing, see `FlvCodec`.
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.codec = self.codec.lenient(lenient);
+        self
+    }
+
+    /// Take the stream byte ranges skipped in lenient mode so far.
+    pub fn take_skipped(&mut self) -> Vec<Range<u64>> {
+        self.codec.take_skipped()
+    }
+
+    /// Append the next chunk of the stream.
+    pub fn feed(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Mark the end of the stream, the trailing incomplete tag is dropped.
+    pub fn finish(&mut self) {
+        self.eof = true;
+    }
+
+    /// FLV header, available once parsed.
+    pub fn header(&self) -> Option<&FlvHeader> {
+        self.codec.header()
+    }
+
+    /// Number of buffered bytes not parsed yet.
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Parse the next complete tag, `None` if more data is required.
+    pub fn next_tag(&mut self) -> Result<Option<FlvTag>, Error<E>> {
+        if self.failed {
+            return Ok(None);
+        }
+
+        let res = if self.eof {
+            self.codec.decode_eof(&mut self.buf)
+        } else {
+            self.codec.decode(&mut self.buf)
+        };
+
+        self.failed = res.is_err();
+
+        res
+    }
+}
+
+impl<E> Default for FlvStreamParser<E> {
+    fn default() -> Self {
+        Self {
+            codec: FlvCodec::default(),
+            buf: BytesMut::new(),
+            eof: false,
+            failed: false,
+        }
+    }
+}
+
+impl<E> Iterator for FlvStreamParser<E> {
+    type Item = Result<FlvTag, Error<E>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_tag().transpose()
+    }
+}
+
+/// Blocking iterator of the tags read from `std::io::Read`.
+#[derive(Debug)]
+pub struct FlvTagIter<R, E = flowly::Void> {
+    reader: R,
+    parser: FlvStreamParser<E>,
+}
+
+impl<R: Read, E> FlvTagIter<R, E> {
+    pub fn new(reader: R, tag_types: u64) -> Self {
+        Self {
+            reader,
+            parser: FlvStreamParser::new(tag_types),
+        }
+    }
+
+    /// Resynchronize after corrupt or truncated tags instead of failing, see `FlvCodec`.
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.parser = self.parser.lenient(lenient);
+        self
+    }
+
+    /// FLV header, available once parsed.
+    pub fn header(&self) -> Option<&FlvHeader> {
+        self.parser.header()
+    }
+
+    /// Take the stream byte ranges skipped in lenient mode so far.
+    pub fn take_skipped(&mut self) -> Vec<Range<u64>> {
+        self.parser.take_skipped()
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Read the next chunk into the parser buffer, returns `false` at the end of the stream.
+    fn fill(&mut self) -> std::io::Result<bool> {
+        let buf = &mut self.parser.buf;
+        let len = buf.len();
+
+        buf.resize(len + READ_CHUNK_SIZE, 0);
+
+        let count = loop {
+            match self.reader.read(&mut buf[len..]) {
+                Ok(count) => break count,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    buf.truncate(len);
+                    return Err(err);
+                }
+            }
+        };
+
+        buf.truncate(len + count);
+
+        Ok(count > 0)
+    }
+}
+
+impl<R: Read, E> Iterator for FlvTagIter<R, E> {
+    type Item = Result<FlvTag, Error<E>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.parser.next_tag() {
+                Ok(None) if !self.parser.eof && !self.parser.failed => (),
+                res => return res.transpose(),
+            }
+
+            match self.fill() {
+                Ok(true) => (),
+                Ok(false) => self.parser.finish(),
+                Err(err) => {
+                    self.parser.failed = true;
+                    return Some(Err(err.into()));
+                }
+            }
+        }
+    }
+}
+
+/// Read FLV tags of `tag_types` from blocking `reader`.
+#[inline]
+pub fn read_flv_tags<R: Read>(reader: R, tag_types: u64) -> FlvTagIter<R> {
+    FlvTagIter::new(reader, tag_types)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use bytes::{BufMut, Bytes, BytesMut};
+
+    use super::{FlvStreamParser, read_flv_tags};
+    use crate::{
+        DEMUX_ALL_TYPES,
+        tag::{FlvTag, FlvTagData, FlvTagType},
+    };
+
+    fn put_tag(buf: &mut BytesMut, tag_type: u8, timestamp: u32, data: &[u8]) {
+        buf.put_u8(tag_type);
+        buf.put_uint(data.len() as u64, 3);
+        buf.put_uint(timestamp as u64 & 0xff_ffff, 3);
+        buf.put_u8((timestamp >> 24) as u8);
+        buf.put_uint(0, 3);
+        buf.put_slice(data);
+        buf.put_u32(data.len() as u32 + 11);
+    }
+
+    /// Audio and video stream with an extended header and a tag of unknown type.
+    fn file() -> Bytes {
+        let mut file = BytesMut::new();
+        file.put_slice(&[
+            b'F', b'L', b'V', 1, 0b101, 0, 0, 0, 11, 0xaa, 0xbb, 0, 0, 0, 0,
+        ]);
+        put_tag(&mut file, 8, 0, &[0xaf, 0, 0x12, 0x10]);
+        put_tag(&mut file, 9, 0, &[0x57, 0]);
+        put_tag(&mut file, 30, 10, &[1, 2, 3]);
+        put_tag(&mut file, 8, 0x0100_0017, &[0xaf, 1, 0x21, 0x10, 0x04]);
+
+        file.freeze()
+    }
+
+    fn timestamps(tags: &[FlvTag]) -> Vec<u32> {
+        tags.iter().map(|tag| tag.header.timestamp).collect()
+    }
+
+    /// Reader returning at most 3 bytes per read.
+    struct Trickle(Bytes);
+
+    impl Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = buf.len().min(self.0.len()).min(3);
+            buf[..n].copy_from_slice(&self.0.split_to(n));
+
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn feed_split_chunks() {
+        let file = file();
+
+        for chunk_size in [1, 2, 7, 16, file.len()] {
+            let mut parser = FlvStreamParser::<flowly::Void>::new(DEMUX_ALL_TYPES);
+            let mut tags = Vec::new();
+
+            for chunk in file.chunks(chunk_size) {
+                parser.feed(chunk);
+                tags.extend(parser.by_ref().map(Result::unwrap));
+            }
+
+            parser.finish();
+            assert!(parser.next().is_none());
+
+            assert_eq!(parser.header().unwrap().remaining, 2);
+            assert_eq!(timestamps(&tags), [0, 0, 10, 0x0100_0017], "{chunk_size}");
+            assert!(matches!(tags[0].data, FlvTagData::Audio(_)));
+            assert!(matches!(tags[2].data, FlvTagData::Unknown));
+        }
+    }
+
+    #[test]
+    fn tag_types() {
+        let mut parser = FlvStreamParser::<flowly::Void>::new(1 << u8::from(FlvTagType::Audio));
+        parser.feed(&file());
+        parser.finish();
+
+        let tags: Vec<_> = parser.map(Result::unwrap).collect();
+        assert_eq!(timestamps(&tags), [0, 0x0100_0017]);
+    }
+
+    #[test]
+    fn blocking_iterator() {
+        let tags: Vec<_> = read_flv_tags(Trickle(file()), DEMUX_ALL_TYPES)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(timestamps(&tags), [0, 0, 10, 0x0100_0017]);
+
+        let mut iter = read_flv_tags(&b"FLX\x01\x05\x00\x00\x00\x09"[..], DEMUX_ALL_TYPES);
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+    }
+}
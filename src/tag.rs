@@ -89,6 +89,9 @@ pub enum FlvTagData {
     /// Script tag data.
     Meta(meta::MetaTag),
 
+    /// Script tag data named `onMetaData`.
+    OnMetaData(Box<meta::OnMetaData>),
+
     /// Unknown
     Unknown,
 }
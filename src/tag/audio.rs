@@ -1,4 +1,12 @@
-use bytes::{Buf, Bytes};
+use bytes::Bytes;
+use flowly::Fourcc;
+
+use super::video::AvMultitrackType;
+use crate::reader::FlvReader;
+
+use aac::AudioSpecificConfig;
+
+pub mod aac;
 
 /// The tag data part of `audio` FLV tag, including `tag data header` and `tag data body`.
 #[derive(Clone, Debug, PartialEq)]
@@ -6,8 +14,75 @@ pub struct AudioTag {
     /// The header part of `audio` FLV tag.
     pub header: AudioTagHeader, // 8 bits.
 
-    /// The body part of `audio` FLV tag.
-    pub body: AudioTagBody,
+    /// The per-track bodies of `audio` FLV tag.
+    ///
+    /// Legacy and single track tags always have exactly one track.
+    pub tracks: Vec<AudioTrack>,
+}
+
+impl AudioTag {
+    /// Legacy AAC sequence header tag carrying `config`.
+    pub fn aac_sequence_start(config: AudioSpecificConfig) -> std::io::Result<Self> {
+        Ok(Self::aac(
+            AudioPacketType::SequenceStart,
+            config,
+            config.to_bytes()?,
+        ))
+    }
+
+    /// Legacy AAC tag of raw AAC frame, e.g. split off ADTS frame by `aac::read_adts_frame`.
+    pub fn aac_coded_frames(config: AudioSpecificConfig, data: Bytes) -> Self {
+        Self::aac(AudioPacketType::CodedFrames, config, data)
+    }
+
+    fn aac(pkt_type: AudioPacketType, config: AudioSpecificConfig, data: Bytes) -> Self {
+        AudioTag {
+            // the legacy header of AAC is always 44 KHz, 16 bit, stereo
+            header: AudioTagHeader {
+                sound_format: SoundFormat::AAC,
+                sound_rate: SoundRate::_44KHZ,
+                sound_size: SoundSize::_16Bit,
+                sound_type: SoundType::Stereo,
+                pkt_type,
+                fourcc: Fourcc::AUDIO_AAC,
+                multitrack: false,
+                multitrack_type: AvMultitrackType::OneTrack,
+                dts_offset_ns: 0,
+            },
+            tracks: vec![AudioTrack {
+                track_id: 0,
+                fourcc: Fourcc::AUDIO_AAC,
+                body: AudioTagBody {
+                    data,
+                    multichannel_config: None,
+                    aac_config: Some(config),
+                },
+            }],
+        }
+    }
+
+    /// Raw AAC frame of the track wrapped into ADTS frame.
+    ///
+    /// `None` if the tag is not AAC `CodedFrames` or the config of the track is unknown.
+    pub fn adts_frame(&self, track_id: u8) -> std::io::Result<Option<Bytes>> {
+        if self.header.pkt_type != AudioPacketType::CodedFrames {
+            return Ok(None);
+        }
+
+        let Some(track) = self
+            .tracks
+            .iter()
+            .find(|x| x.track_id == track_id && x.fourcc == Fourcc::AUDIO_AAC)
+        else {
+            return Ok(None);
+        };
+
+        track
+            .body
+            .aac_config
+            .map(|config| config.adts_frame(&track.body.data))
+            .transpose()
+    }
 }
 
 /// The `tag data header` part of `audio` FLV tag data.
@@ -16,21 +91,149 @@ pub struct AudioTagHeader {
     /// The format of sound, 4 bits.
     pub sound_format: SoundFormat,
 
-    /// The rate of sound, 2 bits.
+    /// The rate of sound, 2 bits. Not used by `ExHeader`.
     pub sound_rate: SoundRate,
 
-    /// The sample size of sound, 1 bit.
+    /// The sample size of sound, 1 bit. Not used by `ExHeader`.
     pub sound_size: SoundSize,
 
-    /// The type of sound, 1 bit.
+    /// The type of sound, 1 bit. Not used by `ExHeader`.
     pub sound_type: SoundType,
+
+    /// The packet type, derived from `AACPacketType` for legacy AAC.
+    pub pkt_type: AudioPacketType,
+
+    /// Codec of all tracks unless `multitrack_type` is `ManyTracksManyCodecs`.
+    pub fourcc: Fourcc,
+
+    pub multitrack: bool,
+    pub multitrack_type: AvMultitrackType,
+
+    /// The ModEx `TimestampOffsetNano` of the packet.
+    pub dts_offset_ns: u32,
+}
+
+impl AudioTagHeader {
+    /// Is Enhanced RTMP audio header.
+    #[inline]
+    pub fn is_enhanced(&self) -> bool {
+        self.sound_format == SoundFormat::ExHeader
+    }
+}
+
+/// Audio track of `audio` FLV tag.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AudioTrack {
+    /// Track ID, always zero for non-multitrack tags.
+    pub track_id: u8,
+
+    /// Codec of the track.
+    pub fourcc: Fourcc,
+
+    /// The body of the track.
+    pub body: AudioTagBody,
 }
 
 /// The `tag data body` part of `audio` FLV tag data.
 #[derive(Clone, Debug, PartialEq)]
 pub struct AudioTagBody {
     /// The actual `tag data body` of `audio` FLV tag data.
+    ///
+    /// Holds the codec configuration for `SequenceStart` and audio frames for `CodedFrames`,
+    /// the legacy `AACPacketType` byte is not included.
     pub data: Bytes,
+
+    /// The channel configuration for `MultichannelConfig` packets.
+    pub multichannel_config: Option<AudioMultichannelConfig>,
+
+    /// AAC config parsed from the sequence header, raw AAC frames get the last one.
+    pub aac_config: Option<AudioSpecificConfig>,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[repr(u8)]
+pub enum AudioPacketType {
+    SequenceStart = 0,
+    CodedFrames = 1,
+
+    /// Signals the end of the sequence, the body is empty.
+    SequenceEnd = 2,
+
+    /// The body carries `AudioMultichannelConfig` rather than audio data.
+    MultichannelConfig = 4,
+
+    /// Turns on audio multitrack mode
+    Multitrack = 5,
+
+    /// Same as `VideoPacketType::ModEx` but for audio packets.
+    ModEx = 7,
+
+    Unknown(u8),
+}
+
+impl From<u8> for AudioPacketType {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => AudioPacketType::SequenceStart,
+            1 => AudioPacketType::CodedFrames,
+            2 => AudioPacketType::SequenceEnd,
+            4 => AudioPacketType::MultichannelConfig,
+            5 => AudioPacketType::Multitrack,
+            7 => AudioPacketType::ModEx,
+            t => AudioPacketType::Unknown(t),
+        }
+    }
+}
+
+impl From<AudioPacketType> for u8 {
+    fn from(value: AudioPacketType) -> Self {
+        match value {
+            AudioPacketType::SequenceStart => 0,
+            AudioPacketType::CodedFrames => 1,
+            AudioPacketType::SequenceEnd => 2,
+            AudioPacketType::MultichannelConfig => 4,
+            AudioPacketType::Multitrack => 5,
+            AudioPacketType::ModEx => 7,
+            AudioPacketType::Unknown(t) => t,
+        }
+    }
+}
+
+/// Channel configuration of `MultichannelConfig` audio packet.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AudioMultichannelConfig {
+    /// Number of channels.
+    pub channel_count: u8,
+
+    /// Order of channels.
+    pub channel_order: AudioChannelOrder,
+}
+
+/// The order of audio channels.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AudioChannelOrder {
+    /// 0, Only the channel count is specified.
+    Unspecified,
+
+    /// 1, Native channel order, with the `AudioChannelMask` flags of present channels.
+    Native(u32),
+
+    /// 2, Custom channel order, with the `AudioChannel` of every channel.
+    Custom(Vec<u8>),
+
+    Unknown(u8),
+}
+
+impl AudioChannelOrder {
+    /// The channel order type.
+    pub fn order_type(&self) -> u8 {
+        match self {
+            AudioChannelOrder::Unspecified => 0,
+            AudioChannelOrder::Native(_) => 1,
+            AudioChannelOrder::Custom(_) => 2,
+            AudioChannelOrder::Unknown(t) => *t,
+        }
+    }
 }
 
 /// The audio format.
@@ -78,6 +281,9 @@ pub enum SoundFormat {
 
     /// 15, DeviceSpecific
     DeviceSpecific,
+
+    /// 12 and 13, reserved
+    Unknown(u8),
 }
 
 impl From<u8> for SoundFormat {
@@ -97,7 +303,50 @@ impl From<u8> for SoundFormat {
             11 => SoundFormat::Speex,
             14 => SoundFormat::MP3_8kHz,
             15 => SoundFormat::DeviceSpecific,
-            _ => unreachable!(),
+            t => SoundFormat::Unknown(t),
+        }
+    }
+}
+
+impl From<SoundFormat> for u8 {
+    fn from(value: SoundFormat) -> Self {
+        match value {
+            SoundFormat::PcmPlatformEndian => 0,
+            SoundFormat::ADPCM => 1,
+            SoundFormat::MP3 => 2,
+            SoundFormat::PcmLittleEndian => 3,
+            SoundFormat::Nellymoser16kHzMono => 4,
+            SoundFormat::Nellymoser8kHzMono => 5,
+            SoundFormat::Nellymoser => 6,
+            SoundFormat::PcmALaw => 7,
+            SoundFormat::PcmMuLaw => 8,
+            SoundFormat::ExHeader => 9,
+            SoundFormat::AAC => 10,
+            SoundFormat::Speex => 11,
+            SoundFormat::MP3_8kHz => 14,
+            SoundFormat::DeviceSpecific => 15,
+            SoundFormat::Unknown(t) => t,
+        }
+    }
+}
+
+impl From<SoundFormat> for Fourcc {
+    fn from(value: SoundFormat) -> Self {
+        match value {
+            SoundFormat::PcmPlatformEndian => Fourcc::from_static("lpcm"),
+            SoundFormat::ADPCM => Fourcc::from_static("adpc"),
+            SoundFormat::MP3 | SoundFormat::MP3_8kHz => Fourcc::AUDIO_MP3,
+            SoundFormat::PcmLittleEndian => Fourcc::from_static("sowt"),
+            SoundFormat::Nellymoser16kHzMono
+            | SoundFormat::Nellymoser8kHzMono
+            | SoundFormat::Nellymoser => Fourcc::from_static("nmos"),
+            SoundFormat::PcmALaw => Fourcc::from_static("alaw"),
+            SoundFormat::PcmMuLaw => Fourcc::from_static("ulaw"),
+            SoundFormat::AAC => Fourcc::AUDIO_AAC,
+            SoundFormat::Speex => Fourcc::from_static("spex"),
+            SoundFormat::ExHeader | SoundFormat::DeviceSpecific | SoundFormat::Unknown(_) => {
+                Fourcc::default()
+            }
         }
     }
 }
@@ -119,13 +368,24 @@ pub enum SoundRate {
 }
 
 impl From<u8> for SoundRate {
+    /// Only 2 lower bits are used.
     fn from(value: u8) -> Self {
-        match value {
+        match value & 0x03 {
             0 => SoundRate::_5_5KHZ,
             1 => SoundRate::_11KHZ,
             2 => SoundRate::_22KHZ,
-            3 => SoundRate::_44KHZ,
-            _ => unreachable!(),
+            _ => SoundRate::_44KHZ,
+        }
+    }
+}
+
+impl From<SoundRate> for u8 {
+    fn from(value: SoundRate) -> Self {
+        match value {
+            SoundRate::_5_5KHZ => 0,
+            SoundRate::_11KHZ => 1,
+            SoundRate::_22KHZ => 2,
+            SoundRate::_44KHZ => 3,
         }
     }
 }
@@ -141,11 +401,20 @@ pub enum SoundSize {
 }
 
 impl From<u8> for SoundSize {
+    /// Only the lowest bit is used.
     fn from(value: u8) -> Self {
-        match value {
+        match value & 1 {
             0 => SoundSize::_8Bit,
-            1 => SoundSize::_16Bit,
-            _ => unreachable!(),
+            _ => SoundSize::_16Bit,
+        }
+    }
+}
+
+impl From<SoundSize> for u8 {
+    fn from(value: SoundSize) -> Self {
+        match value {
+            SoundSize::_8Bit => 0,
+            SoundSize::_16Bit => 1,
         }
     }
 }
@@ -161,11 +430,20 @@ pub enum SoundType {
 }
 
 impl From<u8> for SoundType {
+    /// Only the lowest bit is used.
     fn from(value: u8) -> Self {
-        match value {
+        match value & 1 {
             0 => SoundType::Mono,
-            1 => SoundType::Stereo,
-            _ => unreachable!(),
+            _ => SoundType::Stereo,
+        }
+    }
+}
+
+impl From<SoundType> for u8 {
+    fn from(value: SoundType) -> Self {
+        match value {
+            SoundType::Mono => 0,
+            SoundType::Stereo => 1,
         }
     }
 }
@@ -199,12 +477,62 @@ impl From<u8> for AACPacketType {
     }
 }
 
+impl From<AACPacketType> for u8 {
+    fn from(value: AACPacketType) -> Self {
+        match value {
+            AACPacketType::SequenceHeader => 0,
+            AACPacketType::Raw => 1,
+        }
+    }
+}
+
 /// Parse AAC audio packet.
-pub fn aac_audio_packet(mut reader: Bytes) -> AACAudioPacket {
-    let packet_type = reader.get_u8();
+pub fn aac_audio_packet(mut reader: Bytes) -> std::io::Result<AACAudioPacket> {
+    let packet_type = reader.read_u8()?;
 
-    AACAudioPacket {
+    Ok(AACAudioPacket {
         packet_type: AACPacketType::from(packet_type),
         aac_data: reader,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use flowly::Fourcc;
+
+    use super::{AudioTagHeader, SoundFormat, SoundRate, SoundSize, SoundType, aac_audio_packet};
+    use crate::parser::{FlvParser, Parser};
+
+    #[test]
+    fn unknown_sound_format() {
+        for format in [12, 13] {
+            assert_eq!(SoundFormat::from(format), SoundFormat::Unknown(format));
+            assert_eq!(u8::from(SoundFormat::Unknown(format)), format);
+            assert_eq!(
+                Fourcc::from(SoundFormat::Unknown(format)),
+                Fourcc::default()
+            );
+
+            let mut data = Bytes::from(vec![format << 4 | 0x0f, 0x12, 0x34]);
+            let header: AudioTagHeader =
+                Parser::<flowly::Void, _>::parse(&mut FlvParser::default(), &mut data).unwrap();
+
+            assert_eq!(header.sound_format, SoundFormat::Unknown(format));
+            assert_eq!(header.sound_rate, SoundRate::_44KHZ);
+        }
+    }
+
+    #[test]
+    fn out_of_range_values() {
+        assert_eq!(SoundRate::from(0x07), SoundRate::_44KHZ);
+        assert_eq!(SoundRate::from(0xfc), SoundRate::_5_5KHZ);
+        assert_eq!(SoundSize::from(0x02), SoundSize::_8Bit);
+        assert_eq!(SoundType::from(0xff), SoundType::Stereo);
+    }
+
+    #[test]
+    fn empty_aac_packet() {
+        assert!(aac_audio_packet(Bytes::new()).is_err());
     }
 }
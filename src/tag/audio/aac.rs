@@ -0,0 +1,770 @@
+use std::io::ErrorKind;
+
+use bytes::{Buf, Bytes};
+
+use crate::{
+    bitreader::BitReader, bitwriter::BitWriter, error::Error, parser::Parser, reader::FlvReader,
+};
+
+/// Sampling frequencies by `samplingFrequencyIndex`, see ISO/IEC 14496-3, 1.6.3.3
+const SAMPLING_FREQUENCIES: [u32; 13] = [
+    96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350,
+];
+
+/// `samplingFrequencyIndex` signaling the explicit 24 bit frequency.
+const EXPLICIT_FREQUENCY_INDEX: u8 = 0x0F;
+
+/// `audioObjectType` escape value, the actual type follows in 6 bits.
+const AOT_ESCAPE: u8 = 31;
+
+pub const AOT_AAC_LC: u8 = 2;
+pub const AOT_SBR: u8 = 5;
+pub const AOT_ER_BSAC: u8 = 22;
+pub const AOT_PS: u8 = 29;
+
+/// `syncExtensionType` of the backward compatible SBR signaling.
+const SYNC_EXTENSION_SBR: u32 = 0x2B7;
+
+/// `syncExtensionType` of the backward compatible PS signaling.
+const SYNC_EXTENSION_PS: u32 = 0x548;
+
+/// ADTS `syncword`, 12 bits.
+const ADTS_SYNCWORD: u16 = 0xFFF;
+
+/// Size of ADTS header without and with CRC.
+const ADTS_HEADER_SIZE: usize = 7;
+const ADTS_HEADER_CRC_SIZE: usize = 9;
+
+/// Max ADTS `frame_length`, 13 bits.
+const ADTS_MAX_FRAME_LENGTH: usize = 0x1FFF;
+
+/// ADTS `adts_buffer_fullness` of variable bitrate streams.
+const ADTS_BUFFER_FULLNESS_VBR: u16 = 0x7FF;
+
+/// LOAS `syncword` of `AudioSyncStream`, 11 bits.
+const LOAS_SYNCWORD: u32 = 0x2B7;
+
+/// Size of `AudioSyncStream` header.
+const LOAS_HEADER_SIZE: usize = 3;
+
+/// Max LOAS `audioMuxLengthBytes`, 13 bits.
+const LOAS_MAX_MUX_LENGTH: usize = 0x1FFF;
+
+/// AudioSpecificConfig, see ISO/IEC 14496-3, 1.6.2.1
+///
+/// `GASpecificConfig` fields are set for general audio object types only, the parsing stops
+/// at `program_config_element` and configurations of other object types.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AudioSpecificConfig {
+    /// Object type of the core codec, e.g. 2 for AAC LC even if SBR is signaled by type 5.
+    pub object_type: u8,
+    pub sampling_frequency_index: u8,
+
+    /// Sampling frequency of the core codec in Hz, zero for reserved index.
+    pub sampling_frequency: u32,
+
+    /// 0 - defined by `program_config_element`, 1..=7 - see ISO/IEC 14496-3, 1.6.3.5
+    pub channel_configuration: u8,
+
+    /// Object type of the extension, 5 for SBR or 22 for ER BSAC, zero if not present.
+    pub extension_object_type: u8,
+
+    /// Sampling frequency of the extension in Hz, zero if not present.
+    pub extension_sampling_frequency: u32,
+
+    /// SBR presence, `None` if not signaled explicitly.
+    pub sbr_present: Option<bool>,
+
+    /// PS presence, `None` if not signaled explicitly.
+    pub ps_present: Option<bool>,
+
+    /// 960 samples per frame instead of 1024.
+    pub frame_length_flag: bool,
+    pub depends_on_core_coder: bool,
+    pub core_coder_delay: u16,
+    pub extension_flag: bool,
+}
+
+impl AudioSpecificConfig {
+    /// Parse the config from the data of AAC sequence header.
+    pub fn parse(data: &[u8]) -> std::io::Result<Self> {
+        let mut r = BitReader::new(data);
+        let mut x = AudioSpecificConfig {
+            object_type: read_object_type(&mut r)?,
+            ..Default::default()
+        };
+
+        (x.sampling_frequency_index, x.sampling_frequency) = read_sampling_frequency(&mut r)?;
+        x.channel_configuration = r.read_u8(4)?;
+
+        if x.object_type == AOT_SBR || x.object_type == AOT_PS {
+            // explicit hierarchical signaling of SBR and PS
+            x.extension_object_type = AOT_SBR;
+            x.sbr_present = Some(true);
+
+            if x.object_type == AOT_PS {
+                x.ps_present = Some(true);
+            }
+
+            (_, x.extension_sampling_frequency) = read_sampling_frequency(&mut r)?;
+            x.object_type = read_object_type(&mut r)?;
+
+            if x.object_type == AOT_ER_BSAC {
+                // extensionChannelConfiguration
+                r.skip_bits(4)?;
+            }
+        }
+
+        if !x.parse_ga_specific_config(&mut r)? {
+            return Ok(x);
+        }
+
+        if matches!(x.object_type, 17 | 19..=27 | 39) {
+            let ep_config = r.read_u8(2)?;
+
+            if ep_config == 2 || ep_config == 3 {
+                // ErrorProtectionSpecificConfig is not supported
+                return Ok(x);
+            }
+        }
+
+        if x.extension_object_type != AOT_SBR && r.remaining() >= 16 {
+            x.parse_sync_extension(&mut r)?;
+        }
+
+        Ok(x)
+    }
+
+    /// Parse `GASpecificConfig`, returns `false` if the rest of the config can not be parsed.
+    fn parse_ga_specific_config(&mut self, r: &mut BitReader<'_>) -> std::io::Result<bool> {
+        if !matches!(self.object_type, 1..=4 | 6 | 7 | 17 | 19..=23) {
+            return Ok(false);
+        }
+
+        self.frame_length_flag = r.read_bit()?;
+        self.depends_on_core_coder = r.read_bit()?;
+
+        if self.depends_on_core_coder {
+            self.core_coder_delay = r.read_u32(14)? as u16;
+        }
+
+        self.extension_flag = r.read_bit()?;
+
+        if self.channel_configuration == 0 {
+            // program_config_element is not supported
+            return Ok(false);
+        }
+
+        if self.object_type == 6 || self.object_type == 20 {
+            // layerNr
+            r.skip_bits(3)?;
+        }
+
+        if self.extension_flag {
+            if self.object_type == AOT_ER_BSAC {
+                // numOfSubFrame, layer_length
+                r.skip_bits(5 + 11)?;
+            }
+
+            if matches!(self.object_type, 17 | 19 | 20 | 23) {
+                // aacSectionDataResilienceFlag, aacScalefactorDataResilienceFlag,
+                // aacSpectralDataResilienceFlag
+                r.skip_bits(3)?;
+            }
+
+            // extensionFlag3
+            r.skip_bits(1)?;
+        }
+
+        Ok(true)
+    }
+
+    /// Parse backward compatible signaling of SBR and PS.
+    fn parse_sync_extension(&mut self, r: &mut BitReader<'_>) -> std::io::Result<()> {
+        if r.read_u32(11)? != SYNC_EXTENSION_SBR {
+            return Ok(());
+        }
+
+        self.extension_object_type = read_object_type(r)?;
+
+        match self.extension_object_type {
+            AOT_SBR => {
+                let sbr_present = r.read_bit()?;
+                self.sbr_present = Some(sbr_present);
+
+                if sbr_present {
+                    (_, self.extension_sampling_frequency) = read_sampling_frequency(r)?;
+
+                    if r.remaining() >= 12 && r.read_u32(11)? == SYNC_EXTENSION_PS {
+                        self.ps_present = Some(r.read_bit()?);
+                    }
+                }
+            }
+            AOT_ER_BSAC => {
+                let sbr_present = r.read_bit()?;
+                self.sbr_present = Some(sbr_present);
+
+                if sbr_present {
+                    (_, self.extension_sampling_frequency) = read_sampling_frequency(r)?;
+                }
+
+                // extensionChannelConfiguration
+                r.skip_bits(4)?;
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    /// Serialize the config for AAC sequence header.
+    ///
+    /// SBR and PS are signaled explicitly with the hierarchical object types 5 and 29.
+    pub fn to_bytes(&self) -> std::io::Result<Bytes> {
+        let mut w = BitWriter::new();
+        self.write_bits(&mut w)?;
+
+        Ok(w.into_bytes())
+    }
+
+    fn write_bits(&self, w: &mut BitWriter) -> std::io::Result<()> {
+        if !matches!(self.object_type, 1..=4 | 6 | 7 | 17 | 19..=23) {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("unsupported aac object type {}", self.object_type),
+            ));
+        }
+
+        if self.channel_configuration == 0 {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "aac program_config_element is not supported",
+            ));
+        }
+
+        let sbr = self.sbr_present == Some(true) && self.extension_sampling_frequency > 0;
+
+        if sbr {
+            let object_type = if self.ps_present == Some(true) {
+                AOT_PS
+            } else {
+                AOT_SBR
+            };
+
+            write_object_type(w, object_type);
+            write_sampling_frequency(w, self.sampling_frequency_index, self.sampling_frequency);
+            w.write_bits(self.channel_configuration as u64, 4);
+            write_sampling_frequency(
+                w,
+                sampling_frequency_index(self.extension_sampling_frequency),
+                self.extension_sampling_frequency,
+            );
+            write_object_type(w, self.object_type);
+        } else {
+            write_object_type(w, self.object_type);
+            write_sampling_frequency(w, self.sampling_frequency_index, self.sampling_frequency);
+            w.write_bits(self.channel_configuration as u64, 4);
+        }
+
+        if self.object_type == AOT_ER_BSAC && sbr {
+            // extensionChannelConfiguration
+            w.write_bits(self.channel_configuration as u64, 4);
+        }
+
+        // GASpecificConfig
+        w.write_bit(self.frame_length_flag);
+        w.write_bit(self.depends_on_core_coder);
+
+        if self.depends_on_core_coder {
+            w.write_bits(self.core_coder_delay as u64, 14);
+        }
+
+        w.write_bit(self.extension_flag);
+
+        if self.object_type == 6 || self.object_type == 20 {
+            // layerNr
+            w.write_bits(0, 3);
+        }
+
+        if self.extension_flag {
+            if self.object_type == AOT_ER_BSAC {
+                // numOfSubFrame, layer_length
+                w.write_bits(0, 5 + 11);
+            }
+
+            if matches!(self.object_type, 17 | 19 | 20 | 23) {
+                // resilience flags
+                w.write_bits(0, 3);
+            }
+
+            // extensionFlag3
+            w.write_bit(false);
+        }
+
+        if matches!(self.object_type, 17 | 19..=23) {
+            // epConfig
+            w.write_bits(0, 2);
+        }
+
+        Ok(())
+    }
+
+    /// ADTS header of the frame with `data_len` bytes of raw AAC data.
+    ///
+    /// ADTS can carry AAC Main, LC, SSR and LTP with the indexed sampling frequency only.
+    pub fn adts_header(&self, data_len: usize) -> std::io::Result<AdtsHeader> {
+        if !matches!(self.object_type, 1..=4) {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "aac object type {} can not be carried in adts",
+                    self.object_type
+                ),
+            ));
+        }
+
+        if self.sampling_frequency_index >= SAMPLING_FREQUENCIES.len() as u8 {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "explicit sampling frequency can not be carried in adts",
+            ));
+        }
+
+        let frame_length = ADTS_HEADER_SIZE + data_len;
+
+        if frame_length > ADTS_MAX_FRAME_LENGTH {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("adts frame length {} is too large", frame_length),
+            ));
+        }
+
+        Ok(AdtsHeader {
+            mpeg2: false,
+            protection_absent: true,
+            object_type: self.object_type,
+            sampling_frequency_index: self.sampling_frequency_index,
+            channel_configuration: self.channel_configuration,
+            frame_length: frame_length as u16,
+            buffer_fullness: ADTS_BUFFER_FULLNESS_VBR,
+            raw_data_blocks: 1,
+        })
+    }
+
+    /// Wrap raw AAC frame into ADTS frame.
+    pub fn adts_frame(&self, data: &[u8]) -> std::io::Result<Bytes> {
+        let mut frame = Vec::with_capacity(ADTS_HEADER_SIZE + data.len());
+
+        frame.extend_from_slice(&self.adts_header(data.len())?.to_bytes());
+        frame.extend_from_slice(data);
+
+        Ok(frame.into())
+    }
+
+    /// Wrap raw AAC frame into LOAS `AudioSyncStream` frame carrying LATM `AudioMuxElement`.
+    ///
+    /// Each frame has `StreamMuxConfig` with this config, so decoding may start at any frame.
+    pub fn loas_frame(&self, data: &[u8]) -> std::io::Result<Bytes> {
+        let mut w = BitWriter::new();
+
+        // AudioSyncStream, audioMuxLengthBytes is patched below
+        w.write_bits(LOAS_SYNCWORD as u64, 11);
+        w.write_bits(0, 13);
+
+        // useSameStreamMux
+        w.write_bit(false);
+
+        // StreamMuxConfig: audioMuxVersion, allStreamsSameTimeFraming, numSubFrames,
+        // numProgram, numLayer
+        w.write_bit(false);
+        w.write_bit(true);
+        w.write_bits(0, 6 + 4 + 3);
+
+        self.write_bits(&mut w)?;
+
+        // frameLengthType, latmBufferFullness, otherDataPresent, crcCheckPresent
+        w.write_bits(0, 3);
+        w.write_bits(0xFF, 8);
+        w.write_bit(false);
+        w.write_bit(false);
+
+        // PayloadLengthInfo
+        for _ in 0..data.len() / 255 {
+            w.write_bits(255, 8);
+        }
+        w.write_bits((data.len() % 255) as u64, 8);
+
+        w.write_bytes(data);
+
+        let mux_length = w.len().div_ceil(8) - LOAS_HEADER_SIZE;
+
+        if mux_length > LOAS_MAX_MUX_LENGTH {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("loas frame length {} is too large", mux_length),
+            ));
+        }
+
+        let mut frame = Vec::from(w.into_bytes());
+        frame[1] |= (mux_length >> 8) as u8;
+        frame[2] = mux_length as u8;
+
+        Ok(frame.into())
+    }
+
+    /// Number of channels, zero if defined by `program_config_element`.
+    pub fn channel_count(&self) -> u8 {
+        match self.channel_configuration {
+            1..=6 => self.channel_configuration,
+            7 => 8,
+            _ => 0,
+        }
+    }
+
+    /// Output sampling frequency in Hz, doubled by SBR.
+    pub fn output_sampling_frequency(&self) -> u32 {
+        if self.sbr_present == Some(true) && self.extension_sampling_frequency > 0 {
+            self.extension_sampling_frequency
+        } else {
+            self.sampling_frequency
+        }
+    }
+
+    /// Number of output samples per frame.
+    pub fn frame_length(&self) -> u32 {
+        let length = if self.frame_length_flag { 960 } else { 1024 };
+
+        if self.sbr_present == Some(true) {
+            length * 2
+        } else {
+            length
+        }
+    }
+}
+
+fn read_object_type(r: &mut BitReader<'_>) -> std::io::Result<u8> {
+    let object_type = r.read_u8(5)?;
+
+    if object_type == AOT_ESCAPE {
+        return Ok(32 + r.read_u8(6)?);
+    }
+
+    Ok(object_type)
+}
+
+fn write_object_type(w: &mut BitWriter, object_type: u8) {
+    if object_type >= 32 {
+        w.write_bits(AOT_ESCAPE as u64, 5);
+        w.write_bits((object_type - 32) as u64, 6);
+    } else {
+        w.write_bits(object_type as u64, 5);
+    }
+}
+
+/// Index of the sampling frequency, `EXPLICIT_FREQUENCY_INDEX` if not in the table.
+fn sampling_frequency_index(frequency: u32) -> u8 {
+    SAMPLING_FREQUENCIES
+        .iter()
+        .position(|&x| x == frequency)
+        .map_or(EXPLICIT_FREQUENCY_INDEX, |x| x as u8)
+}
+
+fn write_sampling_frequency(w: &mut BitWriter, index: u8, frequency: u32) {
+    if index < SAMPLING_FREQUENCIES.len() as u8 {
+        w.write_bits(index as u64, 4);
+    } else {
+        w.write_bits(EXPLICIT_FREQUENCY_INDEX as u64, 4);
+        w.write_bits(frequency as u64, 24);
+    }
+}
+
+/// Read sampling frequency index followed by the explicit frequency if signaled.
+fn read_sampling_frequency(r: &mut BitReader<'_>) -> std::io::Result<(u8, u32)> {
+    let index = r.read_u8(4)?;
+
+    let frequency = if index == EXPLICIT_FREQUENCY_INDEX {
+        r.read_u32(24)?
+    } else {
+        SAMPLING_FREQUENCIES
+            .get(index as usize)
+            .copied()
+            .unwrap_or_default()
+    };
+
+    Ok((index, frequency))
+}
+
+/// ADTS frame header, see ISO/IEC 14496-3, 1.A.2.2
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AdtsHeader {
+    /// MPEG-2 AAC stream, MPEG-4 otherwise.
+    pub mpeg2: bool,
+
+    /// No CRC following the header.
+    pub protection_absent: bool,
+
+    /// Audio object type, the `profile` field plus one.
+    pub object_type: u8,
+    pub sampling_frequency_index: u8,
+    pub channel_configuration: u8,
+
+    /// Length of the frame including the header.
+    pub frame_length: u16,
+    pub buffer_fullness: u16,
+
+    /// Number of raw data blocks in the frame.
+    pub raw_data_blocks: u8,
+}
+
+impl AdtsHeader {
+    /// Parse the header at the start of `data`.
+    pub fn parse(data: &[u8]) -> std::io::Result<Self> {
+        let mut r = BitReader::new(data);
+
+        if r.read_u32(12)? as u16 != ADTS_SYNCWORD {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidData,
+                "invalid adts syncword",
+            ));
+        }
+
+        let mpeg2 = r.read_bit()?;
+
+        // layer
+        r.skip_bits(2)?;
+
+        let protection_absent = r.read_bit()?;
+        let object_type = r.read_u8(2)? + 1;
+        let sampling_frequency_index = r.read_u8(4)?;
+
+        // private_bit
+        r.skip_bits(1)?;
+
+        let channel_configuration = r.read_u8(3)?;
+
+        // original_copy, home, copyright_identification_bit, copyright_identification_start
+        r.skip_bits(4)?;
+
+        Ok(AdtsHeader {
+            mpeg2,
+            protection_absent,
+            object_type,
+            sampling_frequency_index,
+            channel_configuration,
+            frame_length: r.read_u32(13)? as u16,
+            buffer_fullness: r.read_u32(11)? as u16,
+            raw_data_blocks: r.read_u8(2)? + 1,
+        })
+    }
+
+    /// Size of the header including CRC.
+    #[inline]
+    pub fn header_size(&self) -> usize {
+        if self.protection_absent {
+            ADTS_HEADER_SIZE
+        } else {
+            ADTS_HEADER_CRC_SIZE
+        }
+    }
+
+    /// Serialize the header without CRC.
+    pub fn to_bytes(&self) -> [u8; ADTS_HEADER_SIZE] {
+        let mut w = BitWriter::new();
+
+        w.write_bits(ADTS_SYNCWORD as u64, 12);
+        w.write_bit(self.mpeg2);
+        w.write_bits(0, 2);
+        w.write_bit(true);
+        w.write_bits((self.object_type.saturating_sub(1) & 0x03) as u64, 2);
+        w.write_bits(self.sampling_frequency_index as u64, 4);
+        w.write_bit(false);
+        w.write_bits(self.channel_configuration as u64, 3);
+        w.write_bits(0, 4);
+        w.write_bits(self.frame_length as u64, 13);
+        w.write_bits(self.buffer_fullness as u64, 11);
+        w.write_bits(self.raw_data_blocks.saturating_sub(1) as u64, 2);
+
+        let mut header = [0; ADTS_HEADER_SIZE];
+        header.copy_from_slice(&w.into_bytes());
+        header
+    }
+
+    /// AudioSpecificConfig equivalent to the header.
+    pub fn config(&self) -> AudioSpecificConfig {
+        AudioSpecificConfig {
+            object_type: self.object_type,
+            sampling_frequency_index: self.sampling_frequency_index,
+            sampling_frequency: SAMPLING_FREQUENCIES
+                .get(self.sampling_frequency_index as usize)
+                .copied()
+                .unwrap_or_default(),
+            channel_configuration: self.channel_configuration,
+            ..Default::default()
+        }
+    }
+}
+
+/// Split ADTS frame off the start of `data`, returns `None` if the frame is incomplete.
+///
+/// The returned data is the raw AAC frame, as carried by FLV `CodedFrames` packets.
+pub fn read_adts_frame(data: &mut Bytes) -> std::io::Result<Option<(AdtsHeader, Bytes)>> {
+    if data.len() < ADTS_HEADER_SIZE {
+        return Ok(None);
+    }
+
+    let header = AdtsHeader::parse(data)?;
+    let frame_length = header.frame_length as usize;
+
+    if frame_length < header.header_size() {
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidData,
+            format!("invalid adts frame length {}", frame_length),
+        ));
+    }
+
+    if header.raw_data_blocks > 1 {
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidData,
+            "multiple raw data blocks in adts frame are not supported",
+        ));
+    }
+
+    if data.len() < frame_length {
+        return Ok(None);
+    }
+
+    let mut frame = data.split_to(frame_length);
+    frame.advance(header.header_size());
+
+    Ok(Some((header, frame)))
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct AacParser {
+    /// The last configuration.
+    pub config: Option<AudioSpecificConfig>,
+}
+
+impl<E> Parser<E, AudioSpecificConfig> for AacParser {
+    type Error = Error<E>;
+
+    /// Parse `AudioSpecificConfig` of AAC sequence header.
+    fn parse(&mut self, reader: &mut impl FlvReader) -> Result<AudioSpecificConfig, Self::Error> {
+        let config = AudioSpecificConfig::parse(&reader.read_to_end()?)?;
+
+        if self.config != Some(config) {
+            log::debug!(
+                "aac config changed: object type {}, {} Hz, {} channels",
+                config.object_type,
+                config.output_sampling_frequency(),
+                config.channel_count()
+            );
+        }
+
+        self.config = Some(config);
+
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::{AOT_AAC_LC, AOT_SBR, AdtsHeader, AudioSpecificConfig, read_adts_frame};
+
+    #[test]
+    fn aac_lc() {
+        let config = AudioSpecificConfig::parse(&[0x12, 0x10]).unwrap();
+
+        assert_eq!(config.object_type, AOT_AAC_LC);
+        assert_eq!(config.sampling_frequency, 44100);
+        assert_eq!(config.output_sampling_frequency(), 44100);
+        assert_eq!(config.channel_count(), 2);
+        assert_eq!(config.frame_length(), 1024);
+        assert_eq!(config.sbr_present, None);
+        assert_eq!(config.to_bytes().unwrap(), &[0x12, 0x10][..]);
+
+        let config = AudioSpecificConfig::parse(&[0x11, 0x90]).unwrap();
+        assert_eq!(config.output_sampling_frequency(), 48000);
+        assert_eq!(config.channel_count(), 2);
+    }
+
+    #[test]
+    fn he_aac() {
+        // explicit hierarchical signaling of SBR
+        let data = [0x2b, 0x92, 0x08, 0x00];
+        let config = AudioSpecificConfig::parse(&data).unwrap();
+
+        assert_eq!(config.object_type, AOT_AAC_LC);
+        assert_eq!(config.sampling_frequency, 22050);
+        assert_eq!(config.extension_object_type, AOT_SBR);
+        assert_eq!(config.output_sampling_frequency(), 44100);
+        assert_eq!(config.frame_length(), 2048);
+        assert_eq!((config.sbr_present, config.ps_present), (Some(true), None));
+        assert_eq!(config.to_bytes().unwrap(), &data[..]);
+
+        // explicit hierarchical signaling of PS
+        let data = [0xeb, 0x92, 0x08, 0x00];
+        let config = AudioSpecificConfig::parse(&data).unwrap();
+
+        assert_eq!(
+            (config.sbr_present, config.ps_present),
+            (Some(true), Some(true))
+        );
+        assert_eq!(config.to_bytes().unwrap(), &data[..]);
+
+        // backward compatible signaling of SBR absence
+        let config = AudioSpecificConfig::parse(&[0x12, 0x10, 0x56, 0xe5, 0x00]).unwrap();
+
+        assert_eq!(config.extension_object_type, AOT_SBR);
+        assert_eq!(config.sbr_present, Some(false));
+        assert_eq!(config.output_sampling_frequency(), 44100);
+    }
+
+    #[test]
+    fn adts_round_trip() {
+        let config = AudioSpecificConfig::parse(&[0x12, 0x10]).unwrap();
+        let payload = vec![0x21; 300];
+
+        let frame = config.adts_frame(&payload).unwrap();
+        assert_eq!(frame[..7], [0xff, 0xf1, 0x50, 0x80, 0x26, 0x7f, 0xfc]);
+        assert_eq!(frame[7..], payload);
+
+        let header = AdtsHeader::parse(&frame).unwrap();
+        assert!(header.protection_absent);
+        assert_eq!(header.frame_length, 307);
+        assert_eq!(header.raw_data_blocks, 1);
+        assert_eq!(header.config(), config);
+        assert_eq!(header.to_bytes(), frame[..7]);
+
+        // two frames, the second one is incomplete
+        let mut data = [&frame[..], &frame[..100]].concat().into();
+        let (header, raw) = read_adts_frame(&mut data).unwrap().unwrap();
+        assert_eq!(header.config(), config);
+        assert_eq!(raw, payload);
+        assert_eq!(read_adts_frame(&mut data).unwrap(), None);
+        assert_eq!(data.len(), 100);
+
+        assert!(read_adts_frame(&mut Bytes::from_static(&[0; 8])).is_err());
+    }
+
+    #[test]
+    fn loas() {
+        let config = AudioSpecificConfig::parse(&[0x11, 0x90]).unwrap();
+        let payload = vec![0x21; 300];
+
+        let frame = config.loas_frame(&payload).unwrap();
+        assert_eq!(frame.len(), 311);
+        assert_eq!(
+            frame[..9],
+            [0x56, 0xe1, 0x34, 0x20, 0x00, 0x11, 0x90, 0x1f, 0xe7]
+        );
+    }
+
+    #[test]
+    fn truncated() {
+        assert!(AudioSpecificConfig::parse(&[]).is_err());
+        assert!(AudioSpecificConfig::parse(&[0x12]).is_err());
+    }
+}
@@ -1,12 +1,18 @@
-use std::collections::HashMap;
-
 use bytes::Bytes;
+use indexmap::IndexMap;
+
+pub mod on_metadata;
+
+pub use on_metadata::OnMetaData;
 
 /// The tag data part of `script` FLV tag, including `name` and `value`.
 /// The `name` is a `ScriptDataValue` enum whose type is `String`.
 /// The `value` is a `ScriptDataValue` enum whose type is `ECMAArray`.
 #[derive(Clone, Debug, PartialEq)]
 pub struct MetaTag {
+    /// Name of the command wrapping the data frame, usually `@setDataFrame`.
+    pub wrapper: Option<Bytes>,
+
     /// Method or object name.
     /// ScriptTagValue.Type = 2 (String)
     pub name: Bytes,
@@ -29,7 +35,7 @@ pub enum MetaDataValue {
     String(Bytes),
 
     /// 3, Object value.
-    Object(HashMap<Bytes, MetaDataValue>),
+    Object(IndexMap<Bytes, MetaDataValue>),
 
     /// 4, MovieClip value.
     MovieClip,
@@ -44,7 +50,10 @@ pub enum MetaDataValue {
     Reference(u16),
 
     /// 8, ECMA Array value.
-    ECMAArray(HashMap<Bytes, MetaDataValue>),
+    ///
+    /// Properties in the order of appearance and the declared array length, which is only
+    /// a hint and often does not match the number of properties.
+    ECMAArray(IndexMap<Bytes, MetaDataValue>, u32),
 
     /// 10, Strict Array value.
     StrictArray(Vec<MetaDataValue>),
@@ -58,6 +67,22 @@ pub enum MetaDataValue {
     Unknown(u8),
 }
 
+/// Kind of the value holding object properties, see `MetaDataValue`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MetaContainer {
+    /// 3, Object value.
+    Object,
+
+    /// 8, ECMA Array value with the declared length, `None` to use the number of properties.
+    ECMAArray(Option<u32>),
+}
+
+impl Default for MetaContainer {
+    fn default() -> Self {
+        MetaContainer::ECMAArray(None)
+    }
+}
+
 /// The `ScriptDataObjectProperty` is the component of `Object` and `ECMAArray`,
 /// which are a kind of `ScriptDataValue`.
 #[derive(Clone, Debug, PartialEq)]
@@ -0,0 +1,304 @@
+use bytes::Bytes;
+use flowly::Fourcc;
+use indexmap::IndexMap;
+
+use super::{MetaContainer, MetaDataValue};
+
+/// Name of the script tag carrying `OnMetaData`.
+pub const ON_METADATA: &[u8] = b"onMetaData";
+
+/// Typed representation of the `onMetaData` script tag.
+///
+/// Properties with an unexpected type or not known to this struct are kept in `extra`,
+/// so the conversion back into `MetaDataValue` does not lose anything.
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct OnMetaData {
+    /// Total duration of the file in seconds.
+    pub duration: Option<f64>,
+
+    /// Width of the video in pixels.
+    pub width: Option<f64>,
+
+    /// Height of the video in pixels.
+    pub height: Option<f64>,
+
+    /// Video bitrate in kilobits per second.
+    pub videodatarate: Option<f64>,
+
+    /// Number of frames per second.
+    pub framerate: Option<f64>,
+
+    /// Video codec ID (or FourCC as a number for Enhanced RTMP).
+    pub videocodecid: Option<f64>,
+
+    /// Audio codec ID (or FourCC as a number for Enhanced RTMP).
+    pub audiocodecid: Option<f64>,
+
+    /// Frequency at which the audio stream is replayed.
+    pub audiosamplerate: Option<f64>,
+
+    /// Indicating stereo audio.
+    pub stereo: Option<bool>,
+
+    /// Total size of the file in bytes.
+    pub filesize: Option<f64>,
+
+    /// Name of the encoder.
+    pub encoder: Option<Bytes>,
+
+    /// Keyframes index.
+    pub keyframes: Option<MetaDataKeyframes>,
+
+    /// Enhanced RTMP video FourCC.
+    pub video_four_cc: Option<Fourcc>,
+
+    /// Enhanced RTMP audio FourCC.
+    pub audio_four_cc: Option<Fourcc>,
+
+    /// Enhanced RTMP per-track audio properties keyed by track id.
+    pub audio_track_id_info_map: Option<IndexMap<Bytes, MetaDataValue>>,
+
+    /// Enhanced RTMP per-track video properties keyed by track id.
+    pub video_track_id_info_map: Option<IndexMap<Bytes, MetaDataValue>>,
+
+    /// Other properties.
+    pub extra: IndexMap<Bytes, MetaDataValue>,
+
+    /// Name of the command wrapping the data frame, usually `@setDataFrame`.
+    pub wrapper: Option<Bytes>,
+
+    /// Kind of the value the properties were read from and are written back into.
+    pub container: MetaContainer,
+
+    /// Order of the properties as read, new properties are written after them.
+    pub key_order: Vec<Bytes>,
+}
+
+/// Keyframes index of `onMetaData`.
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct MetaDataKeyframes {
+    /// Timestamps of keyframes in seconds.
+    pub times: Vec<f64>,
+
+    /// Byte offsets of keyframes in the file.
+    pub filepositions: Vec<f64>,
+}
+
+impl OnMetaData {
+    /// Build from `Object` or `ECMAArray` value.
+    pub fn from_value(value: MetaDataValue) -> Option<Self> {
+        let (props, container) = match value {
+            MetaDataValue::Object(props) => (props, MetaContainer::Object),
+            MetaDataValue::ECMAArray(props, len) => (props, MetaContainer::ECMAArray(Some(len))),
+            _ => return None,
+        };
+
+        Some(OnMetaData {
+            container,
+            ..props.into()
+        })
+    }
+}
+
+fn take_with<T>(
+    props: &mut IndexMap<Bytes, MetaDataValue>,
+    key: &'static str,
+    f: impl FnOnce(&MetaDataValue) -> Option<T>,
+) -> Option<T> {
+    let value = f(props.get(key.as_bytes())?)?;
+    props.shift_remove(key.as_bytes());
+
+    Some(value)
+}
+
+pub(crate) fn take_number(
+    props: &mut IndexMap<Bytes, MetaDataValue>,
+    key: &'static str,
+) -> Option<f64> {
+    take_with(props, key, |x| match x {
+        MetaDataValue::Number(v) => Some(*v),
+        _ => None,
+    })
+}
+
+fn take_fourcc(props: &mut IndexMap<Bytes, MetaDataValue>, key: &'static str) -> Option<Fourcc> {
+    take_with(props, key, |x| match x {
+        MetaDataValue::String(v) => <&[u8; 4]>::try_from(v.as_ref()).ok().map(Fourcc::from),
+        _ => None,
+    })
+}
+
+pub(crate) fn take_object(
+    props: &mut IndexMap<Bytes, MetaDataValue>,
+    key: &'static str,
+) -> Option<IndexMap<Bytes, MetaDataValue>> {
+    if !matches!(props.get(key.as_bytes()), Some(MetaDataValue::Object(_))) {
+        return None;
+    }
+
+    match props.shift_remove(key.as_bytes()) {
+        Some(MetaDataValue::Object(v)) => Some(v),
+        _ => None,
+    }
+}
+
+fn numbers(value: &MetaDataValue) -> Option<Vec<f64>> {
+    match value {
+        MetaDataValue::StrictArray(arr) => arr
+            .iter()
+            .map(|x| match x {
+                MetaDataValue::Number(v) => Some(*v),
+                _ => None,
+            })
+            .collect(),
+        _ => None,
+    }
+}
+
+fn keyframes(value: &MetaDataValue) -> Option<MetaDataKeyframes> {
+    match value {
+        MetaDataValue::Object(props) if props.len() == 2 => Some(MetaDataKeyframes {
+            times: numbers(props.get(&b"times"[..])?)?,
+            filepositions: numbers(props.get(&b"filepositions"[..])?)?,
+        }),
+        _ => None,
+    }
+}
+
+fn put(
+    props: &mut IndexMap<Bytes, MetaDataValue>,
+    key: &'static str,
+    value: Option<MetaDataValue>,
+) {
+    if let Some(value) = value {
+        props.insert(Bytes::from_static(key.as_bytes()), value);
+    }
+}
+
+fn fourcc_value(fourcc: Fourcc) -> MetaDataValue {
+    MetaDataValue::String(Bytes::copy_from_slice(&<[u8; 4]>::from(fourcc)))
+}
+
+impl From<IndexMap<Bytes, MetaDataValue>> for OnMetaData {
+    fn from(mut props: IndexMap<Bytes, MetaDataValue>) -> Self {
+        let key_order = props.keys().cloned().collect();
+
+        OnMetaData {
+            duration: take_number(&mut props, "duration"),
+            width: take_number(&mut props, "width"),
+            height: take_number(&mut props, "height"),
+            videodatarate: take_number(&mut props, "videodatarate"),
+            framerate: take_number(&mut props, "framerate"),
+            videocodecid: take_number(&mut props, "videocodecid"),
+            audiocodecid: take_number(&mut props, "audiocodecid"),
+            audiosamplerate: take_number(&mut props, "audiosamplerate"),
+            stereo: take_with(&mut props, "stereo", |x| match x {
+                MetaDataValue::Boolean(v) => Some(*v),
+                _ => None,
+            }),
+            filesize: take_number(&mut props, "filesize"),
+            encoder: take_with(&mut props, "encoder", |x| match x {
+                MetaDataValue::String(v) => Some(v.clone()),
+                _ => None,
+            }),
+            keyframes: take_with(&mut props, "keyframes", keyframes),
+            video_four_cc: take_fourcc(&mut props, "videoFourCc"),
+            audio_four_cc: take_fourcc(&mut props, "audioFourCc"),
+            audio_track_id_info_map: take_object(&mut props, "audioTrackIdInfoMap"),
+            video_track_id_info_map: take_object(&mut props, "videoTrackIdInfoMap"),
+            extra: props,
+            wrapper: None,
+            container: MetaContainer::default(),
+            key_order,
+        }
+    }
+}
+
+impl From<OnMetaData> for IndexMap<Bytes, MetaDataValue> {
+    fn from(value: OnMetaData) -> Self {
+        let mut props = value.extra;
+        let number = |x: Option<f64>| x.map(MetaDataValue::Number);
+
+        put(&mut props, "duration", number(value.duration));
+        put(&mut props, "width", number(value.width));
+        put(&mut props, "height", number(value.height));
+        put(&mut props, "videodatarate", number(value.videodatarate));
+        put(&mut props, "framerate", number(value.framerate));
+        put(&mut props, "videocodecid", number(value.videocodecid));
+        put(&mut props, "audiocodecid", number(value.audiocodecid));
+        put(&mut props, "audiosamplerate", number(value.audiosamplerate));
+        put(
+            &mut props,
+            "stereo",
+            value.stereo.map(MetaDataValue::Boolean),
+        );
+        put(&mut props, "filesize", number(value.filesize));
+        put(
+            &mut props,
+            "encoder",
+            value.encoder.map(MetaDataValue::String),
+        );
+        put(
+            &mut props,
+            "keyframes",
+            value.keyframes.map(|kf| {
+                let numbers = |v: Vec<f64>| {
+                    MetaDataValue::StrictArray(v.into_iter().map(MetaDataValue::Number).collect())
+                };
+
+                let mut obj = IndexMap::new();
+                put(&mut obj, "times", Some(numbers(kf.times)));
+                put(&mut obj, "filepositions", Some(numbers(kf.filepositions)));
+
+                MetaDataValue::Object(obj)
+            }),
+        );
+        put(
+            &mut props,
+            "videoFourCc",
+            value.video_four_cc.map(fourcc_value),
+        );
+        put(
+            &mut props,
+            "audioFourCc",
+            value.audio_four_cc.map(fourcc_value),
+        );
+        put(
+            &mut props,
+            "audioTrackIdInfoMap",
+            value.audio_track_id_info_map.map(MetaDataValue::Object),
+        );
+        put(
+            &mut props,
+            "videoTrackIdInfoMap",
+            value.video_track_id_info_map.map(MetaDataValue::Object),
+        );
+
+        if !value.key_order.is_empty() {
+            props.sort_by_cached_key(|key, _| {
+                value
+                    .key_order
+                    .iter()
+                    .position(|x| x == key)
+                    .unwrap_or(usize::MAX)
+            });
+        }
+
+        props
+    }
+}
+
+impl From<OnMetaData> for MetaDataValue {
+    fn from(value: OnMetaData) -> Self {
+        let container = value.container;
+        let props: IndexMap<Bytes, MetaDataValue> = value.into();
+
+        match container {
+            MetaContainer::Object => MetaDataValue::Object(props),
+            MetaContainer::ECMAArray(len) => {
+                let len = len.unwrap_or(props.len() as u32);
+                MetaDataValue::ECMAArray(props, len)
+            }
+        }
+    }
+}
@@ -1,7 +1,15 @@
 use bytes::Bytes;
 use flowly::Fourcc;
 
+use color_info::VideoColorInfo;
+
+pub mod av1;
+pub mod color_info;
+pub mod hevc;
 pub mod mpeg4_avc;
+pub mod vp9;
+
+mod vui;
 
 /// The tag data part of `video` FLV tag, including `tag data header` and `tag data body`.
 #[derive(Clone, Debug, PartialEq)]
@@ -9,18 +17,54 @@ pub struct VideoTag {
     /// The header part of `video` FLV tag.
     pub header: VideoTagHeader, // 8 bits.
 
-    /// The body part of `video` FLV tag.
-    pub body: VideoTagBody,
+    /// The per-track bodies of `video` FLV tag.
+    ///
+    /// Legacy and single track tags have exactly one track, command frames have none.
+    pub tracks: Vec<VideoTrack>,
+}
 
-    /// Track ID
+impl VideoTag {
+    /// Color info of the first track carrying `Metadata` packet.
+    pub fn color_info(&self) -> Option<&VideoColorInfo> {
+        self.tracks.iter().find_map(|x| x.body.color_info.as_ref())
+    }
+}
+
+/// Video track of `video` FLV tag.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VideoTrack {
+    /// Track ID, always zero for non-multitrack tags.
     pub track_id: u8,
+
+    /// Codec of the track.
+    pub fourcc: Fourcc,
+
+    /// Packet type, the same for all tracks of the tag.
+    pub pkt_type: VideoPacketType,
+
+    /// Frame type of the tag header, corrected by the frame header where the codec allows.
+    pub frame_type: VideoFrameType,
+
+    /// The body of the track.
+    pub body: VideoTagBody,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct VideoTagBody {
     pub pts_offset: i32,
     pub param_count: u32,
+
+    /// Parameter sets followed by coded units, or the AMF encoded body of `Metadata` packet.
     pub nalus: Vec<Bytes>,
+
+    /// Color info decoded from `Metadata` packet.
+    pub color_info: Option<VideoColorInfo>,
+
+    /// AVC/HEVC configuration record of `SequenceStart` packet as parsed, written back as is
+    /// instead of the record built from the parameter sets.
+    ///
+    /// An invalid record is kept here as well, `nalus` has no parameter sets then.
+    pub config: Option<Bytes>,
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
@@ -77,7 +121,23 @@ impl From<u8> for VideoPacketType {
             5 => VideoPacketType::MPEG2TSSequenceStart,
             6 => VideoPacketType::Multitrack,
             7 => VideoPacketType::ModEx,
-            _ => unreachable!(),
+            t => VideoPacketType::Unknown(t),
+        }
+    }
+}
+
+impl From<VideoPacketType> for u8 {
+    fn from(value: VideoPacketType) -> Self {
+        match value {
+            VideoPacketType::SequenceStart => 0,
+            VideoPacketType::CodedFrames => 1,
+            VideoPacketType::SequenceEnd => 2,
+            VideoPacketType::CodedFramesX => 3,
+            VideoPacketType::Metadata => 4,
+            VideoPacketType::MPEG2TSSequenceStart => 5,
+            VideoPacketType::Multitrack => 6,
+            VideoPacketType::ModEx => 7,
+            VideoPacketType::Unknown(t) => t,
         }
     }
 }
@@ -109,6 +169,17 @@ impl From<u8> for AvMultitrackType {
     }
 }
 
+impl From<AvMultitrackType> for u8 {
+    fn from(value: AvMultitrackType) -> Self {
+        match value {
+            AvMultitrackType::OneTrack => 0,
+            AvMultitrackType::ManyTracks => 1,
+            AvMultitrackType::ManyTracksManyCodecs => 2,
+            AvMultitrackType::Unknown(t) => t,
+        }
+    }
+}
+
 /// The `tag data header` part of `video` FLV tag data.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct VideoTagHeader {
@@ -118,6 +189,12 @@ pub struct VideoTagHeader {
     pub fourcc: Fourcc,
     pub has_body: bool,
     pub multitrack_type: AvMultitrackType,
+
+    /// The ModEx `TimestampOffsetNano` of the packet.
+    pub dts_offset_ns: u32,
+
+    /// The command of `Command` frame, such tags have no body.
+    pub video_command: Option<VideoCommand>,
     pub(crate) enhanced: bool,
 }
 
@@ -130,22 +207,47 @@ impl From<u8> for VideoPacketModExType {
     }
 }
 
+/// ModEx data of the packet, shared by video and audio packets.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct PacketExData {
-    pub pkt_type: VideoPacketType,
+pub struct PacketExData<T = VideoPacketType> {
+    pub pkt_type: T,
     pub ex_type: VideoPacketModExType,
     pub dts_offset_ns: u32,
     pub ex_data: Bytes,
 }
 
+/// The command of `Command` video frame.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 #[repr(u8)]
 pub enum VideoCommand {
+    /// Start of client-side seeking video frame sequence.
     StartSeek = 0,
+
+    /// End of client-side seeking video frame sequence.
     EndSeek = 1,
     Unknown(u8), // 0x03..0xff = reserved
 }
 
+impl From<u8> for VideoCommand {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => VideoCommand::StartSeek,
+            1 => VideoCommand::EndSeek,
+            t => VideoCommand::Unknown(t),
+        }
+    }
+}
+
+impl From<VideoCommand> for u8 {
+    fn from(value: VideoCommand) -> Self {
+        match value {
+            VideoCommand::StartSeek => 0,
+            VideoCommand::EndSeek => 1,
+            VideoCommand::Unknown(t) => t,
+        }
+    }
+}
+
 /// The type of video frame.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum VideoFrameType {
@@ -181,6 +283,19 @@ impl From<u8> for VideoFrameType {
     }
 }
 
+impl From<VideoFrameType> for u8 {
+    fn from(value: VideoFrameType) -> Self {
+        match value {
+            VideoFrameType::Key => 1,
+            VideoFrameType::Inter => 2,
+            VideoFrameType::DisposableInter => 3,
+            VideoFrameType::Generated => 4,
+            VideoFrameType::Command => 5,
+            VideoFrameType::Unknown(t) => t,
+        }
+    }
+}
+
 /// The code identifier of video.
 #[allow(clippy::upper_case_acronyms)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
@@ -230,6 +345,56 @@ impl From<u8> for CodecID {
     }
 }
 
+impl From<CodecID> for u8 {
+    fn from(value: CodecID) -> Self {
+        match value {
+            CodecID::SorensonH263 => 2,
+            CodecID::Screen1 => 3,
+            CodecID::VP6 => 4,
+            CodecID::VP6Alpha => 5,
+            CodecID::Screen2 => 6,
+            CodecID::AVC => 7,
+            CodecID::RealH263 => 8,
+            CodecID::Hevc => 12,
+            CodecID::Unknown(c) => c,
+        }
+    }
+}
+
+impl From<CodecID> for Fourcc {
+    fn from(value: CodecID) -> Self {
+        match value {
+            CodecID::SorensonH263 => Fourcc::from_static("M263"),
+            CodecID::Screen1 => Fourcc::from_static("SCR1"),
+            CodecID::VP6 => Fourcc::from_static("VP06"),
+            CodecID::VP6Alpha => Fourcc::from_static("VP6a"),
+            CodecID::Screen2 => Fourcc::from_static("SCR2"),
+            CodecID::AVC => Fourcc::VIDEO_AVC,
+            CodecID::RealH263 => Fourcc::from_static("H263"),
+            CodecID::Hevc => Fourcc::VIDEO_HEVC,
+            CodecID::Unknown(_) => Fourcc::default(),
+        }
+    }
+}
+
+impl From<Fourcc> for CodecID {
+    fn from(value: Fourcc) -> Self {
+        [
+            CodecID::SorensonH263,
+            CodecID::Screen1,
+            CodecID::VP6,
+            CodecID::VP6Alpha,
+            CodecID::Screen2,
+            CodecID::AVC,
+            CodecID::RealH263,
+            CodecID::Hevc,
+        ]
+        .into_iter()
+        .find(|&id| Fourcc::from(id) == value)
+        .unwrap_or(CodecID::Unknown(0))
+    }
+}
+
 /// The type of AVC packet.
 #[allow(clippy::upper_case_acronyms)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
@@ -258,3 +423,39 @@ impl From<u8> for AvcPacketType {
         }
     }
 }
+
+impl From<AvcPacketType> for u8 {
+    fn from(value: AvcPacketType) -> Self {
+        match value {
+            AvcPacketType::SequenceHeader => 0,
+            AvcPacketType::NALU => 1,
+            AvcPacketType::EndOfSequence => 2,
+            AvcPacketType::Unknown(t) => t,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::{VideoPacketType, VideoTag, VideoTagHeader};
+    use crate::parser::{FlvParser, Parser};
+
+    #[test]
+    fn unknown_packet_type() {
+        assert_eq!(VideoPacketType::from(9), VideoPacketType::Unknown(9));
+        assert_eq!(u8::from(VideoPacketType::Unknown(9)), 9);
+
+        // enhanced key frame with reserved packet type 15
+        let data = Bytes::from_static(&[0x9f, b'a', b'v', b'c', b'1', 0x12, 0x34]);
+
+        let header: VideoTagHeader =
+            Parser::<flowly::Void, _>::parse(&mut FlvParser::default(), &mut data.clone()).unwrap();
+        assert_eq!(header.pkt_type, VideoPacketType::Unknown(15));
+
+        let tag: VideoTag =
+            Parser::<flowly::Void, _>::parse(&mut FlvParser::default(), &mut data.clone()).unwrap();
+        assert_eq!(tag.tracks[0].pkt_type, VideoPacketType::Unknown(15));
+    }
+}
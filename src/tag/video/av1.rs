@@ -0,0 +1,567 @@
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::{
+    bitreader::BitReader,
+    error::Error,
+    parser::Parser,
+    reader::FlvReader,
+    writer::{FlvWriter, Writer},
+};
+
+/// OBU type of the sequence header.
+pub const OBU_SEQUENCE_HEADER: u8 = 1;
+
+/// OBU type of the temporal delimiter.
+pub const OBU_TEMPORAL_DELIMITER: u8 = 2;
+
+/// OBU type of the frame header.
+pub const OBU_FRAME_HEADER: u8 = 3;
+
+/// OBU type of the frame, a frame header followed by a tile group.
+pub const OBU_FRAME: u8 = 6;
+
+/// `frame_type` of the key frame.
+const KEY_FRAME: u8 = 0;
+
+/// AV1CodecConfigurationRecord, see AV1 Codec ISO Media File Format Binding, 2.3
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct Av1CodecConfigurationRecord {
+    pub seq_profile: u8,
+    pub seq_level_idx_0: u8,
+    pub seq_tier_0: bool,
+    pub high_bitdepth: bool,
+    pub twelve_bit: bool,
+    pub monochrome: bool,
+    pub chroma_subsampling_x: bool,
+    pub chroma_subsampling_y: bool,
+    pub chroma_sample_position: u8,
+    pub initial_presentation_delay_minus_one: Option<u8>,
+
+    /// Sequence header and metadata OBUs.
+    pub config_obus: Vec<Bytes>,
+}
+
+impl Av1CodecConfigurationRecord {
+    /// Build a configuration record from the configuration OBUs.
+    ///
+    /// The profile, level and color config are taken from the sequence header OBU.
+    pub fn from_params(params: impl IntoIterator<Item = Bytes>) -> Self {
+        let mut record = Av1CodecConfigurationRecord {
+            config_obus: params.into_iter().collect(),
+            ..Default::default()
+        };
+
+        let seq_header = record
+            .config_obus
+            .iter()
+            .find(|x| obu_type(x) == Some(OBU_SEQUENCE_HEADER))
+            .and_then(|x| Av1SequenceHeader::parse(obu_payload(x)?).ok());
+
+        if let Some(x) = seq_header {
+            record.seq_profile = x.seq_profile;
+            record.seq_level_idx_0 = x.seq_level_idx_0;
+            record.seq_tier_0 = x.seq_tier_0;
+            record.high_bitdepth = x.high_bitdepth;
+            record.twelve_bit = x.twelve_bit;
+            record.monochrome = x.monochrome;
+            record.chroma_subsampling_x = x.chroma_subsampling_x;
+            record.chroma_subsampling_y = x.chroma_subsampling_y;
+            record.chroma_sample_position = x.chroma_sample_position;
+        }
+
+        record
+    }
+}
+
+/// The fields of AV1 sequence header OBU, see AV1 Bitstream & Decoding Process, 5.5
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct Av1SequenceHeader {
+    pub seq_profile: u8,
+    pub still_picture: bool,
+    pub reduced_still_picture_header: bool,
+    pub seq_level_idx_0: u8,
+    pub seq_tier_0: bool,
+    pub max_frame_width: u32,
+    pub max_frame_height: u32,
+    pub high_bitdepth: bool,
+    pub twelve_bit: bool,
+    pub monochrome: bool,
+    pub color_primaries: u8,
+    pub transfer_characteristics: u8,
+    pub matrix_coefficients: u8,
+    pub color_range: bool,
+    pub chroma_subsampling_x: bool,
+    pub chroma_subsampling_y: bool,
+    pub chroma_sample_position: u8,
+}
+
+impl Av1SequenceHeader {
+    /// Parse the payload of the sequence header OBU.
+    pub fn parse(payload: &[u8]) -> std::io::Result<Self> {
+        let mut r = BitReader::new(payload);
+        let mut x = Av1SequenceHeader {
+            seq_profile: r.read_u8(3)?,
+            still_picture: r.read_bit()?,
+            reduced_still_picture_header: r.read_bit()?,
+            ..Default::default()
+        };
+
+        if x.reduced_still_picture_header {
+            x.seq_level_idx_0 = r.read_u8(5)?;
+        } else {
+            let mut buffer_delay_length = 0;
+            let mut decoder_model_info_present = false;
+
+            // timing_info_present_flag
+            if r.read_bit()? {
+                // num_units_in_display_tick and time_scale
+                r.skip_bits(64)?;
+
+                // equal_picture_interval
+                if r.read_bit()? {
+                    r.read_uvlc()?;
+                }
+
+                decoder_model_info_present = r.read_bit()?;
+                if decoder_model_info_present {
+                    buffer_delay_length = r.read_u8(5)? as usize + 1;
+
+                    // num_units_in_decoding_tick, buffer_removal_time_length_minus_1,
+                    // frame_presentation_time_length_minus_1
+                    r.skip_bits(32 + 5 + 5)?;
+                }
+            }
+
+            let initial_display_delay_present = r.read_bit()?;
+            let operating_points_cnt = r.read_u8(5)? + 1;
+
+            for i in 0..operating_points_cnt {
+                // operating_point_idc
+                r.skip_bits(12)?;
+
+                let seq_level_idx = r.read_u8(5)?;
+                let seq_tier = seq_level_idx > 7 && r.read_bit()?;
+
+                if i == 0 {
+                    x.seq_level_idx_0 = seq_level_idx;
+                    x.seq_tier_0 = seq_tier;
+                }
+
+                // decoder_model_present_for_this_op
+                if decoder_model_info_present && r.read_bit()? {
+                    // decoder_buffer_delay, encoder_buffer_delay, low_delay_mode_flag
+                    r.skip_bits(buffer_delay_length * 2 + 1)?;
+                }
+
+                // initial_display_delay_present_for_this_op
+                if initial_display_delay_present && r.read_bit()? {
+                    r.skip_bits(4)?;
+                }
+            }
+        }
+
+        let frame_width_bits = r.read_u32(4)? + 1;
+        let frame_height_bits = r.read_u32(4)? + 1;
+        x.max_frame_width = r.read_u32(frame_width_bits)? + 1;
+        x.max_frame_height = r.read_u32(frame_height_bits)? + 1;
+
+        // frame_id_numbers_present_flag
+        if !x.reduced_still_picture_header && r.read_bit()? {
+            // delta_frame_id_length_minus_2, additional_frame_id_length_minus_1
+            r.skip_bits(4 + 3)?;
+        }
+
+        // use_128x128_superblock, enable_filter_intra, enable_intra_edge_filter
+        r.skip_bits(3)?;
+
+        if !x.reduced_still_picture_header {
+            // enable_interintra_compound, enable_masked_compound,
+            // enable_warped_motion, enable_dual_filter
+            r.skip_bits(4)?;
+
+            let enable_order_hint = r.read_bit()?;
+            if enable_order_hint {
+                // enable_jnt_comp, enable_ref_frame_mvs
+                r.skip_bits(2)?;
+            }
+
+            // seq_choose_screen_content_tools
+            let seq_force_screen_content_tools = if r.read_bit()? { 2 } else { r.read_u8(1)? };
+
+            // seq_choose_integer_mv
+            if seq_force_screen_content_tools > 0 && !r.read_bit()? {
+                // seq_force_integer_mv
+                r.skip_bits(1)?;
+            }
+
+            if enable_order_hint {
+                // order_hint_bits_minus_1
+                r.skip_bits(3)?;
+            }
+        }
+
+        // enable_superres, enable_cdef, enable_restoration
+        r.skip_bits(3)?;
+
+        x.parse_color_config(&mut r)?;
+
+        Ok(x)
+    }
+
+    fn parse_color_config(&mut self, r: &mut BitReader<'_>) -> std::io::Result<()> {
+        self.high_bitdepth = r.read_bit()?;
+        if self.seq_profile == 2 && self.high_bitdepth {
+            self.twelve_bit = r.read_bit()?;
+        }
+
+        self.monochrome = self.seq_profile != 1 && r.read_bit()?;
+
+        // CP_UNSPECIFIED, TC_UNSPECIFIED and MC_UNSPECIFIED
+        (
+            self.color_primaries,
+            self.transfer_characteristics,
+            self.matrix_coefficients,
+        ) = (2, 2, 2);
+
+        // color_description_present_flag
+        if r.read_bit()? {
+            self.color_primaries = r.read_u8(8)?;
+            self.transfer_characteristics = r.read_u8(8)?;
+            self.matrix_coefficients = r.read_u8(8)?;
+        }
+
+        if self.monochrome {
+            self.color_range = r.read_bit()?;
+            self.chroma_subsampling_x = true;
+            self.chroma_subsampling_y = true;
+
+            return Ok(());
+        }
+
+        // sRGB with identity matrix
+        if self.color_primaries == 1
+            && self.transfer_characteristics == 13
+            && self.matrix_coefficients == 0
+        {
+            self.color_range = true;
+
+            return Ok(());
+        }
+
+        self.color_range = r.read_bit()?;
+
+        match self.seq_profile {
+            0 => (self.chroma_subsampling_x, self.chroma_subsampling_y) = (true, true),
+            1 => (),
+            _ if self.twelve_bit => {
+                self.chroma_subsampling_x = r.read_bit()?;
+                self.chroma_subsampling_y = self.chroma_subsampling_x && r.read_bit()?;
+            }
+            _ => self.chroma_subsampling_x = true,
+        }
+
+        if self.chroma_subsampling_x && self.chroma_subsampling_y {
+            self.chroma_sample_position = r.read_u8(2)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// OBU type from the OBU header.
+#[inline]
+pub fn obu_type(obu: &[u8]) -> Option<u8> {
+    obu.first().map(|x| (x >> 3) & 0x0F)
+}
+
+/// Payload of the OBU, without the OBU header and size field.
+pub fn obu_payload(obu: &[u8]) -> Option<&[u8]> {
+    let header = *obu.first()?;
+    let mut offset = 1 + ((header >> 2) & 1) as usize;
+
+    if (header >> 1) & 1 > 0 {
+        let (size, len) = read_leb128(obu.get(offset..)?)?;
+        offset += len;
+
+        return obu.get(offset..offset.checked_add(size as usize)?);
+    }
+
+    obu.get(offset..)
+}
+
+/// Read `leb128()` value returning it with the count of bytes read.
+fn read_leb128(data: &[u8]) -> Option<(u64, usize)> {
+    let mut value = 0u64;
+
+    for (i, byte) in data.iter().take(8).enumerate() {
+        value |= ((byte & 0x7F) as u64) << (i * 7);
+
+        if byte & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+    }
+
+    None
+}
+
+/// Split low overhead bitstream into OBUs, each OBU keeps its header and size field.
+pub fn split_obus(mut data: Bytes) -> std::io::Result<Vec<Bytes>> {
+    let mut obus = Vec::new();
+
+    while !data.is_empty() {
+        let header = data[0];
+        let mut size = data.len();
+
+        if (header >> 1) & 1 > 0 {
+            let offset = 1 + ((header >> 2) & 1) as usize;
+            let (payload_size, len) = data
+                .get(offset..)
+                .and_then(read_leb128)
+                .ok_or(std::io::ErrorKind::InvalidData)?;
+
+            size = (offset + len)
+                .checked_add(payload_size as usize)
+                .filter(|&x| x <= data.len())
+                .ok_or(std::io::ErrorKind::UnexpectedEof)?;
+        }
+
+        obus.push(data.split_to(size));
+    }
+
+    Ok(obus)
+}
+
+/// OBUs of the temporal unit.
+pub struct Av1TemporalUnit {
+    pub obus: Vec<Bytes>,
+
+    /// The temporal unit contains a key frame.
+    pub keyframe: bool,
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct Av1Parser {
+    pub reduced_still_picture_header: bool,
+}
+
+impl Av1Parser {
+    fn apply_obus(&mut self, obus: &[Bytes]) -> bool {
+        let mut keyframe = false;
+
+        for obu in obus {
+            let Some(payload) = obu_payload(obu) else {
+                continue;
+            };
+
+            match obu_type(obu) {
+                Some(OBU_SEQUENCE_HEADER) => {
+                    if let Ok(x) = Av1SequenceHeader::parse(payload) {
+                        self.reduced_still_picture_header = x.reduced_still_picture_header;
+                    }
+                }
+                Some(OBU_FRAME_HEADER | OBU_FRAME) => {
+                    keyframe |= self.reduced_still_picture_header
+                        || payload
+                            .first()
+                            // show_existing_frame is not set and frame_type is KEY_FRAME
+                            .is_some_and(|x| x >> 7 == 0 && (x >> 5) & 0x03 == KEY_FRAME);
+                }
+                _ => (),
+            }
+        }
+
+        keyframe
+    }
+}
+
+impl<E> Parser<E, Av1CodecConfigurationRecord> for Av1Parser {
+    type Error = Error<E>;
+
+    fn parse(
+        &mut self,
+        reader: &mut impl FlvReader,
+    ) -> Result<Av1CodecConfigurationRecord, Self::Error> {
+        /*marker and version*/
+        reader.read_u8()?;
+
+        let profile_level = reader.read_u8()?;
+        let flags = reader.read_u8()?;
+        let delay = reader.read_u8()?;
+
+        let config_obus = split_obus(reader.read_to_end()?)?;
+        self.apply_obus(&config_obus);
+
+        Ok(Av1CodecConfigurationRecord {
+            seq_profile: profile_level >> 5,
+            seq_level_idx_0: profile_level & 0x1F,
+            seq_tier_0: flags >> 7 > 0,
+            high_bitdepth: (flags >> 6) & 1 > 0,
+            twelve_bit: (flags >> 5) & 1 > 0,
+            monochrome: (flags >> 4) & 1 > 0,
+            chroma_subsampling_x: (flags >> 3) & 1 > 0,
+            chroma_subsampling_y: (flags >> 2) & 1 > 0,
+            chroma_sample_position: flags & 0x03,
+            initial_presentation_delay_minus_one: ((delay >> 4) & 1 > 0).then_some(delay & 0x0F),
+            config_obus,
+        })
+    }
+}
+
+impl<E> Parser<E, Av1TemporalUnit> for Av1Parser {
+    type Error = Error<E>;
+
+    fn parse(&mut self, reader: &mut impl FlvReader) -> Result<Av1TemporalUnit, Self::Error> {
+        let obus = split_obus(reader.read_to_end()?)?;
+        let keyframe = self.apply_obus(&obus);
+
+        Ok(Av1TemporalUnit { obus, keyframe })
+    }
+}
+
+impl<E> Writer<E, Av1CodecConfigurationRecord> for FlvWriter {
+    type Error = Error<E>;
+
+    fn write(
+        &mut self,
+        buf: &mut BytesMut,
+        value: &Av1CodecConfigurationRecord,
+    ) -> Result<(), Self::Error> {
+        /*marker and version*/
+        buf.put_u8(0x81);
+
+        buf.put_u8((value.seq_profile << 5) | (value.seq_level_idx_0 & 0x1F));
+        buf.put_u8(
+            ((value.seq_tier_0 as u8) << 7)
+                | ((value.high_bitdepth as u8) << 6)
+                | ((value.twelve_bit as u8) << 5)
+                | ((value.monochrome as u8) << 4)
+                | ((value.chroma_subsampling_x as u8) << 3)
+                | ((value.chroma_subsampling_y as u8) << 2)
+                | (value.chroma_sample_position & 0x03),
+        );
+
+        buf.put_u8(match value.initial_presentation_delay_minus_one {
+            Some(delay) => 0x10 | (delay & 0x0F),
+            None => 0,
+        });
+
+        for obu in &value.config_obus {
+            buf.put_slice(obu);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{Bytes, BytesMut};
+
+    use super::{
+        Av1CodecConfigurationRecord, Av1Parser, Av1SequenceHeader, Av1TemporalUnit, OBU_FRAME,
+        OBU_SEQUENCE_HEADER, OBU_TEMPORAL_DELIMITER, obu_payload, obu_type, split_obus,
+    };
+    use crate::{
+        error::Error,
+        parser::Parser,
+        writer::{FlvWriter, Writer},
+    };
+
+    /// Sequence header OBU of 1920x1080 Main profile 8 bit 4:2:0 BT.709 stream, level 4.0.
+    const SEQUENCE_HEADER: &[u8] = &[
+        0x0a, 0x0e, 0x00, 0x00, 0x00, 0x42, 0xab, 0xbf, 0xc3, 0x73, 0xff, 0xe6, 0x40, 0x40, 0x40,
+        0x41,
+    ];
+
+    /// Key frame OBU with `show_existing_frame` unset and `frame_type` of `KEY_FRAME`.
+    const KEY_FRAME: &[u8] = &[0x32, 0x02, 0x10, 0x00];
+
+    /// Inter frame OBU.
+    const INTER_FRAME: &[u8] = &[0x32, 0x02, 0x30, 0x00];
+
+    fn temporal_unit(frame: &[u8]) -> Bytes {
+        let mut data = vec![0x12, 0x00];
+        data.extend_from_slice(SEQUENCE_HEADER);
+
+        // padding OBU with 200 bytes of payload, the size takes two bytes of leb128
+        data.extend_from_slice(&[0x7a, 0xc8, 0x01]);
+        data.extend_from_slice(&[0; 200]);
+
+        data.extend_from_slice(frame);
+        data.into()
+    }
+
+    #[test]
+    fn sequence_header() {
+        assert_eq!(obu_type(SEQUENCE_HEADER), Some(OBU_SEQUENCE_HEADER));
+
+        let x = Av1SequenceHeader::parse(obu_payload(SEQUENCE_HEADER).unwrap()).unwrap();
+
+        assert_eq!(
+            (x.seq_profile, x.seq_level_idx_0, x.seq_tier_0),
+            (0, 8, false)
+        );
+        assert!(!x.reduced_still_picture_header);
+        assert_eq!((x.max_frame_width, x.max_frame_height), (1920, 1080));
+        assert!(!x.high_bitdepth && !x.monochrome);
+        assert_eq!(
+            (
+                x.color_primaries,
+                x.transfer_characteristics,
+                x.matrix_coefficients
+            ),
+            (1, 1, 1)
+        );
+        assert!(x.chroma_subsampling_x && x.chroma_subsampling_y);
+    }
+
+    #[test]
+    fn obus() {
+        let obus = split_obus(temporal_unit(KEY_FRAME)).unwrap();
+
+        assert_eq!(obus.len(), 4);
+        assert_eq!(obu_type(&obus[0]), Some(OBU_TEMPORAL_DELIMITER));
+        assert_eq!(obu_payload(&obus[0]), Some(&[][..]));
+        assert_eq!(obus[1], SEQUENCE_HEADER);
+        assert_eq!(obu_payload(&obus[2]).map(|x| x.len()), Some(200));
+        assert_eq!(obu_type(&obus[3]), Some(OBU_FRAME));
+
+        // the size field exceeds the data
+        let err = split_obus(Bytes::from_static(&[0x32, 0x05, 0x00])).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+
+        // unterminated leb128
+        assert!(split_obus(Bytes::from_static(&[0x32, 0x80])).is_err());
+        assert_eq!(obu_payload(&[0x32, 0x80]), None);
+    }
+
+    #[test]
+    fn keyframes() {
+        let mut parser = Av1Parser::default();
+
+        for (frame, keyframe) in [(KEY_FRAME, true), (INTER_FRAME, false)] {
+            let tu: Av1TemporalUnit =
+                Parser::<flowly::Void, _>::parse(&mut parser, &mut temporal_unit(frame)).unwrap();
+
+            assert_eq!(tu.obus.len(), 4);
+            assert_eq!(tu.keyframe, keyframe);
+        }
+    }
+
+    #[test]
+    fn record_round_trip() {
+        let record =
+            Av1CodecConfigurationRecord::from_params([Bytes::from_static(SEQUENCE_HEADER)]);
+
+        assert_eq!((record.seq_profile, record.seq_level_idx_0), (0, 8));
+        assert!(record.chroma_subsampling_x && record.chroma_subsampling_y);
+
+        let mut buf = BytesMut::new();
+        Writer::<flowly::Void, _>::write(&mut FlvWriter::default(), &mut buf, &record).unwrap();
+        assert_eq!(buf[..4], [0x81, 0x08, 0x0c, 0x00]);
+
+        let parsed: Result<Av1CodecConfigurationRecord, Error> =
+            Av1Parser::default().parse(&mut buf.freeze());
+        assert_eq!(parsed.unwrap(), record);
+    }
+}
@@ -0,0 +1,198 @@
+use bytes::Bytes;
+use indexmap::IndexMap;
+
+use crate::tag::meta::{
+    MetaDataValue,
+    on_metadata::{take_number, take_object},
+};
+
+/// Name of the video metadata entry carrying `VideoColorInfo`.
+pub const COLOR_INFO: &[u8] = b"colorInfo";
+
+/// Typed representation of the Enhanced RTMP `colorInfo` video metadata.
+///
+/// All values follow ITU-T H.273 unless stated otherwise.
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct VideoColorInfo {
+    pub color_config: Option<VideoColorConfig>,
+
+    /// HDR content light level.
+    pub hdr_cll: Option<HdrContentLightLevel>,
+
+    /// HDR mastering display color volume.
+    pub hdr_mdcv: Option<HdrMasteringDisplayColorVolume>,
+}
+
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct VideoColorConfig {
+    /// Number of bits used to record the color channels for each pixel.
+    pub bit_depth: Option<f64>,
+
+    pub color_primaries: Option<f64>,
+    pub transfer_characteristics: Option<f64>,
+    pub matrix_coefficients: Option<f64>,
+}
+
+/// Content light level, see CTA-861-G.
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct HdrContentLightLevel {
+    /// Maximum frame-average light level in cd/m2.
+    pub max_fall: Option<f64>,
+
+    /// Maximum content light level in cd/m2.
+    pub max_cll: Option<f64>,
+}
+
+/// Mastering display color volume, see SMPTE ST 2086.
+///
+/// Chromaticity coordinates are in range 0.0 - 1.0, luminance is in cd/m2.
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct HdrMasteringDisplayColorVolume {
+    pub red_x: Option<f64>,
+    pub red_y: Option<f64>,
+    pub green_x: Option<f64>,
+    pub green_y: Option<f64>,
+    pub blue_x: Option<f64>,
+    pub blue_y: Option<f64>,
+    pub white_point_x: Option<f64>,
+    pub white_point_y: Option<f64>,
+    pub max_luminance: Option<f64>,
+    pub min_luminance: Option<f64>,
+}
+
+impl VideoColorInfo {
+    /// Build from `Object` or `ECMAArray` value.
+    pub fn from_value(value: MetaDataValue) -> Option<Self> {
+        match value {
+            MetaDataValue::Object(props) | MetaDataValue::ECMAArray(props, _) => Some(props.into()),
+            _ => None,
+        }
+    }
+}
+
+impl From<IndexMap<Bytes, MetaDataValue>> for VideoColorInfo {
+    fn from(mut props: IndexMap<Bytes, MetaDataValue>) -> Self {
+        VideoColorInfo {
+            color_config: take_object(&mut props, "colorConfig").map(|mut x| VideoColorConfig {
+                bit_depth: take_number(&mut x, "bitDepth"),
+                color_primaries: take_number(&mut x, "colorPrimaries"),
+                transfer_characteristics: take_number(&mut x, "transferCharacteristics"),
+                matrix_coefficients: take_number(&mut x, "matrixCoefficients"),
+            }),
+            hdr_cll: take_object(&mut props, "hdrCll").map(|mut x| HdrContentLightLevel {
+                max_fall: take_number(&mut x, "maxFall"),
+                max_cll: take_number(&mut x, "maxCLL"),
+            }),
+            hdr_mdcv: take_object(&mut props, "hdrMdcv").map(|mut x| {
+                HdrMasteringDisplayColorVolume {
+                    red_x: take_number(&mut x, "redX"),
+                    red_y: take_number(&mut x, "redY"),
+                    green_x: take_number(&mut x, "greenX"),
+                    green_y: take_number(&mut x, "greenY"),
+                    blue_x: take_number(&mut x, "blueX"),
+                    blue_y: take_number(&mut x, "blueY"),
+                    white_point_x: take_number(&mut x, "whitePointX"),
+                    white_point_y: take_number(&mut x, "whitePointY"),
+                    max_luminance: take_number(&mut x, "maxLuminance"),
+                    min_luminance: take_number(&mut x, "minLuminance"),
+                }
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{BufMut, Bytes, BytesMut};
+
+    use super::{HdrContentLightLevel, VideoColorConfig, VideoColorInfo};
+    use crate::{
+        parser::{FlvParser, Parser},
+        tag::{meta::MetaDataValue, video::VideoTag},
+        writer::{FlvWriter, Writer},
+    };
+
+    fn object(props: impl IntoIterator<Item = (&'static str, MetaDataValue)>) -> MetaDataValue {
+        MetaDataValue::Object(
+            props
+                .into_iter()
+                .map(|(k, v)| (Bytes::from_static(k.as_bytes()), v))
+                .collect(),
+        )
+    }
+
+    fn hdr10() -> MetaDataValue {
+        object([
+            (
+                "colorConfig",
+                object([
+                    ("bitDepth", MetaDataValue::Number(10.0)),
+                    ("colorPrimaries", MetaDataValue::Number(9.0)),
+                    ("transferCharacteristics", MetaDataValue::Number(16.0)),
+                    ("matrixCoefficients", MetaDataValue::Number(9.0)),
+                ]),
+            ),
+            (
+                "hdrCll",
+                object([
+                    ("maxFall", MetaDataValue::Number(400.0)),
+                    ("maxCLL", MetaDataValue::Number(1000.0)),
+                ]),
+            ),
+            ("hdrMdcv", MetaDataValue::Null),
+        ])
+    }
+
+    fn expected() -> VideoColorInfo {
+        VideoColorInfo {
+            color_config: Some(VideoColorConfig {
+                bit_depth: Some(10.0),
+                color_primaries: Some(9.0),
+                transfer_characteristics: Some(16.0),
+                matrix_coefficients: Some(9.0),
+            }),
+            hdr_cll: Some(HdrContentLightLevel {
+                max_fall: Some(400.0),
+                max_cll: Some(1000.0),
+            }),
+            hdr_mdcv: None,
+        }
+    }
+
+    #[test]
+    fn from_value() {
+        assert_eq!(VideoColorInfo::from_value(hdr10()), Some(expected()));
+        assert_eq!(VideoColorInfo::from_value(MetaDataValue::Null), None);
+
+        // values of wrong type are skipped
+        let value = object([(
+            "colorConfig",
+            object([("bitDepth", MetaDataValue::Boolean(true))]),
+        )]);
+        assert_eq!(
+            VideoColorInfo::from_value(value).unwrap().color_config,
+            Some(VideoColorConfig::default())
+        );
+    }
+
+    #[test]
+    fn metadata_packet() {
+        let mut data = BytesMut::new();
+        data.put_u8(0x80 | 4);
+        data.put_slice(b"av01");
+
+        let mut writer = FlvWriter::default();
+        let name = MetaDataValue::String(Bytes::from_static(b"colorInfo"));
+        Writer::<flowly::Void, _>::write(&mut writer, &mut data, &name).unwrap();
+        Writer::<flowly::Void, _>::write(&mut writer, &mut data, &hdr10()).unwrap();
+
+        let tag: VideoTag =
+            Parser::<flowly::Void, _>::parse(&mut FlvParser::default(), &mut data.clone().freeze())
+                .unwrap();
+        assert_eq!(tag.color_info(), Some(&expected()));
+
+        let mut buf = BytesMut::new();
+        Writer::<flowly::Void, _>::write(&mut writer, &mut buf, &tag).unwrap();
+        assert_eq!(buf, data);
+    }
+}
@@ -0,0 +1,409 @@
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::{
+    bitreader::remove_emulation_prevention,
+    error::Error,
+    parser::Parser,
+    reader::FlvReader,
+    writer::{FlvWriter, Writer},
+};
+
+use super::mpeg4_avc::{Mpeg4AvcNALUSeq, Mpeg4AvcParser};
+
+use sps::{HevcSps, HevcVps};
+
+pub mod sps;
+
+/// NAL unit type of the video parameter set.
+pub const NALU_TYPE_VPS: u8 = 32;
+
+/// NAL unit type of the sequence parameter set.
+pub const NALU_TYPE_SPS: u8 = 33;
+
+/// NAL unit type of the picture parameter set.
+pub const NALU_TYPE_PPS: u8 = 34;
+
+/// HEVCDecoderConfigurationRecord, see ISO/IEC 14496-15:2022, 8.3.3.1
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct HevcDecoderConfigurationRecord {
+    pub general_profile_space: u8,
+    pub general_tier_flag: bool,
+    pub general_profile_idc: u8,
+    pub general_profile_compatibility_flags: u32,
+
+    /// 48 bits of general constraint indicator flags
+    pub general_constraint_indicator_flags: u64,
+    pub general_level_idc: u8,
+    pub min_spatial_segmentation_idc: u16,
+    pub parallelism_type: u8,
+    pub chroma_format_idc: u8,
+    pub bit_depth_luma_minus8: u8,
+    pub bit_depth_chroma_minus8: u8,
+    pub avg_frame_rate: u16,
+    pub constant_frame_rate: u8,
+    pub num_temporal_layers: u8,
+    pub temporal_id_nested: bool,
+    pub nalu_length: u8,
+    pub arrays: Vec<HevcNaluArray>,
+}
+
+/// Array of NAL units of the same type in HEVCDecoderConfigurationRecord.
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct HevcNaluArray {
+    pub array_completeness: bool,
+    pub nal_unit_type: u8,
+    pub nalus: Vec<Bytes>,
+}
+
+impl HevcDecoderConfigurationRecord {
+    /// Build a configuration record from VPS, SPS and PPS NAL units.
+    ///
+    /// The general profile, tier and level are taken from the first SPS.
+    pub fn from_params(params: impl IntoIterator<Item = Bytes>, nalu_length: u8) -> Self {
+        let mut record = HevcDecoderConfigurationRecord {
+            chroma_format_idc: 1,
+            num_temporal_layers: 1,
+            nalu_length,
+            ..Default::default()
+        };
+
+        for nalu in params {
+            let Some(nal_unit_type) = nalu.first().map(|x| (x >> 1) & 0x3F) else {
+                continue;
+            };
+
+            if nal_unit_type == NALU_TYPE_SPS && !record.has_nalu_type(NALU_TYPE_SPS) {
+                record.apply_sps(&nalu);
+            }
+
+            match record
+                .arrays
+                .iter_mut()
+                .find(|x| x.nal_unit_type == nal_unit_type)
+            {
+                Some(array) => array.nalus.push(nalu),
+                None => record.arrays.push(HevcNaluArray {
+                    array_completeness: true,
+                    nal_unit_type,
+                    nalus: vec![nalu],
+                }),
+            }
+        }
+
+        record.arrays.sort_by_key(|x| x.nal_unit_type);
+        record
+    }
+
+    fn has_nalu_type(&self, nal_unit_type: u8) -> bool {
+        self.arrays.iter().any(|x| x.nal_unit_type == nal_unit_type)
+    }
+
+    /// Fill the general profile_tier_level, chroma format and bit depth fields from the SPS
+    /// NAL unit.
+    fn apply_sps(&mut self, sps: &[u8]) {
+        if let Ok(parsed) = HevcSps::parse(sps) {
+            self.chroma_format_idc = parsed.chroma_format_idc;
+            self.bit_depth_luma_minus8 = parsed.bit_depth_luma_minus8;
+            self.bit_depth_chroma_minus8 = parsed.bit_depth_chroma_minus8;
+        }
+
+        // 2 bytes of NAL unit header, 1 byte of sps_video_parameter_set_id,
+        // sps_max_sub_layers_minus1, sps_temporal_id_nesting_flag and
+        // 12 bytes of general profile_tier_level
+        let rbsp = remove_emulation_prevention(sps);
+        let Some(ptl) = rbsp.get(2..15) else {
+            return;
+        };
+
+        self.num_temporal_layers = ((ptl[0] >> 1) & 0x07) + 1;
+        self.temporal_id_nested = ptl[0] & 1 > 0;
+        self.general_profile_space = ptl[1] >> 6;
+        self.general_tier_flag = (ptl[1] >> 5) & 1 > 0;
+        self.general_profile_idc = ptl[1] & 0x1F;
+        self.general_profile_compatibility_flags =
+            u32::from_be_bytes([ptl[2], ptl[3], ptl[4], ptl[5]]);
+        self.general_constraint_indicator_flags =
+            u64::from_be_bytes([0, 0, ptl[6], ptl[7], ptl[8], ptl[9], ptl[10], ptl[11]]);
+        self.general_level_idc = ptl[12];
+    }
+
+    /// Parse the first VPS, `None` if the record has no VPS.
+    pub fn parse_vps(&self) -> std::io::Result<Option<HevcVps>> {
+        self.first_nalu(NALU_TYPE_VPS)
+            .map(|x| HevcVps::parse(x))
+            .transpose()
+    }
+
+    /// Parse the first SPS, `None` if the record has no SPS.
+    ///
+    /// Provides picture size, frame rate, chroma format, bit depth and HDR transfer of the stream.
+    pub fn parse_sps(&self) -> std::io::Result<Option<HevcSps>> {
+        self.first_nalu(NALU_TYPE_SPS)
+            .map(|x| HevcSps::parse(x))
+            .transpose()
+    }
+
+    fn first_nalu(&self, nal_unit_type: u8) -> Option<&Bytes> {
+        self.arrays
+            .iter()
+            .filter(|x| x.nal_unit_type == nal_unit_type)
+            .find_map(|x| x.nalus.first())
+    }
+
+    /// Iterator by NAL units of all arrays.
+    pub fn nalus(&self) -> impl Iterator<Item = &Bytes> {
+        self.arrays.iter().flat_map(|x| x.nalus.iter())
+    }
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct HevcParser {
+    pub nalu_length: u8,
+}
+
+impl<E> Parser<E, HevcDecoderConfigurationRecord> for HevcParser {
+    type Error = Error<E>;
+
+    fn parse(
+        &mut self,
+        reader: &mut impl FlvReader,
+    ) -> Result<HevcDecoderConfigurationRecord, Self::Error> {
+        /*configurationVersion*/
+        reader.read_u8()?;
+
+        let ptl = reader.read_u8()?;
+        let general_profile_compatibility_flags = reader.read_u32()?;
+        let general_constraint_indicator_flags =
+            ((reader.read_u16()? as u64) << 32) | reader.read_u32()? as u64;
+        let general_level_idc = reader.read_u8()?;
+
+        let min_spatial_segmentation_idc = reader.read_u16()? & 0x0FFF;
+        let parallelism_type = reader.read_u8()? & 0x03;
+        let chroma_format_idc = reader.read_u8()? & 0x03;
+        let bit_depth_luma_minus8 = reader.read_u8()? & 0x07;
+        let bit_depth_chroma_minus8 = reader.read_u8()? & 0x07;
+        let avg_frame_rate = reader.read_u16()?;
+
+        let flags = reader.read_u8()?;
+        let nalu_length = (flags & 0x03) + 1;
+
+        self.nalu_length = nalu_length;
+
+        /*numOfArrays*/
+        let nb_arrays = reader.read_u8()?;
+        let mut arrays = Vec::with_capacity(nb_arrays as usize);
+
+        for _ in 0..nb_arrays {
+            let array_header = reader.read_u8()?;
+            let nb_nalus = reader.read_u16()?;
+            let mut nalus = Vec::with_capacity(nb_nalus as usize);
+
+            for _ in 0..nb_nalus {
+                let size = reader.read_u16()?;
+                nalus.push(reader.read_to_bytes(size as usize)?);
+            }
+
+            arrays.push(HevcNaluArray {
+                array_completeness: array_header >> 7 > 0,
+                nal_unit_type: array_header & 0x3F,
+                nalus,
+            });
+        }
+
+        Ok(HevcDecoderConfigurationRecord {
+            general_profile_space: ptl >> 6,
+            general_tier_flag: (ptl >> 5) & 1 > 0,
+            general_profile_idc: ptl & 0x1F,
+            general_profile_compatibility_flags,
+            general_constraint_indicator_flags,
+            general_level_idc,
+            min_spatial_segmentation_idc,
+            parallelism_type,
+            chroma_format_idc,
+            bit_depth_luma_minus8,
+            bit_depth_chroma_minus8,
+            avg_frame_rate,
+            constant_frame_rate: flags >> 6,
+            num_temporal_layers: (flags >> 3) & 0x07,
+            temporal_id_nested: (flags >> 2) & 1 > 0,
+            nalu_length,
+            arrays,
+        })
+    }
+}
+
+impl<E> Parser<E, Mpeg4AvcNALUSeq> for HevcParser {
+    type Error = Error<E>;
+
+    /// Split HEVC coded frames into NAL units, HEVC uses the same framing as AVC.
+    fn parse(&mut self, reader: &mut impl FlvReader) -> Result<Mpeg4AvcNALUSeq, Self::Error> {
+        Mpeg4AvcParser {
+            nalu_length: self.nalu_length,
+        }
+        .parse(reader)
+    }
+}
+
+impl<E> Writer<E, HevcDecoderConfigurationRecord> for FlvWriter {
+    type Error = Error<E>;
+
+    fn write(
+        &mut self,
+        buf: &mut BytesMut,
+        value: &HevcDecoderConfigurationRecord,
+    ) -> Result<(), Self::Error> {
+        /*configurationVersion*/
+        buf.put_u8(1);
+
+        buf.put_u8(
+            (value.general_profile_space << 6)
+                | ((value.general_tier_flag as u8) << 5)
+                | (value.general_profile_idc & 0x1F),
+        );
+        buf.put_u32(value.general_profile_compatibility_flags);
+        buf.put_uint(value.general_constraint_indicator_flags, 6);
+        buf.put_u8(value.general_level_idc);
+
+        buf.put_u16(0xF000 | (value.min_spatial_segmentation_idc & 0x0FFF));
+        buf.put_u8(0xFC | (value.parallelism_type & 0x03));
+        buf.put_u8(0xFC | (value.chroma_format_idc & 0x03));
+        buf.put_u8(0xF8 | (value.bit_depth_luma_minus8 & 0x07));
+        buf.put_u8(0xF8 | (value.bit_depth_chroma_minus8 & 0x07));
+        buf.put_u16(value.avg_frame_rate);
+
+        buf.put_u8(
+            (value.constant_frame_rate << 6)
+                | ((value.num_temporal_layers & 0x07) << 3)
+                | ((value.temporal_id_nested as u8) << 2)
+                | (value.nalu_length.saturating_sub(1) & 0x03),
+        );
+
+        /*numOfArrays*/
+        buf.put_u8(value.arrays.len() as u8);
+        for array in &value.arrays {
+            buf.put_u8(((array.array_completeness as u8) << 7) | (array.nal_unit_type & 0x3F));
+            buf.put_u16(array.nalus.len() as u16);
+
+            for nalu in &array.nalus {
+                buf.put_u16(nalu.len() as u16);
+                buf.put_slice(nalu);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{BufMut, Bytes, BytesMut};
+
+    use super::{
+        HevcDecoderConfigurationRecord, HevcParser, NALU_TYPE_PPS, NALU_TYPE_SPS, NALU_TYPE_VPS,
+    };
+    use crate::{
+        parser::Parser,
+        tag::video::mpeg4_avc::Mpeg4AvcNALUSeq,
+        writer::{FlvWriter, Writer},
+    };
+
+    /// 720p25 Main profile parameter sets.
+    const VPS: &[u8] = &[
+        0x40, 0x01, 0x0c, 0x01, 0xff, 0xff, 0x01, 0x60, 0x00, 0x00, 0x03, 0x00, 0xb0, 0x00, 0x00,
+        0x03, 0x00, 0x00, 0x03, 0x00, 0x5d, 0xac, 0x09,
+    ];
+    const SPS: &[u8] = &[
+        0x42, 0x01, 0x01, 0x01, 0x60, 0x00, 0x00, 0x03, 0x00, 0xb0, 0x00, 0x00, 0x03, 0x00, 0x00,
+        0x03, 0x00, 0x5d, 0xa0, 0x02, 0x80, 0x80, 0x2d, 0x16, 0x59, 0x59, 0xa4, 0x93, 0x2b, 0x9a,
+        0x80, 0x80, 0x80, 0x82, 0x00, 0x00, 0x03, 0x00, 0x02, 0x00, 0x00, 0x03, 0x00, 0x32, 0x10,
+    ];
+    const PPS: &[u8] = &[0x44, 0x01, 0xc1, 0x72, 0xb4, 0x62, 0x40];
+
+    fn record_bytes() -> BytesMut {
+        let mut data = BytesMut::new();
+        data.put_slice(&[1, 0x01, 0x60, 0, 0, 0, 0xb0, 0, 0, 0, 0, 0, 0x5d]);
+        data.put_slice(&[0xf0, 0, 0xfc, 0xfd, 0xf8, 0xf8, 0, 0, 0x0f, 3]);
+
+        for (nal_unit_type, nalu) in [(32, VPS), (33, SPS), (34, PPS)] {
+            data.put_u8(0x80 | nal_unit_type);
+            data.put_u16(1);
+            data.put_u16(nalu.len() as u16);
+            data.put_slice(nalu);
+        }
+
+        data
+    }
+
+    #[test]
+    fn record_round_trip() {
+        let data = record_bytes();
+        let mut parser = HevcParser::default();
+        let record: HevcDecoderConfigurationRecord =
+            Parser::<flowly::Void, _>::parse(&mut parser, &mut data.clone().freeze()).unwrap();
+
+        assert_eq!(parser.nalu_length, 4);
+        assert_eq!(record.general_profile_idc, 1);
+        assert_eq!(record.general_profile_compatibility_flags, 0x6000_0000);
+        assert_eq!(record.general_constraint_indicator_flags, 0xb000_0000_0000);
+        assert_eq!(record.general_level_idc, 93);
+        assert_eq!(record.chroma_format_idc, 1);
+        assert_eq!(record.num_temporal_layers, 1);
+        assert!(record.temporal_id_nested);
+        assert!(record.nalus().eq([VPS, SPS, PPS]));
+
+        let mut buf = BytesMut::new();
+        Writer::<flowly::Void, _>::write(&mut FlvWriter::default(), &mut buf, &record).unwrap();
+        assert_eq!(buf, data);
+    }
+
+    #[test]
+    fn from_params() {
+        let params = [PPS, SPS, VPS].map(Bytes::from_static);
+        let record = HevcDecoderConfigurationRecord::from_params(params, 4);
+
+        let types: Vec<_> = record.arrays.iter().map(|x| x.nal_unit_type).collect();
+        assert_eq!(types, [NALU_TYPE_VPS, NALU_TYPE_SPS, NALU_TYPE_PPS]);
+
+        // all of the fields are derived from the SPS
+        let parsed: HevcDecoderConfigurationRecord = Parser::<flowly::Void, _>::parse(
+            &mut HevcParser::default(),
+            &mut record_bytes().freeze(),
+        )
+        .unwrap();
+        assert_eq!(record, parsed);
+    }
+
+    #[test]
+    fn split_nalus() {
+        let mut parser = HevcParser { nalu_length: 4 };
+        let mut data = Bytes::from_static(&[0, 0, 0, 3, 0x26, 0x01, 0xaf, 0, 0, 0, 2, 0x02, 0x01]);
+
+        let seq: Mpeg4AvcNALUSeq =
+            Parser::<flowly::Void, _>::parse(&mut parser, &mut data).unwrap();
+        assert_eq!(seq.nalus, [&[0x26, 0x01, 0xaf][..], &[0x02, 0x01]]);
+    }
+
+    /// 1280x720 Main profile SPS re-encoded with 10 bit luma and chroma.
+    const SPS_10BIT: &[u8] = &[
+        0x42, 0x01, 0x01, 0x01, 0x60, 0x00, 0x00, 0x03, 0x00, 0xb0, 0x00, 0x00, 0x03, 0x00, 0x00,
+        0x03, 0x00, 0x5d, 0xa0, 0x02, 0x80, 0x80, 0x2d, 0x13, 0x65, 0x95, 0x9a, 0x49, 0x32, 0xb9,
+        0xa8, 0x08, 0x08, 0x08, 0x20, 0x00, 0x00, 0x03, 0x00, 0x20, 0x00, 0x00, 0x03, 0x03, 0x21,
+    ];
+
+    #[test]
+    fn from_params_uses_sps() {
+        let record =
+            HevcDecoderConfigurationRecord::from_params([Bytes::from_static(SPS_10BIT)], 4);
+
+        assert_eq!(record.general_profile_idc, 1);
+        assert_eq!(record.general_level_idc, 93);
+        assert_eq!(record.chroma_format_idc, 1);
+        assert_eq!(record.bit_depth_luma_minus8, 2);
+        assert_eq!(record.bit_depth_chroma_minus8, 2);
+        assert_eq!(record.arrays.len(), 1);
+        assert_eq!(record.arrays[0].nal_unit_type, NALU_TYPE_SPS);
+
+        let sps = record.parse_sps().unwrap().unwrap();
+        assert_eq!((sps.width(), sps.height()), (1280, 720));
+    }
+}
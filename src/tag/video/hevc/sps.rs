@@ -0,0 +1,581 @@
+use std::io::ErrorKind;
+
+use crate::{
+    bitreader::{BitReader, remove_emulation_prevention},
+    tag::video::vui::read_sample_aspect_ratio,
+};
+
+use super::{NALU_TYPE_SPS, NALU_TYPE_VPS};
+
+/// `transfer_characteristics` of SMPTE ST 2084 (PQ).
+pub const TRANSFER_PQ: u8 = 16;
+
+/// `transfer_characteristics` of ARIB STD-B67 (HLG).
+pub const TRANSFER_HLG: u8 = 18;
+
+/// Max number of `st_ref_pic_set()` in SPS.
+const MAX_SHORT_TERM_REF_PIC_SETS: u32 = 64;
+
+/// Max number of long term reference pictures in SPS.
+const MAX_LONG_TERM_REF_PICS: u32 = 32;
+
+/// General part of `profile_tier_level()`, see ITU-T H.265, 7.3.3
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct HevcProfileTierLevel {
+    pub general_profile_space: u8,
+    pub general_tier_flag: bool,
+    pub general_profile_idc: u8,
+    pub general_profile_compatibility_flags: u32,
+
+    /// 48 bits of general constraint indicator flags
+    pub general_constraint_indicator_flags: u64,
+    pub general_level_idc: u8,
+}
+
+/// Video parameter set, see ITU-T H.265, 7.3.2.1
+///
+/// The parsing stops after timing info, layer sets and HRD parameters are skipped.
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct HevcVps {
+    pub vps_video_parameter_set_id: u8,
+    pub vps_max_layers_minus1: u8,
+    pub vps_max_sub_layers_minus1: u8,
+    pub vps_temporal_id_nesting_flag: bool,
+    pub profile_tier_level: HevcProfileTierLevel,
+
+    /// `vps_num_units_in_tick` and `vps_time_scale`.
+    pub timing_info: Option<(u32, u32)>,
+}
+
+/// Sequence parameter set, see ITU-T H.265, 7.3.2.2
+///
+/// The parsing stops after VUI timing info, SPS extensions are skipped.
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct HevcSps {
+    pub sps_video_parameter_set_id: u8,
+    pub sps_max_sub_layers_minus1: u8,
+    pub sps_temporal_id_nesting_flag: bool,
+    pub profile_tier_level: HevcProfileTierLevel,
+    pub sps_seq_parameter_set_id: u32,
+
+    /// 0 - monochrome, 1 - 4:2:0, 2 - 4:2:2, 3 - 4:4:4
+    pub chroma_format_idc: u8,
+    pub separate_colour_plane_flag: bool,
+    pub pic_width_in_luma_samples: u32,
+    pub pic_height_in_luma_samples: u32,
+
+    /// Conformance window in units of `SubWidthC` and `SubHeightC`, zeros if not present.
+    pub conf_win_left_offset: u32,
+    pub conf_win_right_offset: u32,
+    pub conf_win_top_offset: u32,
+    pub conf_win_bottom_offset: u32,
+
+    pub bit_depth_luma_minus8: u8,
+    pub bit_depth_chroma_minus8: u8,
+    pub log2_max_pic_order_cnt_lsb_minus4: u8,
+    pub vui: Option<HevcVui>,
+}
+
+/// Video usability information, see ITU-T H.265, E.2.1
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct HevcVui {
+    /// Sample aspect ratio as width and height, `None` if not present or unspecified.
+    pub sample_aspect_ratio: Option<(u16, u16)>,
+
+    /// 0 - component, 1 - PAL, 2 - NTSC, 3 - SECAM, 4 - MAC, 5 - unspecified
+    pub video_format: u8,
+    pub video_full_range_flag: bool,
+
+    /// `colour_primaries`, `transfer_characteristics` and `matrix_coefficients`, see ITU-T H.273
+    pub colour_description: Option<(u8, u8, u8)>,
+
+    /// Each picture is a field rather than a frame.
+    pub field_seq_flag: bool,
+
+    /// `vui_num_units_in_tick` and `vui_time_scale`.
+    pub timing_info: Option<(u32, u32)>,
+}
+
+impl HevcProfileTierLevel {
+    /// Parse `profile_tier_level(1, max_sub_layers_minus1)` skipping the sub-layer part.
+    fn parse(r: &mut BitReader<'_>, max_sub_layers_minus1: u8) -> std::io::Result<Self> {
+        let x = HevcProfileTierLevel {
+            general_profile_space: r.read_u8(2)?,
+            general_tier_flag: r.read_bit()?,
+            general_profile_idc: r.read_u8(5)?,
+            general_profile_compatibility_flags: r.read_u32(32)?,
+            general_constraint_indicator_flags: r.read_bits(48)?,
+            general_level_idc: r.read_u8(8)?,
+        };
+
+        let mut sub_layers = Vec::with_capacity(max_sub_layers_minus1 as usize);
+
+        for _ in 0..max_sub_layers_minus1 {
+            // sub_layer_profile_present_flag, sub_layer_level_present_flag
+            sub_layers.push((r.read_bit()?, r.read_bit()?));
+        }
+
+        if max_sub_layers_minus1 > 0 {
+            // reserved_zero_2bits
+            r.skip_bits(2 * (8 - max_sub_layers_minus1 as usize))?;
+        }
+
+        for (profile_present, level_present) in sub_layers {
+            if profile_present {
+                r.skip_bits(88)?;
+            }
+
+            if level_present {
+                r.skip_bits(8)?;
+            }
+        }
+
+        Ok(x)
+    }
+}
+
+impl HevcVps {
+    /// Parse VPS NAL unit including the NAL unit header.
+    pub fn parse(nalu: &[u8]) -> std::io::Result<Self> {
+        let rbsp = nalu_rbsp(nalu, NALU_TYPE_VPS)?;
+        let mut r = BitReader::new(&rbsp);
+
+        let vps_video_parameter_set_id = r.read_u8(4)?;
+
+        // vps_base_layer_internal_flag, vps_base_layer_available_flag
+        r.skip_bits(2)?;
+
+        let vps_max_layers_minus1 = r.read_u8(6)?;
+        let vps_max_sub_layers_minus1 = read_max_sub_layers_minus1(&mut r)?;
+        let vps_temporal_id_nesting_flag = r.read_bit()?;
+
+        // vps_reserved_0xffff_16bits
+        r.skip_bits(16)?;
+
+        let mut x = HevcVps {
+            vps_video_parameter_set_id,
+            vps_max_layers_minus1,
+            vps_max_sub_layers_minus1,
+            vps_temporal_id_nesting_flag,
+            profile_tier_level: HevcProfileTierLevel::parse(&mut r, vps_max_sub_layers_minus1)?,
+            timing_info: None,
+        };
+
+        skip_sub_layer_ordering_info(&mut r, vps_max_sub_layers_minus1)?;
+
+        let vps_max_layer_id = r.read_u8(6)?;
+        let vps_num_layer_sets_minus1 = r.read_ue_max(1023)?;
+
+        // layer_id_included_flag
+        r.skip_bits(vps_num_layer_sets_minus1 as usize * (vps_max_layer_id as usize + 1))?;
+
+        // vps_timing_info_present_flag
+        if r.read_bit()? {
+            x.timing_info = Some((r.read_u32(32)?, r.read_u32(32)?));
+        }
+
+        Ok(x)
+    }
+
+    /// Frame rate from timing info, `None` if not present.
+    pub fn frame_rate(&self) -> Option<f64> {
+        frame_rate(self.timing_info?)
+    }
+}
+
+impl HevcSps {
+    /// Parse SPS NAL unit including the NAL unit header.
+    pub fn parse(nalu: &[u8]) -> std::io::Result<Self> {
+        let rbsp = nalu_rbsp(nalu, NALU_TYPE_SPS)?;
+        let mut r = BitReader::new(&rbsp);
+
+        let sps_video_parameter_set_id = r.read_u8(4)?;
+        let sps_max_sub_layers_minus1 = read_max_sub_layers_minus1(&mut r)?;
+        let sps_temporal_id_nesting_flag = r.read_bit()?;
+
+        let mut x = HevcSps {
+            sps_video_parameter_set_id,
+            sps_max_sub_layers_minus1,
+            sps_temporal_id_nesting_flag,
+            profile_tier_level: HevcProfileTierLevel::parse(&mut r, sps_max_sub_layers_minus1)?,
+            sps_seq_parameter_set_id: r.read_ue_max(15)?,
+            chroma_format_idc: r.read_ue_max(3)? as u8,
+            ..Default::default()
+        };
+
+        if x.chroma_format_idc == 3 {
+            x.separate_colour_plane_flag = r.read_bit()?;
+        }
+
+        x.pic_width_in_luma_samples = r.read_ue()?;
+        x.pic_height_in_luma_samples = r.read_ue()?;
+
+        // conformance_window_flag
+        if r.read_bit()? {
+            x.conf_win_left_offset = r.read_ue()?;
+            x.conf_win_right_offset = r.read_ue()?;
+            x.conf_win_top_offset = r.read_ue()?;
+            x.conf_win_bottom_offset = r.read_ue()?;
+        }
+
+        x.bit_depth_luma_minus8 = r.read_ue_max(8)? as u8;
+        x.bit_depth_chroma_minus8 = r.read_ue_max(8)? as u8;
+        x.log2_max_pic_order_cnt_lsb_minus4 = r.read_ue_max(12)? as u8;
+
+        skip_sub_layer_ordering_info(&mut r, sps_max_sub_layers_minus1)?;
+
+        // log2_min_luma_coding_block_size_minus3, log2_diff_max_min_luma_coding_block_size,
+        // log2_min_luma_transform_block_size_minus2, log2_diff_max_min_luma_transform_block_size,
+        // max_transform_hierarchy_depth_inter, max_transform_hierarchy_depth_intra
+        for _ in 0..6 {
+            r.read_ue()?;
+        }
+
+        // scaling_list_enabled_flag, sps_scaling_list_data_present_flag
+        if r.read_bit()? && r.read_bit()? {
+            skip_scaling_list_data(&mut r)?;
+        }
+
+        // amp_enabled_flag, sample_adaptive_offset_enabled_flag
+        r.skip_bits(2)?;
+
+        // pcm_enabled_flag
+        if r.read_bit()? {
+            // pcm_sample_bit_depth_luma_minus1, pcm_sample_bit_depth_chroma_minus1,
+            // log2_min_pcm_luma_coding_block_size_minus3,
+            // log2_diff_max_min_pcm_luma_coding_block_size, pcm_loop_filter_disabled_flag
+            r.skip_bits(8)?;
+            r.read_ue()?;
+            r.read_ue()?;
+            r.skip_bits(1)?;
+        }
+
+        skip_short_term_ref_pic_sets(&mut r)?;
+
+        // long_term_ref_pics_present_flag
+        if r.read_bit()? {
+            let count = r.read_ue_max(MAX_LONG_TERM_REF_PICS)? as usize;
+
+            // lt_ref_pic_poc_lsb_sps, used_by_curr_pic_lt_sps_flag
+            r.skip_bits(count * (x.log2_max_pic_order_cnt_lsb_minus4 as usize + 4 + 1))?;
+        }
+
+        // sps_temporal_mvp_enabled_flag, strong_intra_smoothing_enabled_flag
+        r.skip_bits(2)?;
+
+        // vui_parameters_present_flag
+        if r.read_bit()? {
+            x.vui = Some(HevcVui::parse(&mut r)?);
+        }
+
+        Ok(x)
+    }
+
+    /// `ChromaArrayType`, zero if the colour planes are coded separately.
+    #[inline]
+    pub fn chroma_array_type(&self) -> u8 {
+        if self.separate_colour_plane_flag {
+            0
+        } else {
+            self.chroma_format_idc
+        }
+    }
+
+    #[inline]
+    pub fn bit_depth_luma(&self) -> u8 {
+        self.bit_depth_luma_minus8 + 8
+    }
+
+    #[inline]
+    pub fn bit_depth_chroma(&self) -> u8 {
+        self.bit_depth_chroma_minus8 + 8
+    }
+
+    /// `SubWidthC` and `SubHeightC`.
+    fn sub_sampling(&self) -> (u32, u32) {
+        match self.chroma_array_type() {
+            1 => (2, 2),
+            2 => (2, 1),
+            _ => (1, 1),
+        }
+    }
+
+    /// Width of the picture after cropping to the conformance window.
+    pub fn width(&self) -> u32 {
+        let (unit, _) = self.sub_sampling();
+        let crop = self
+            .conf_win_left_offset
+            .saturating_add(self.conf_win_right_offset)
+            .saturating_mul(unit);
+
+        self.pic_width_in_luma_samples.saturating_sub(crop)
+    }
+
+    /// Height of the picture after cropping to the conformance window.
+    pub fn height(&self) -> u32 {
+        let (_, unit) = self.sub_sampling();
+        let crop = self
+            .conf_win_top_offset
+            .saturating_add(self.conf_win_bottom_offset)
+            .saturating_mul(unit);
+
+        self.pic_height_in_luma_samples.saturating_sub(crop)
+    }
+
+    /// Frame rate from VUI timing info, `None` if not present.
+    pub fn frame_rate(&self) -> Option<f64> {
+        frame_rate(self.vui.as_ref()?.timing_info?)
+    }
+
+    /// `transfer_characteristics` from VUI colour description, `None` if not present.
+    pub fn transfer_characteristics(&self) -> Option<u8> {
+        self.vui
+            .as_ref()?
+            .colour_description
+            .map(|(_, transfer, _)| transfer)
+    }
+
+    /// Is HDR stream, transferred with PQ or HLG.
+    pub fn is_hdr(&self) -> bool {
+        matches!(
+            self.transfer_characteristics(),
+            Some(TRANSFER_PQ | TRANSFER_HLG)
+        )
+    }
+}
+
+impl HevcVui {
+    fn parse(r: &mut BitReader<'_>) -> std::io::Result<Self> {
+        let mut x = HevcVui {
+            video_format: 5,
+            ..Default::default()
+        };
+
+        // aspect_ratio_info_present_flag
+        if r.read_bit()? {
+            x.sample_aspect_ratio = read_sample_aspect_ratio(r)?;
+        }
+
+        // overscan_info_present_flag, overscan_appropriate_flag
+        if r.read_bit()? {
+            r.skip_bits(1)?;
+        }
+
+        // video_signal_type_present_flag
+        if r.read_bit()? {
+            x.video_format = r.read_u8(3)?;
+            x.video_full_range_flag = r.read_bit()?;
+
+            // colour_description_present_flag
+            if r.read_bit()? {
+                x.colour_description = Some((r.read_u8(8)?, r.read_u8(8)?, r.read_u8(8)?));
+            }
+        }
+
+        // chroma_loc_info_present_flag, chroma_sample_loc_type_top_field,
+        // chroma_sample_loc_type_bottom_field
+        if r.read_bit()? {
+            r.read_ue()?;
+            r.read_ue()?;
+        }
+
+        // neutral_chroma_indication_flag
+        r.skip_bits(1)?;
+
+        x.field_seq_flag = r.read_bit()?;
+
+        // frame_field_info_present_flag
+        r.skip_bits(1)?;
+
+        // default_display_window_flag and the offsets
+        if r.read_bit()? {
+            for _ in 0..4 {
+                r.read_ue()?;
+            }
+        }
+
+        // vui_timing_info_present_flag
+        if r.read_bit()? {
+            x.timing_info = Some((r.read_u32(32)?, r.read_u32(32)?));
+        }
+
+        Ok(x)
+    }
+}
+
+/// Check NAL unit type and get RBSP following the NAL unit header.
+fn nalu_rbsp(nalu: &[u8], nal_unit_type: u8) -> std::io::Result<Vec<u8>> {
+    if nalu.len() < 2 || (nalu[0] >> 1) & 0x3F != nal_unit_type {
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidData,
+            format!("not a nal unit of type {}", nal_unit_type),
+        ));
+    }
+
+    Ok(remove_emulation_prevention(&nalu[2..]))
+}
+
+fn frame_rate((num_units_in_tick, time_scale): (u32, u32)) -> Option<f64> {
+    (num_units_in_tick > 0 && time_scale > 0).then(|| time_scale as f64 / num_units_in_tick as f64)
+}
+
+/// Read `max_sub_layers_minus1`, the value 7 is reserved.
+fn read_max_sub_layers_minus1(r: &mut BitReader<'_>) -> std::io::Result<u8> {
+    let value = r.read_u8(3)?;
+
+    if value > 6 {
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidData,
+            "invalid max_sub_layers_minus1 7",
+        ));
+    }
+
+    Ok(value)
+}
+
+/// Skip `max_dec_pic_buffering_minus1`, `max_num_reorder_pics` and `max_latency_increase_plus1`
+/// of the sub-layers.
+fn skip_sub_layer_ordering_info(
+    r: &mut BitReader<'_>,
+    max_sub_layers_minus1: u8,
+) -> std::io::Result<()> {
+    // sub_layer_ordering_info_present_flag
+    let count = if r.read_bit()? {
+        max_sub_layers_minus1 + 1
+    } else {
+        1
+    };
+
+    for _ in 0..count * 3 {
+        r.read_ue()?;
+    }
+
+    Ok(())
+}
+
+/// Skip `scaling_list_data()`, see ITU-T H.265, 7.3.4
+fn skip_scaling_list_data(r: &mut BitReader<'_>) -> std::io::Result<()> {
+    for size_id in 0..4 {
+        let step = if size_id == 3 { 3 } else { 1 };
+
+        for _ in (0..6).step_by(step) {
+            // scaling_list_pred_mode_flag
+            if !r.read_bit()? {
+                // scaling_list_pred_matrix_id_delta
+                r.read_ue()?;
+                continue;
+            }
+
+            if size_id > 1 {
+                // scaling_list_dc_coef_minus8
+                r.read_se()?;
+            }
+
+            // scaling_list_delta_coef
+            for _ in 0..(1 << (4 + (size_id << 1))).min(64) {
+                r.read_se()?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Skip `st_ref_pic_set()` of SPS, see ITU-T H.265, 7.3.7
+fn skip_short_term_ref_pic_sets(r: &mut BitReader<'_>) -> std::io::Result<()> {
+    let count = r.read_ue_max(MAX_SHORT_TERM_REF_PIC_SETS)?;
+
+    // NumDeltaPocs of the previous set
+    let mut num_delta_pocs = 0u32;
+
+    for idx in 0..count {
+        // inter_ref_pic_set_prediction_flag
+        if idx != 0 && r.read_bit()? {
+            // delta_rps_sign, abs_delta_rps_minus1
+            r.skip_bits(1)?;
+            r.read_ue()?;
+
+            let mut next = 0;
+
+            for _ in 0..=num_delta_pocs {
+                // used_by_curr_pic_flag, use_delta_flag
+                if r.read_bit()? || r.read_bit()? {
+                    next += 1;
+                }
+            }
+
+            num_delta_pocs = next;
+        } else {
+            let num_negative_pics = r.read_ue_max(16)?;
+            let num_positive_pics = r.read_ue_max(16)?;
+
+            for _ in 0..num_negative_pics + num_positive_pics {
+                // delta_poc_sX_minus1, used_by_curr_pic_sX_flag
+                r.read_ue()?;
+                r.skip_bits(1)?;
+            }
+
+            num_delta_pocs = num_negative_pics + num_positive_pics;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HevcSps, HevcVps};
+
+    /// 720p25 Main profile parameter sets.
+    const VPS: &[u8] = &[
+        0x40, 0x01, 0x0c, 0x01, 0xff, 0xff, 0x01, 0x60, 0x00, 0x00, 0x03, 0x00, 0xb0, 0x00, 0x00,
+        0x03, 0x00, 0x00, 0x03, 0x00, 0x5d, 0xac, 0x09,
+    ];
+    const SPS: &[u8] = &[
+        0x42, 0x01, 0x01, 0x01, 0x60, 0x00, 0x00, 0x03, 0x00, 0xb0, 0x00, 0x00, 0x03, 0x00, 0x00,
+        0x03, 0x00, 0x5d, 0xa0, 0x02, 0x80, 0x80, 0x2d, 0x16, 0x59, 0x59, 0xa4, 0x93, 0x2b, 0x9a,
+        0x80, 0x80, 0x80, 0x82, 0x00, 0x00, 0x03, 0x00, 0x02, 0x00, 0x00, 0x03, 0x00, 0x32, 0x10,
+    ];
+
+    #[test]
+    fn vps() {
+        let vps = HevcVps::parse(VPS).unwrap();
+
+        assert_eq!(vps.vps_max_sub_layers_minus1, 0);
+        assert!(vps.vps_temporal_id_nesting_flag);
+        assert_eq!(vps.profile_tier_level.general_profile_idc, 1);
+        assert_eq!(vps.profile_tier_level.general_level_idc, 93);
+        assert_eq!(
+            vps.profile_tier_level.general_profile_compatibility_flags,
+            0x6000_0000
+        );
+        assert_eq!(vps.frame_rate(), None);
+    }
+
+    #[test]
+    fn sps() {
+        let sps = HevcSps::parse(SPS).unwrap();
+
+        assert_eq!(
+            sps.profile_tier_level,
+            HevcVps::parse(VPS).unwrap().profile_tier_level
+        );
+        assert_eq!(sps.chroma_format_idc, 1);
+        assert_eq!((sps.bit_depth_luma(), sps.bit_depth_chroma()), (8, 8));
+        assert_eq!((sps.width(), sps.height()), (1280, 720));
+        assert_eq!(sps.frame_rate(), Some(25.0));
+        assert_eq!(sps.transfer_characteristics(), Some(1));
+        assert!(!sps.is_hdr());
+
+        let vui = sps.vui.unwrap();
+        assert_eq!(vui.colour_description, Some((1, 1, 1)));
+        assert_eq!(vui.timing_info, Some((1, 25)));
+    }
+
+    #[test]
+    fn wrong_nalu_type() {
+        assert!(HevcSps::parse(VPS).is_err());
+        assert!(HevcVps::parse(SPS).is_err());
+        assert!(HevcSps::parse(&SPS[..20]).is_err());
+    }
+}
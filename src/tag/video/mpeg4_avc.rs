@@ -1,30 +1,120 @@
-use bytes::Bytes;
+use bytes::{BufMut, Bytes, BytesMut};
 
-use crate::{error::Error, parser::Parser, reader::FlvReader};
+use crate::{
+    error::Error,
+    parser::Parser,
+    reader::FlvReader,
+    writer::{FlvWriter, Writer},
+};
 
+use sps::AvcSps;
+
+pub mod sps;
+
+/// NAL unit type of the sequence parameter set.
+pub const NALU_TYPE_SPS: u8 = 7;
+
+/// NAL unit type of the picture parameter set.
+pub const NALU_TYPE_PPS: u8 = 8;
+
+/// NAL unit type of the sequence parameter set extension.
+pub const NALU_TYPE_SPS_EXT: u8 = 13;
+
+/// The only defined `configurationVersion` of AVCDecoderConfigurationRecord.
+const AVC_CONFIG_VERSION: u8 = 1;
+
+/// AVCDecoderConfigurationRecord, see ISO/IEC 14496-15:2022, 5.3.2.1
 #[derive(Default, Clone, Debug)]
 pub struct Mpeg4AvcRecord {
+    pub version: u8,
     pub profile: u8,
     pub compatibility: u8,
     pub level: u8,
     pub nalu_length: u8,
     pub sps: Vec<Bytes>,
     pub pps: Vec<Bytes>,
-}
 
-#[derive(Clone, Debug)]
-pub struct Mpeg4AvcParser {
-    pub nalu_length: u8,
+    /// Is the record followed by the high profile extension, which is missing in the records
+    /// of some encoders, see `has_high_profile_ext`.
+    pub high_profile_ext: bool,
+
+    /// 0 - monochrome, 1 - 4:2:0, 2 - 4:2:2, 3 - 4:4:4, the fields below are carried by
+    /// the high profile extension only.
+    pub chroma_format: u8,
+    pub bit_depth_luma_minus8: u8,
+    pub bit_depth_chroma_minus8: u8,
+    pub sps_ext: Vec<Bytes>,
 }
 
-impl Default for Mpeg4AvcParser {
-    fn default() -> Self {
-        Self {
-            nalu_length: Default::default(),
+impl Mpeg4AvcRecord {
+    /// Build a configuration record from SPS and PPS NAL units.
+    ///
+    /// Profile, compatibility, level, chroma format and bit depth are taken from the first SPS.
+    pub fn from_params(params: impl IntoIterator<Item = Bytes>, nalu_length: u8) -> Self {
+        let (mut sps, mut pps, mut sps_ext) = (Vec::new(), Vec::new(), Vec::new());
+
+        for nalu in params {
+            match nalu.first().map(|x| x & 0x1F) {
+                Some(NALU_TYPE_SPS) => sps.push(nalu),
+                Some(NALU_TYPE_PPS) => pps.push(nalu),
+                Some(NALU_TYPE_SPS_EXT) => sps_ext.push(nalu),
+                _ => (),
+            }
         }
+
+        let (profile, compatibility, level) = match sps.first() {
+            Some(x) if x.len() >= 4 => (x[1], x[2], x[3]),
+            _ => (0, 0, 0),
+        };
+
+        let parsed = sps.first().and_then(|x| AvcSps::parse(x).ok());
+
+        Mpeg4AvcRecord {
+            version: AVC_CONFIG_VERSION,
+            profile,
+            compatibility,
+            level,
+            nalu_length,
+            sps,
+            pps,
+            high_profile_ext: is_high_profile(profile),
+            chroma_format: parsed.as_ref().map_or(1, |x| x.chroma_format_idc),
+            bit_depth_luma_minus8: parsed.as_ref().map_or(0, |x| x.bit_depth_luma_minus8),
+            bit_depth_chroma_minus8: parsed.as_ref().map_or(0, |x| x.bit_depth_chroma_minus8),
+            sps_ext,
+        }
+    }
+
+    /// Is the record of a high profile, which is followed by chroma format, bit depth and
+    /// SPS-ext.
+    #[inline]
+    pub fn has_high_profile_ext(&self) -> bool {
+        is_high_profile(self.profile)
+    }
+
+    /// Parameter sets of the record, SPS followed by SPS-ext and PPS.
+    pub fn params(&self) -> impl Iterator<Item = &Bytes> {
+        self.sps.iter().chain(&self.sps_ext).chain(&self.pps)
+    }
+
+    /// Parse the first SPS, `None` if the record has no SPS.
+    ///
+    /// Provides picture size, frame rate, chroma format and bit depth of the stream.
+    pub fn parse_sps(&self) -> std::io::Result<Option<AvcSps>> {
+        self.sps.first().map(|x| AvcSps::parse(x)).transpose()
     }
 }
 
+#[inline]
+fn is_high_profile(profile: u8) -> bool {
+    matches!(profile, 100 | 110 | 122 | 144)
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct Mpeg4AvcParser {
+    pub nalu_length: u8,
+}
+
 impl<E> Parser<E, Mpeg4AvcRecord> for Mpeg4AvcParser {
     type Error = Error<E>;
 
@@ -32,7 +122,10 @@ impl<E> Parser<E, Mpeg4AvcRecord> for Mpeg4AvcParser {
         let (mut pps, mut sps) = (Vec::new(), Vec::new());
 
         /*version */
-        reader.read_u8()?;
+        let version = reader.read_u8()?;
+        if version != AVC_CONFIG_VERSION {
+            return Err(Error::InvalidAvcConfigVersion(version));
+        }
 
         /*avc profile*/
         let profile = reader.read_u8()?;
@@ -66,18 +159,52 @@ impl<E> Parser<E, Mpeg4AvcRecord> for Mpeg4AvcParser {
             pps.push(pps_data);
         }
 
-        Ok(Mpeg4AvcRecord {
+        let mut record = Mpeg4AvcRecord {
+            version,
             profile,
             compatibility,
             level,
             nalu_length,
             sps,
             pps,
-        })
+            chroma_format: 1,
+            ..Default::default()
+        };
+
+        // the extension is missing in the records of some encoders
+        if record.has_high_profile_ext() && reader.available() > 0 {
+            record.high_profile_ext = true;
+
+            /*chroma format*/
+            record.chroma_format = reader.read_u8()? & 0x03;
+
+            /*bit depth luma and chroma*/
+            record.bit_depth_luma_minus8 = reader.read_u8()? & 0x07;
+            record.bit_depth_chroma_minus8 = reader.read_u8()? & 0x07;
+
+            let bit_depth_minus8 = record
+                .bit_depth_luma_minus8
+                .max(record.bit_depth_chroma_minus8);
+
+            if bit_depth_minus8 > 6 {
+                return Err(Error::InvalidAvcBitDepth(bit_depth_minus8 + 8));
+            }
+
+            /*number of SPS-ext NALUs*/
+            let nb_sps_ext = reader.read_u8()?;
+            for _ in 0..nb_sps_ext as usize {
+                let sps_ext_data_size = reader.read_u16()?;
+                let sps_ext_data = reader.read_to_bytes(sps_ext_data_size as usize)?;
+                record.sps_ext.push(sps_ext_data);
+            }
+        }
+
+        Ok(record)
     }
 }
 
 impl Mpeg4AvcParser {
+    /// Read NAL unit size, fails if `nalu_length` is not known from the configuration record.
     #[inline]
     fn read_nalu_size(&mut self, reader: &mut impl FlvReader) -> std::io::Result<usize> {
         Ok(match self.nalu_length {
@@ -85,7 +212,12 @@ impl Mpeg4AvcParser {
             2 => reader.read_u16()? as _,
             3 => reader.read_u24()? as _,
             4 => reader.read_u32()? as _,
-            _ => unreachable!(),
+            _ => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("invalid nalu length size {}", self.nalu_length),
+                ));
+            }
         })
     }
 }
@@ -99,10 +231,176 @@ impl<E> Parser<E, Mpeg4AvcNALUSeq> for Mpeg4AvcParser {
 
     fn parse(&mut self, reader: &mut impl FlvReader) -> Result<Mpeg4AvcNALUSeq, Self::Error> {
         let mut nalus = Vec::new();
-        while let Ok(size) = self.read_nalu_size(reader) {
+        while reader.available() > 0 {
+            let size = match self.read_nalu_size(reader) {
+                Ok(size) => size,
+
+                // trailing bytes shorter than the size field
+                Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(err) => return Err(err.into()),
+            };
+
             nalus.push(reader.read_to_bytes(size)?);
         }
 
         Ok(Mpeg4AvcNALUSeq { nalus })
     }
 }
+
+impl<E> Writer<E, Mpeg4AvcRecord> for FlvWriter {
+    type Error = Error<E>;
+
+    fn write(&mut self, buf: &mut BytesMut, value: &Mpeg4AvcRecord) -> Result<(), Self::Error> {
+        /*version */
+        buf.put_u8(AVC_CONFIG_VERSION);
+
+        /*avc profile*/
+        buf.put_u8(value.profile);
+
+        /*avc compatibility*/
+        buf.put_u8(value.compatibility);
+
+        /*avc level*/
+        buf.put_u8(value.level);
+
+        /*nalu length*/
+        buf.put_u8(0xFC | (value.nalu_length.saturating_sub(1) & 0x03));
+
+        /*number of SPS NALUs */
+        buf.put_u8(0xE0 | (value.sps.len() as u8 & 0x1F));
+        for sps in &value.sps {
+            buf.put_u16(sps.len() as u16);
+            buf.put_slice(sps);
+        }
+
+        /*number of PPS NALUs*/
+        buf.put_u8(value.pps.len() as u8);
+        for pps in &value.pps {
+            buf.put_u16(pps.len() as u16);
+            buf.put_slice(pps);
+        }
+
+        if value.high_profile_ext {
+            /*chroma format*/
+            buf.put_u8(0xFC | (value.chroma_format & 0x03));
+
+            /*bit depth luma and chroma*/
+            buf.put_u8(0xF8 | (value.bit_depth_luma_minus8 & 0x07));
+            buf.put_u8(0xF8 | (value.bit_depth_chroma_minus8 & 0x07));
+
+            /*number of SPS-ext NALUs*/
+            buf.put_u8(value.sps_ext.len() as u8);
+            for sps_ext in &value.sps_ext {
+                buf.put_u16(sps_ext.len() as u16);
+                buf.put_slice(sps_ext);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FlvWriter {
+    /// Write NAL units of the track, each prefixed with its size.
+    pub(crate) fn write_nalus<'a>(
+        &mut self,
+        buf: &mut BytesMut,
+        track_id: u8,
+        nalus: impl IntoIterator<Item = &'a [u8]>,
+    ) {
+        let nalu_length = self
+            .track_nalu_length
+            .get(&track_id)
+            .copied()
+            .unwrap_or(self.nalu_length);
+
+        for nalu in nalus {
+            buf.put_uint(nalu.len() as u64, nalu_length as usize);
+            buf.put_slice(nalu);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{Bytes, BytesMut};
+
+    use super::{Mpeg4AvcNALUSeq, Mpeg4AvcParser, Mpeg4AvcRecord};
+    use crate::{
+        error::Error,
+        parser::Parser,
+        writer::{FlvWriter, Writer},
+    };
+
+    /// High profile record of 1920x1080 stream with no high profile extension.
+    const RECORD: &[u8] = &[
+        0x01, 0x64, 0x00, 0x2a, 0xff, 0xe1, 0x00, 0x1b, 0x67, 0x64, 0x00, 0x2a, 0xac, 0xd9, 0x40,
+        0x78, 0x02, 0x27, 0xe5, 0xc0, 0x44, 0x00, 0x00, 0x03, 0x00, 0x04, 0x00, 0x00, 0x03, 0x00,
+        0x78, 0x3c, 0x60, 0xc6, 0x58, 0x01, 0x00, 0x06, 0x68, 0xeb, 0xe3, 0xcb, 0x22, 0xc0,
+    ];
+
+    fn parse(data: &[u8]) -> Result<Mpeg4AvcRecord, Error> {
+        Mpeg4AvcParser::default().parse(&mut Bytes::copy_from_slice(data))
+    }
+
+    fn write(record: &Mpeg4AvcRecord) -> BytesMut {
+        let mut buf = BytesMut::new();
+        Writer::<flowly::Void, _>::write(&mut FlvWriter::default(), &mut buf, record).unwrap();
+        buf
+    }
+
+    #[test]
+    fn record_round_trip() {
+        let record = parse(RECORD).unwrap();
+        assert!(!record.high_profile_ext);
+        assert_eq!(record.sps.len(), 1);
+        assert_eq!(record.pps.len(), 1);
+        assert_eq!(write(&record), RECORD);
+
+        let mut data = RECORD.to_vec();
+        data.extend_from_slice(&[0xfd, 0xf8, 0xf8, 0x00]);
+
+        let record = parse(&data).unwrap();
+        assert!(record.high_profile_ext);
+        assert_eq!(record.chroma_format, 1);
+        assert_eq!(write(&record), data);
+
+        let record = Mpeg4AvcRecord::from_params(record.params().cloned(), 4);
+        assert!(record.high_profile_ext);
+        assert_eq!(write(&record), data);
+    }
+
+    #[test]
+    fn invalid_record() {
+        let mut data = RECORD.to_vec();
+        data[0] = 2;
+        assert!(matches!(
+            parse(&data),
+            Err(Error::InvalidAvcConfigVersion(2))
+        ));
+
+        let mut data = RECORD.to_vec();
+        data.extend_from_slice(&[0xfd, 0xff, 0xf8, 0x00]);
+        assert!(matches!(parse(&data), Err(Error::InvalidAvcBitDepth(15))));
+    }
+
+    #[test]
+    fn nalus_without_config() {
+        let mut data = Bytes::from_static(&[0, 0, 0, 2, 0x65, 0x88]);
+
+        let res: Result<Mpeg4AvcNALUSeq, Error> =
+            Parser::parse(&mut Mpeg4AvcParser::default(), &mut data);
+
+        assert!(matches!(res, Err(Error::IoError(_))));
+    }
+
+    #[test]
+    fn nalus_with_config() {
+        let mut parser = Mpeg4AvcParser { nalu_length: 4 };
+        let mut data = Bytes::from_static(&[0, 0, 0, 2, 0x65, 0x88]);
+
+        let seq: Mpeg4AvcNALUSeq =
+            Parser::<flowly::Void, _>::parse(&mut parser, &mut data).unwrap();
+        assert_eq!(seq.nalus, [Bytes::from_static(&[0x65, 0x88])]);
+    }
+}
@@ -0,0 +1,354 @@
+use std::io::ErrorKind;
+
+use crate::{
+    bitreader::{BitReader, remove_emulation_prevention},
+    tag::video::vui::read_sample_aspect_ratio,
+};
+
+use super::NALU_TYPE_SPS;
+
+/// Sequence parameter set, see ITU-T H.264, 7.3.2.1.1
+///
+/// The parsing stops after VUI timing info, HRD parameters and the rest of VUI are skipped.
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct AvcSps {
+    pub profile_idc: u8,
+
+    /// `constraint_set0_flag` to `constraint_set5_flag` and reserved zero bits.
+    pub constraint_flags: u8,
+    pub level_idc: u8,
+    pub seq_parameter_set_id: u32,
+
+    /// 0 - monochrome, 1 - 4:2:0, 2 - 4:2:2, 3 - 4:4:4
+    pub chroma_format_idc: u8,
+    pub separate_colour_plane_flag: bool,
+    pub bit_depth_luma_minus8: u8,
+    pub bit_depth_chroma_minus8: u8,
+    pub qpprime_y_zero_transform_bypass_flag: bool,
+    pub seq_scaling_matrix_present_flag: bool,
+    pub log2_max_frame_num_minus4: u32,
+    pub pic_order_cnt_type: u32,
+    pub max_num_ref_frames: u32,
+    pub gaps_in_frame_num_value_allowed_flag: bool,
+    pub pic_width_in_mbs_minus1: u32,
+    pub pic_height_in_map_units_minus1: u32,
+    pub frame_mbs_only_flag: bool,
+    pub mb_adaptive_frame_field_flag: bool,
+    pub direct_8x8_inference_flag: bool,
+
+    /// Frame cropping in units of `CropUnitX` and `CropUnitY`, zeros if not present.
+    pub frame_crop_left_offset: u32,
+    pub frame_crop_right_offset: u32,
+    pub frame_crop_top_offset: u32,
+    pub frame_crop_bottom_offset: u32,
+
+    pub vui: Option<AvcVui>,
+}
+
+/// Video usability information, see ITU-T H.264, E.1.1
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct AvcVui {
+    /// Sample aspect ratio as width and height, `None` if not present or unspecified.
+    pub sample_aspect_ratio: Option<(u16, u16)>,
+
+    /// 0 - component, 1 - PAL, 2 - NTSC, 3 - SECAM, 4 - MAC, 5 - unspecified
+    pub video_format: u8,
+    pub video_full_range_flag: bool,
+
+    /// `colour_primaries`, `transfer_characteristics` and `matrix_coefficients`, see ITU-T H.273
+    pub colour_description: Option<(u8, u8, u8)>,
+
+    /// `num_units_in_tick`, `time_scale` and `fixed_frame_rate_flag`.
+    pub timing_info: Option<(u32, u32, bool)>,
+}
+
+impl AvcSps {
+    /// Parse SPS NAL unit including the NAL unit header.
+    pub fn parse(nalu: &[u8]) -> std::io::Result<Self> {
+        if nalu.first().map(|x| x & 0x1F) != Some(NALU_TYPE_SPS) {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidData,
+                "not a sequence parameter set",
+            ));
+        }
+
+        let rbsp = remove_emulation_prevention(&nalu[1..]);
+        let mut r = BitReader::new(&rbsp);
+
+        let mut x = AvcSps {
+            profile_idc: r.read_u8(8)?,
+            constraint_flags: r.read_u8(8)?,
+            level_idc: r.read_u8(8)?,
+            seq_parameter_set_id: r.read_ue()?,
+            chroma_format_idc: 1,
+            ..Default::default()
+        };
+
+        if matches!(
+            x.profile_idc,
+            100 | 110 | 122 | 244 | 44 | 83 | 86 | 118 | 128 | 138 | 139 | 134 | 135
+        ) {
+            x.chroma_format_idc = r.read_ue_max(3)? as u8;
+
+            if x.chroma_format_idc == 3 {
+                x.separate_colour_plane_flag = r.read_bit()?;
+            }
+
+            x.bit_depth_luma_minus8 = r.read_ue_max(6)? as u8;
+            x.bit_depth_chroma_minus8 = r.read_ue_max(6)? as u8;
+            x.qpprime_y_zero_transform_bypass_flag = r.read_bit()?;
+            x.seq_scaling_matrix_present_flag = r.read_bit()?;
+
+            if x.seq_scaling_matrix_present_flag {
+                let count = if x.chroma_format_idc != 3 { 8 } else { 12 };
+
+                for i in 0..count {
+                    // seq_scaling_list_present_flag
+                    if r.read_bit()? {
+                        skip_scaling_list(&mut r, if i < 6 { 16 } else { 64 })?;
+                    }
+                }
+            }
+        }
+
+        x.log2_max_frame_num_minus4 = r.read_ue_max(12)?;
+        x.pic_order_cnt_type = r.read_ue_max(2)?;
+
+        match x.pic_order_cnt_type {
+            // log2_max_pic_order_cnt_lsb_minus4
+            0 => _ = r.read_ue_max(12)?,
+            1 => {
+                // delta_pic_order_always_zero_flag, offset_for_non_ref_pic,
+                // offset_for_top_to_bottom_field
+                r.skip_bits(1)?;
+                r.read_se()?;
+                r.read_se()?;
+
+                // offset_for_ref_frame
+                for _ in 0..r.read_ue_max(255)? {
+                    r.read_se()?;
+                }
+            }
+            _ => (),
+        }
+
+        x.max_num_ref_frames = r.read_ue()?;
+        x.gaps_in_frame_num_value_allowed_flag = r.read_bit()?;
+        x.pic_width_in_mbs_minus1 = r.read_ue()?;
+        x.pic_height_in_map_units_minus1 = r.read_ue()?;
+        x.frame_mbs_only_flag = r.read_bit()?;
+
+        if !x.frame_mbs_only_flag {
+            x.mb_adaptive_frame_field_flag = r.read_bit()?;
+        }
+
+        x.direct_8x8_inference_flag = r.read_bit()?;
+
+        // frame_cropping_flag
+        if r.read_bit()? {
+            x.frame_crop_left_offset = r.read_ue()?;
+            x.frame_crop_right_offset = r.read_ue()?;
+            x.frame_crop_top_offset = r.read_ue()?;
+            x.frame_crop_bottom_offset = r.read_ue()?;
+        }
+
+        // vui_parameters_present_flag
+        if r.read_bit()? {
+            x.vui = Some(AvcVui::parse(&mut r)?);
+        }
+
+        Ok(x)
+    }
+
+    /// `ChromaArrayType`, zero if the colour planes are coded separately.
+    #[inline]
+    pub fn chroma_array_type(&self) -> u8 {
+        if self.separate_colour_plane_flag {
+            0
+        } else {
+            self.chroma_format_idc
+        }
+    }
+
+    #[inline]
+    pub fn bit_depth_luma(&self) -> u8 {
+        self.bit_depth_luma_minus8 + 8
+    }
+
+    #[inline]
+    pub fn bit_depth_chroma(&self) -> u8 {
+        self.bit_depth_chroma_minus8 + 8
+    }
+
+    /// Width of the decoded picture before cropping.
+    pub fn coded_width(&self) -> u32 {
+        (self.pic_width_in_mbs_minus1 + 1).saturating_mul(16)
+    }
+
+    /// Height of the decoded picture before cropping.
+    pub fn coded_height(&self) -> u32 {
+        let field_factor = 2 - self.frame_mbs_only_flag as u32;
+
+        (self.pic_height_in_map_units_minus1 + 1)
+            .saturating_mul(16)
+            .saturating_mul(field_factor)
+    }
+
+    /// `CropUnitX` and `CropUnitY`.
+    fn crop_units(&self) -> (u32, u32) {
+        let (sub_width, sub_height) = match self.chroma_array_type() {
+            1 => (2, 2),
+            2 => (2, 1),
+            _ => (1, 1),
+        };
+
+        (
+            sub_width,
+            sub_height * (2 - self.frame_mbs_only_flag as u32),
+        )
+    }
+
+    /// Width of the picture after cropping.
+    pub fn width(&self) -> u32 {
+        let (unit, _) = self.crop_units();
+        let crop = self
+            .frame_crop_left_offset
+            .saturating_add(self.frame_crop_right_offset)
+            .saturating_mul(unit);
+
+        self.coded_width().saturating_sub(crop)
+    }
+
+    /// Height of the picture after cropping.
+    pub fn height(&self) -> u32 {
+        let (_, unit) = self.crop_units();
+        let crop = self
+            .frame_crop_top_offset
+            .saturating_add(self.frame_crop_bottom_offset)
+            .saturating_mul(unit);
+
+        self.coded_height().saturating_sub(crop)
+    }
+
+    /// Frame rate from VUI timing info, `None` if not present.
+    ///
+    /// A tick is a field period, so two ticks make a frame.
+    pub fn frame_rate(&self) -> Option<f64> {
+        match self.vui.as_ref()?.timing_info? {
+            (num_units_in_tick, time_scale, _) if num_units_in_tick > 0 && time_scale > 0 => {
+                Some(time_scale as f64 / (2.0 * num_units_in_tick as f64))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl AvcVui {
+    fn parse(r: &mut BitReader<'_>) -> std::io::Result<Self> {
+        let mut x = AvcVui {
+            video_format: 5,
+            ..Default::default()
+        };
+
+        // aspect_ratio_info_present_flag
+        if r.read_bit()? {
+            x.sample_aspect_ratio = read_sample_aspect_ratio(r)?;
+        }
+
+        // overscan_info_present_flag, overscan_appropriate_flag
+        if r.read_bit()? {
+            r.skip_bits(1)?;
+        }
+
+        // video_signal_type_present_flag
+        if r.read_bit()? {
+            x.video_format = r.read_u8(3)?;
+            x.video_full_range_flag = r.read_bit()?;
+
+            // colour_description_present_flag
+            if r.read_bit()? {
+                x.colour_description = Some((r.read_u8(8)?, r.read_u8(8)?, r.read_u8(8)?));
+            }
+        }
+
+        // chroma_loc_info_present_flag, chroma_sample_loc_type_top_field,
+        // chroma_sample_loc_type_bottom_field
+        if r.read_bit()? {
+            r.read_ue()?;
+            r.read_ue()?;
+        }
+
+        // timing_info_present_flag
+        if r.read_bit()? {
+            x.timing_info = Some((r.read_u32(32)?, r.read_u32(32)?, r.read_bit()?));
+        }
+
+        Ok(x)
+    }
+}
+
+/// Skip `scaling_list()` of `size` entries, see ITU-T H.264, 7.3.2.1.1.1
+fn skip_scaling_list(r: &mut BitReader<'_>, size: usize) -> std::io::Result<()> {
+    let mut last_scale = 8i32;
+    let mut next_scale = 8i32;
+
+    for _ in 0..size {
+        if next_scale != 0 {
+            let delta_scale = r.read_se()?;
+            next_scale = last_scale.wrapping_add(delta_scale).rem_euclid(256);
+        }
+
+        if next_scale != 0 {
+            last_scale = next_scale;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AvcSps;
+
+    /// x264 1080p30 High profile SPS.
+    const SPS_1080P30: &[u8] = &[
+        0x67, 0x64, 0x00, 0x2a, 0xac, 0xd9, 0x40, 0x78, 0x02, 0x27, 0xe5, 0xc0, 0x44, 0x00, 0x00,
+        0x03, 0x00, 0x04, 0x00, 0x00, 0x03, 0x00, 0xf0, 0x3c, 0x60, 0xc6, 0x58,
+    ];
+
+    /// 720p25 High profile SPS.
+    const SPS_720P25: &[u8] = &[
+        0x67, 0x64, 0x00, 0x1f, 0xac, 0xd9, 0x40, 0x50, 0x05, 0xbb, 0x01, 0x10, 0x00, 0x00, 0x03,
+        0x00, 0x10, 0x00, 0x00, 0x03, 0x03, 0x20, 0xf1, 0x83, 0x19, 0x60, 0x00,
+    ];
+
+    #[test]
+    fn sps_1080p30() {
+        let sps = AvcSps::parse(SPS_1080P30).unwrap();
+
+        assert_eq!((sps.profile_idc, sps.level_idc), (100, 42));
+        assert_eq!(sps.chroma_format_idc, 1);
+        assert_eq!((sps.bit_depth_luma(), sps.bit_depth_chroma()), (8, 8));
+        assert_eq!((sps.coded_width(), sps.coded_height()), (1920, 1088));
+        assert_eq!((sps.width(), sps.height()), (1920, 1080));
+        assert_eq!(sps.frame_rate(), Some(30.0));
+
+        let vui = sps.vui.unwrap();
+        assert_eq!(vui.sample_aspect_ratio, Some((1, 1)));
+        assert_eq!(vui.timing_info, Some((1, 60, false)));
+    }
+
+    #[test]
+    fn sps_720p25() {
+        let sps = AvcSps::parse(SPS_720P25).unwrap();
+
+        assert_eq!((sps.width(), sps.height()), (1280, 720));
+        assert_eq!(sps.frame_rate(), Some(25.0));
+    }
+
+    #[test]
+    fn not_sps() {
+        assert!(AvcSps::parse(&[0x68, 0xeb, 0xe3, 0xcb, 0x22, 0xc0]).is_err());
+        assert!(AvcSps::parse(&SPS_1080P30[..6]).is_err());
+    }
+}
@@ -0,0 +1,314 @@
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::{
+    bitreader::BitReader,
+    error::Error,
+    parser::Parser,
+    reader::FlvReader,
+    writer::{FlvWriter, Writer},
+};
+
+/// `frame_sync_code` of key and intra-only frames.
+const FRAME_SYNC_CODE: u32 = 0x498342;
+
+/// `color_space` of RGB.
+const CS_RGB: u8 = 7;
+
+/// VPCodecConfigurationRecord, see VP Codec ISO Media File Format Binding, 2.2
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct VpCodecConfigurationRecord {
+    pub profile: u8,
+    pub level: u8,
+    pub bit_depth: u8,
+
+    /// 0 - 4:2:0 vertical, 1 - 4:2:0 colocated, 2 - 4:2:2, 3 - 4:4:4
+    pub chroma_subsampling: u8,
+    pub video_full_range_flag: bool,
+    pub colour_primaries: u8,
+    pub transfer_characteristics: u8,
+    pub matrix_coefficients: u8,
+    pub codec_initialization_data: Bytes,
+}
+
+/// The fields of VP9 uncompressed frame header, see VP9 Bitstream Specification, 6.2
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct Vp9FrameHeader {
+    pub profile: u8,
+    pub show_existing_frame: bool,
+    pub keyframe: bool,
+    pub intra_only: bool,
+    pub show_frame: bool,
+
+    /// Bit depth, only for key and intra-only frames.
+    pub bit_depth: u8,
+
+    /// Frame size, only for key and intra-only frames.
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Vp9FrameHeader {
+    /// Parse the uncompressed header of the frame.
+    ///
+    /// For a superframe the header of its first frame is parsed.
+    pub fn parse(data: &[u8]) -> std::io::Result<Self> {
+        let mut r = BitReader::new(data);
+
+        if r.read_u8(2)? != 2 {
+            return Err(std::io::ErrorKind::InvalidData.into());
+        }
+
+        let profile_low_bit = r.read_u8(1)?;
+        let profile_high_bit = r.read_u8(1)?;
+
+        let mut x = Vp9FrameHeader {
+            profile: (profile_high_bit << 1) | profile_low_bit,
+            bit_depth: 8,
+            ..Default::default()
+        };
+
+        if x.profile == 3 {
+            // reserved_zero
+            r.skip_bits(1)?;
+        }
+
+        x.show_existing_frame = r.read_bit()?;
+        if x.show_existing_frame {
+            return Ok(x);
+        }
+
+        x.keyframe = !r.read_bit()?;
+        x.show_frame = r.read_bit()?;
+
+        let error_resilient_mode = r.read_bit()?;
+
+        if !x.keyframe {
+            x.intra_only = !x.show_frame && r.read_bit()?;
+
+            if !error_resilient_mode {
+                // reset_frame_context
+                r.skip_bits(2)?;
+            }
+
+            if !x.intra_only {
+                return Ok(x);
+            }
+        }
+
+        if r.read_u32(24)? != FRAME_SYNC_CODE {
+            return Err(std::io::ErrorKind::InvalidData.into());
+        }
+
+        if x.keyframe || x.profile > 0 {
+            x.parse_color_config(&mut r)?;
+        }
+
+        if x.intra_only {
+            // refresh_frame_flags
+            r.skip_bits(8)?;
+        }
+
+        x.width = r.read_u32(16)? + 1;
+        x.height = r.read_u32(16)? + 1;
+
+        Ok(x)
+    }
+
+    fn parse_color_config(&mut self, r: &mut BitReader<'_>) -> std::io::Result<()> {
+        if self.profile >= 2 {
+            self.bit_depth = if r.read_bit()? { 12 } else { 10 };
+        }
+
+        let color_space = r.read_u8(3)?;
+        let has_subsampling = self.profile == 1 || self.profile == 3;
+
+        if color_space != CS_RGB {
+            // color_range
+            r.skip_bits(1)?;
+
+            if has_subsampling {
+                // subsampling_x, subsampling_y, reserved_zero
+                r.skip_bits(3)?;
+            }
+        } else if has_subsampling {
+            // reserved_zero
+            r.skip_bits(1)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct Vp9Parser {
+    /// The last configuration record.
+    pub config: Option<VpCodecConfigurationRecord>,
+
+    /// The last known frame size.
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Vp9Parser {
+    /// Parse the frame header, tracking the frame size.
+    pub fn parse_frame_header(&mut self, data: &[u8]) -> Option<Vp9FrameHeader> {
+        let x = Vp9FrameHeader::parse(data).ok()?;
+
+        if x.width > 0 && (x.width, x.height) != (self.width, self.height) {
+            log::debug!("vp9 frame size changed to {}x{}", x.width, x.height);
+
+            self.width = x.width;
+            self.height = x.height;
+        }
+
+        Some(x)
+    }
+}
+
+impl<E> Parser<E, VpCodecConfigurationRecord> for Vp9Parser {
+    type Error = Error<E>;
+
+    /// Parse `vpcC` box payload, starting with its version and flags.
+    fn parse(
+        &mut self,
+        reader: &mut impl FlvReader,
+    ) -> Result<VpCodecConfigurationRecord, Self::Error> {
+        /*version and flags*/
+        reader.read_u32()?;
+
+        let profile = reader.read_u8()?;
+        let level = reader.read_u8()?;
+        let flags = reader.read_u8()?;
+        let colour_primaries = reader.read_u8()?;
+        let transfer_characteristics = reader.read_u8()?;
+        let matrix_coefficients = reader.read_u8()?;
+        let size = reader.read_u16()?;
+
+        let record = VpCodecConfigurationRecord {
+            profile,
+            level,
+            bit_depth: flags >> 4,
+            chroma_subsampling: (flags >> 1) & 0x07,
+            video_full_range_flag: flags & 1 > 0,
+            colour_primaries,
+            transfer_characteristics,
+            matrix_coefficients,
+            codec_initialization_data: reader.read_to_bytes(size as usize)?,
+        };
+
+        self.config = Some(record.clone());
+
+        Ok(record)
+    }
+}
+
+impl<E> Writer<E, VpCodecConfigurationRecord> for FlvWriter {
+    type Error = Error<E>;
+
+    fn write(
+        &mut self,
+        buf: &mut BytesMut,
+        value: &VpCodecConfigurationRecord,
+    ) -> Result<(), Self::Error> {
+        /*version and flags*/
+        buf.put_u32(0x01000000);
+
+        buf.put_u8(value.profile);
+        buf.put_u8(value.level);
+        buf.put_u8(
+            (value.bit_depth << 4)
+                | ((value.chroma_subsampling & 0x07) << 1)
+                | value.video_full_range_flag as u8,
+        );
+        buf.put_u8(value.colour_primaries);
+        buf.put_u8(value.transfer_characteristics);
+        buf.put_u8(value.matrix_coefficients);
+        buf.put_u16(value.codec_initialization_data.len() as u16);
+        buf.put_slice(&value.codec_initialization_data);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{Bytes, BytesMut};
+
+    use super::{Vp9FrameHeader, Vp9Parser, VpCodecConfigurationRecord};
+    use crate::{
+        error::Error,
+        parser::Parser,
+        writer::{FlvWriter, Writer},
+    };
+
+    /// Profile 0 key frame of 1280x720.
+    const KEY_FRAME: &[u8] = &[0x82, 0x49, 0x83, 0x42, 0x00, 0x4f, 0xf0, 0x2c, 0xf0];
+
+    /// Profile 2 key frame of 1280x720, 10 bit.
+    const KEY_FRAME_10BIT: &[u8] = &[0x92, 0x49, 0x83, 0x42, 0x20, 0x27, 0xf8, 0x16, 0x78];
+
+    /// Profile 0 inter frame.
+    const INTER_FRAME: &[u8] = &[0x86, 0x00, 0x40];
+
+    #[test]
+    fn frame_header() {
+        let x = Vp9FrameHeader::parse(KEY_FRAME).unwrap();
+        assert_eq!(x.profile, 0);
+        assert!(x.keyframe && x.show_frame && !x.intra_only);
+        assert_eq!((x.width, x.height, x.bit_depth), (1280, 720, 8));
+
+        let x = Vp9FrameHeader::parse(KEY_FRAME_10BIT).unwrap();
+        assert_eq!(x.profile, 2);
+        assert!(x.keyframe);
+        assert_eq!((x.width, x.height, x.bit_depth), (1280, 720, 10));
+
+        let x = Vp9FrameHeader::parse(INTER_FRAME).unwrap();
+        assert!(!x.keyframe && x.show_frame);
+        assert_eq!(x.width, 0);
+
+        let x = Vp9FrameHeader::parse(&[0x88]).unwrap();
+        assert!(x.show_existing_frame && !x.keyframe);
+    }
+
+    #[test]
+    fn invalid_frame_header() {
+        // frame_marker
+        assert!(Vp9FrameHeader::parse(&[0x02, 0x49, 0x83, 0x42]).is_err());
+
+        // frame_sync_code
+        assert!(Vp9FrameHeader::parse(&[0x82, 0x49, 0x83, 0x43, 0x00, 0x4f, 0xf0, 0x2c]).is_err());
+
+        assert!(Vp9FrameHeader::parse(&KEY_FRAME[..6]).is_err());
+    }
+
+    #[test]
+    fn frame_size_tracking() {
+        let mut parser = Vp9Parser::default();
+
+        assert!(parser.parse_frame_header(KEY_FRAME).unwrap().keyframe);
+        assert!(!parser.parse_frame_header(INTER_FRAME).unwrap().keyframe);
+        assert_eq!((parser.width, parser.height), (1280, 720));
+        assert!(parser.parse_frame_header(&[0x02]).is_none());
+    }
+
+    #[test]
+    fn record_round_trip() {
+        let data = [
+            0x01, 0x00, 0x00, 0x00, 0x00, 0x1f, 0x82, 0x01, 0x01, 0x01, 0x00, 0x00,
+        ];
+
+        let mut parser = Vp9Parser::default();
+        let record: Result<VpCodecConfigurationRecord, Error> =
+            parser.parse(&mut Bytes::copy_from_slice(&data));
+        let record = record.unwrap();
+
+        assert_eq!((record.profile, record.level, record.bit_depth), (0, 31, 8));
+        assert_eq!(record.chroma_subsampling, 1);
+        assert!(!record.video_full_range_flag);
+        assert_eq!(parser.config.as_ref(), Some(&record));
+
+        let mut buf = BytesMut::new();
+        Writer::<flowly::Void, _>::write(&mut FlvWriter::default(), &mut buf, &record).unwrap();
+        assert_eq!(buf, &data[..]);
+    }
+}
@@ -0,0 +1,43 @@
+use crate::bitreader::BitReader;
+
+/// `aspect_ratio_idc` signaling the explicit sample aspect ratio.
+const ASPECT_RATIO_EXTENDED_SAR: u8 = 255;
+
+/// Sample aspect ratios by `aspect_ratio_idc`, see ITU-T H.264, table E-1 and ITU-T H.265,
+/// table E-1.
+const SAMPLE_ASPECT_RATIOS: [(u16, u16); 17] = [
+    (0, 0),
+    (1, 1),
+    (12, 11),
+    (10, 11),
+    (16, 11),
+    (40, 33),
+    (24, 11),
+    (20, 11),
+    (32, 11),
+    (80, 33),
+    (18, 11),
+    (15, 11),
+    (64, 33),
+    (160, 99),
+    (4, 3),
+    (3, 2),
+    (2, 1),
+];
+
+/// Read `aspect_ratio_idc` and the explicit sample aspect ratio following it, `None` if
+/// unspecified.
+pub(crate) fn read_sample_aspect_ratio(
+    r: &mut BitReader<'_>,
+) -> std::io::Result<Option<(u16, u16)>> {
+    let aspect_ratio_idc = r.read_u8(8)?;
+
+    Ok(if aspect_ratio_idc == ASPECT_RATIO_EXTENDED_SAR {
+        Some((r.read_u32(16)? as u16, r.read_u32(16)? as u16))
+    } else {
+        SAMPLE_ASPECT_RATIOS
+            .get(aspect_ratio_idc as usize)
+            .filter(|x| x.0 > 0)
+            .copied()
+    })
+}
@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+
+use bytes::{BufMut, BytesMut};
+
+use crate::{error::Error, header::FlvHeader};
+
+mod tag;
+
+pub trait Writer<E, T> {
+    type Error;
+
+    fn write(&mut self, buf: &mut BytesMut, value: &T) -> Result<(), Self::Error>;
+}
+
+#[derive(Debug, Clone)]
+pub struct FlvWriter {
+    /// Size in bytes of the NALU length prefix used for AVC/HEVC bodies.
+    pub nalu_length: u8,
+
+    /// NALU length size of the parsed configuration records written as is, by track id.
+    pub(crate) track_nalu_length: HashMap<u8, u8>,
+}
+
+impl Default for FlvWriter {
+    fn default() -> Self {
+        Self::new(4)
+    }
+}
+
+impl FlvWriter {
+    pub fn new(nalu_length: u8) -> Self {
+        Self {
+            nalu_length,
+            track_nalu_length: HashMap::new(),
+        }
+    }
+}
+
+impl<E> Writer<E, FlvHeader> for FlvWriter {
+    type Error = Error<E>;
+
+    /// Write FLV header followed by the `PreviousTagSize0` field.
+    fn write(&mut self, buf: &mut BytesMut, value: &FlvHeader) -> Result<(), Self::Error> {
+        let mut flags = 0u8;
+
+        if value.is_audio_present {
+            flags |= 0b00000100;
+        }
+
+        if value.is_video_present {
+            flags |= 0b00000001;
+        }
+
+        buf.put_slice(b"FLV");
+        buf.put_u8(value.version);
+        buf.put_u8(flags);
+        buf.put_u32(9 + value.remaining);
+        buf.put_bytes(0, value.remaining as usize);
+
+        // PreviousTagSize0 is always 0
+        buf.put_u32(0);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{BufMut, BytesMut};
+
+    use super::{FlvWriter, Writer};
+    use crate::{DEMUX_ALL_TYPES, header::FlvHeader, sync::read_flv_tags};
+
+    const SPS: &[u8] = &[
+        0x67, 0x64, 0x00, 0x2a, 0xac, 0xd9, 0x40, 0x78, 0x02, 0x27, 0xe5, 0xc0, 0x44, 0x00, 0x00,
+        0x03, 0x00, 0x04, 0x00, 0x00, 0x03, 0x00, 0x78, 0x3c, 0x60, 0xc6, 0x58,
+    ];
+    const PPS: &[u8] = &[0x68, 0xeb, 0xe3, 0xcb, 0x22, 0xc0];
+
+    /// 720p25 Main profile HEVC parameter sets.
+    const HEVC_VPS: &[u8] = &[
+        0x40, 0x01, 0x0c, 0x01, 0xff, 0xff, 0x01, 0x60, 0x00, 0x00, 0x03, 0x00, 0xb0, 0x00, 0x00,
+        0x03, 0x00, 0x00, 0x03, 0x00, 0x5d, 0xac, 0x09,
+    ];
+    const HEVC_SPS: &[u8] = &[
+        0x42, 0x01, 0x01, 0x01, 0x60, 0x00, 0x00, 0x03, 0x00, 0xb0, 0x00, 0x00, 0x03, 0x00, 0x00,
+        0x03, 0x00, 0x5d, 0xa0, 0x02, 0x80, 0x80, 0x2d, 0x16, 0x59, 0x59, 0xa4, 0x93, 0x2b, 0x9a,
+        0x80, 0x80, 0x80, 0x82, 0x00, 0x00, 0x03, 0x00, 0x02, 0x00, 0x00, 0x03, 0x00, 0x32, 0x10,
+    ];
+    const HEVC_PPS: &[u8] = &[0x44, 0x01, 0xc1, 0x72, 0xb4, 0x62, 0x40];
+
+    fn put_tag(buf: &mut BytesMut, tag_type: u8, timestamp: u32, data: &[u8]) {
+        buf.put_u8(tag_type);
+        buf.put_uint(data.len() as u64, 3);
+        buf.put_uint((timestamp & 0xFFFFFF) as u64, 3);
+        buf.put_u8((timestamp >> 24) as u8);
+        buf.put_uint(0, 3);
+        buf.put_slice(data);
+        buf.put_u32(data.len() as u32 + 11);
+    }
+
+    fn put_meta_number(buf: &mut BytesMut, key: &str, value: f64) {
+        buf.put_u16(key.len() as u16);
+        buf.put_slice(key.as_bytes());
+        buf.put_u8(0);
+        buf.put_f64(value);
+    }
+
+    /// Legacy FLV with `onMetaData`, AVC and AAC sequence headers and a few frames.
+    fn legacy_avc_aac() -> BytesMut {
+        let mut file = BytesMut::new();
+        file.put_slice(&[b'F', b'L', b'V', 1, 0b101, 0, 0, 0, 9, 0, 0, 0, 0]);
+
+        let mut meta = BytesMut::new();
+        meta.put_u8(2);
+        meta.put_u16(10);
+        meta.put_slice(b"onMetaData");
+        meta.put_u8(8);
+        meta.put_u32(6);
+        put_meta_number(&mut meta, "duration", 0.1);
+        put_meta_number(&mut meta, "width", 1920.0);
+        put_meta_number(&mut meta, "height", 1080.0);
+        put_meta_number(&mut meta, "videocodecid", 7.0);
+        put_meta_number(&mut meta, "audiocodecid", 10.0);
+        meta.put_u16(7);
+        meta.put_slice(b"encoder");
+        meta.put_u8(2);
+        meta.put_u16(13);
+        meta.put_slice(b"Lavf58.76.100");
+        meta.put_slice(&[0, 0, 9]);
+        put_tag(&mut file, 18, 0, &meta);
+
+        let mut avc = BytesMut::new();
+        avc.put_slice(&[0x17, 0, 0, 0, 0]);
+        avc.put_slice(&[1, 0x64, 0x00, 0x2a, 0xff, 0xe1]);
+        avc.put_u16(SPS.len() as u16);
+        avc.put_slice(SPS);
+        avc.put_u8(1);
+        avc.put_u16(PPS.len() as u16);
+        avc.put_slice(PPS);
+        avc.put_slice(&[0xfd, 0xf8, 0xf8, 0]);
+        put_tag(&mut file, 9, 0, &avc);
+
+        put_tag(&mut file, 8, 0, &[0xaf, 0, 0x12, 0x10]);
+
+        let mut frame = BytesMut::new();
+        frame.put_slice(&[0x17, 1, 0, 0, 0x42]);
+        frame.put_u32(5);
+        frame.put_slice(&[0x65, 0x88, 0x84, 0x00, 0x33]);
+        put_tag(&mut file, 9, 0, &frame);
+
+        put_tag(
+            &mut file,
+            8,
+            0,
+            &[0xaf, 1, 0x21, 0x10, 0x04, 0x60, 0x8c, 0x1c],
+        );
+
+        let mut frame = BytesMut::new();
+        frame.put_slice(&[0x27, 1, 0, 0, 0x21]);
+        frame.put_u32(4);
+        frame.put_slice(&[0x41, 0x9a, 0x02, 0x01]);
+        frame.put_u32(3);
+        frame.put_slice(&[0x06, 0x05, 0x80]);
+        put_tag(&mut file, 9, 33, &frame);
+
+        put_tag(&mut file, 8, 23, &[0xaf, 1, 0x21, 0x10, 0x05, 0x20]);
+
+        file
+    }
+
+    /// AVC records without high profile extension and with 2 bytes NALU length size, HEVC record
+    /// with `min_spatial_segmentation_idc` and `parallelismType` set.
+    fn config_records() -> BytesMut {
+        let mut file = BytesMut::new();
+        file.put_slice(&[b'F', b'L', b'V', 1, 0b001, 0, 0, 0, 9, 0, 0, 0, 0]);
+
+        for (timestamp, length_size) in [(0, 4), (33, 2)] {
+            let mut avc = BytesMut::new();
+            avc.put_slice(&[0x17, 0, 0, 0, 0]);
+            avc.put_slice(&[1, 0x64, 0x00, 0x2a, 0xfc | (length_size - 1), 0xe1]);
+            avc.put_u16(SPS.len() as u16);
+            avc.put_slice(SPS);
+            avc.put_u8(1);
+            avc.put_u16(PPS.len() as u16);
+            avc.put_slice(PPS);
+            put_tag(&mut file, 9, timestamp, &avc);
+
+            let mut frame = BytesMut::new();
+            frame.put_slice(&[0x17, 1, 0, 0, 0]);
+            frame.put_uint(5, length_size as usize);
+            frame.put_slice(&[0x65, 0x88, 0x84, 0x00, 0x33]);
+            put_tag(&mut file, 9, timestamp, &frame);
+        }
+
+        let mut hevc = BytesMut::new();
+        hevc.put_slice(&[0x90, b'h', b'v', b'c', b'1']);
+        hevc.put_slice(&[1, 0x01, 0x60, 0, 0, 0, 0xb0, 0, 0, 0, 0, 0, 0x5d]);
+        hevc.put_slice(&[0xf0, 0x10, 0xfd, 0xfd, 0xf8, 0xf8, 0, 0, 0x0f, 3]);
+
+        for (nal_unit_type, nalu) in [(32, HEVC_VPS), (33, HEVC_SPS), (34, HEVC_PPS)] {
+            hevc.put_u8(0x80 | nal_unit_type);
+            hevc.put_u16(1);
+            hevc.put_u16(nalu.len() as u16);
+            hevc.put_slice(nalu);
+        }
+        put_tag(&mut file, 9, 66, &hevc);
+
+        let mut frame = BytesMut::new();
+        frame.put_slice(&[0x93, b'h', b'v', b'c', b'1']);
+        frame.put_u32(3);
+        frame.put_slice(&[0x26, 0x01, 0xaf]);
+        put_tag(&mut file, 9, 66, &frame);
+
+        file
+    }
+
+    /// Parse `file` and write the tags back, returns the number of tags.
+    fn round_trip(file: &[u8]) -> (usize, BytesMut) {
+        let mut tags = read_flv_tags(file, DEMUX_ALL_TYPES);
+
+        let mut buf = BytesMut::new();
+        let mut writer = FlvWriter::default();
+        let mut count = 0;
+
+        while let Some(tag) = tags.next() {
+            if count == 0 {
+                let header: &FlvHeader = tags.header().unwrap();
+                Writer::<flowly::Void, _>::write(&mut writer, &mut buf, header).unwrap();
+            }
+
+            Writer::<flowly::Void, _>::write(&mut writer, &mut buf, &tag.unwrap()).unwrap();
+            count += 1;
+        }
+
+        (count, buf)
+    }
+
+    #[test]
+    fn legacy_avc_aac_round_trip() {
+        let file = legacy_avc_aac();
+        let (count, buf) = round_trip(&file);
+
+        assert_eq!(count, 7);
+        assert_eq!(buf, file);
+    }
+
+    #[test]
+    fn config_records_round_trip() {
+        let file = config_records();
+        let (count, buf) = round_trip(&file);
+
+        assert_eq!(count, 6);
+        assert_eq!(buf, file);
+    }
+}
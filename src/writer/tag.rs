@@ -0,0 +1,194 @@
+mod audio;
+mod meta;
+mod video;
+
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::{
+    error::Error,
+    tag::{
+        FlvTag, FlvTagData, FlvTagHeader,
+        meta::{MetaTag, on_metadata::ON_METADATA},
+    },
+};
+
+use super::{FlvWriter, Writer};
+
+/// Size of FLV tag header in bytes.
+pub(crate) const TAG_HEADER_SIZE: usize = 11;
+
+impl<E> Writer<E, FlvTagHeader> for FlvWriter {
+    type Error = Error<E>;
+
+    /// Write FLV tag header.
+    fn write(&mut self, buf: &mut BytesMut, value: &FlvTagHeader) -> Result<(), Self::Error> {
+        buf.put_u8(u8::from(value.tag_type) & 0b11111);
+
+        // The size of the tag's data part
+        buf.put_uint(value.data_size as u64, 3);
+
+        // The lower 24 bits of the timestamp followed by the extended upper 8 bits
+        buf.put_uint((value.timestamp & 0xFFFFFF) as u64, 3);
+        buf.put_u8((value.timestamp >> 24) as u8);
+
+        // The id of stream
+        buf.put_uint(value.stream_id as u64, 3);
+
+        Ok(())
+    }
+}
+
+impl<E> Writer<E, FlvTag> for FlvWriter {
+    type Error = Error<E>;
+
+    /// Write FLV tag followed by its `PreviousTagSize` field.
+    ///
+    /// The `data_size` of the tag header is recalculated from the encoded data.
+    fn write(&mut self, buf: &mut BytesMut, value: &FlvTag) -> Result<(), Self::Error> {
+        self.write_tag_with(buf, &value.header, |w, buf| {
+            w.write_flv_data::<E>(buf, &value.data)
+        })
+    }
+}
+
+impl FlvWriter {
+    /// Write FLV tag whose data is produced by `f`, followed by its `PreviousTagSize` field.
+    pub(crate) fn write_tag_with<E>(
+        &mut self,
+        buf: &mut BytesMut,
+        header: &FlvTagHeader,
+        f: impl FnOnce(&mut Self, &mut BytesMut) -> Result<(), Error<E>>,
+    ) -> Result<(), Error<E>> {
+        let start = buf.len();
+
+        Writer::<E, _>::write(self, buf, header)?;
+        f(self, buf)?;
+
+        let data_size = buf.len() - start - TAG_HEADER_SIZE;
+        buf[start + 1..start + 4].copy_from_slice(&(data_size as u32).to_be_bytes()[1..]);
+        buf.put_u32((TAG_HEADER_SIZE + data_size) as u32);
+
+        Ok(())
+    }
+
+    /// Write FLV tag data.
+    pub(crate) fn write_flv_data<E>(
+        &mut self,
+        buf: &mut BytesMut,
+        data: &FlvTagData,
+    ) -> Result<(), Error<E>> {
+        match data {
+            FlvTagData::Audio(tag) => self.write(buf, tag),
+            FlvTagData::Video(tag) => self.write(buf, tag),
+            FlvTagData::Meta(tag) => self.write(buf, tag),
+            FlvTagData::OnMetaData(meta) => {
+                let tag = MetaTag {
+                    wrapper: meta.wrapper.clone(),
+                    name: Bytes::from_static(ON_METADATA),
+                    value: meta.as_ref().clone().into(),
+                };
+
+                self.write(buf, &tag)
+            }
+            FlvTagData::Unknown => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{BufMut, BytesMut};
+
+    use crate::{
+        parser::FlvParser,
+        tag::{
+            FlvTagData, FlvTagType,
+            meta::{MetaContainer, MetaDataValue},
+        },
+        writer::FlvWriter,
+    };
+
+    fn put_string(buf: &mut BytesMut, value: &str) {
+        buf.put_u8(2);
+        buf.put_u16(value.len() as u16);
+        buf.put_slice(value.as_bytes());
+    }
+
+    fn put_number(buf: &mut BytesMut, key: &str, value: f64) {
+        buf.put_u16(key.len() as u16);
+        buf.put_slice(key.as_bytes());
+        buf.put_u8(0);
+        buf.put_f64(value);
+    }
+
+    fn round_trip(data: &BytesMut) -> FlvTagData {
+        let tag = FlvParser::default()
+            .parse_flv_data::<flowly::Void>(&mut data.clone().freeze(), FlvTagType::Metadata)
+            .unwrap();
+
+        let mut buf = BytesMut::new();
+        FlvWriter::default()
+            .write_flv_data::<flowly::Void>(&mut buf, &tag)
+            .unwrap();
+
+        assert_eq!(&buf, data);
+
+        tag
+    }
+
+    #[test]
+    fn on_metadata_object_with_set_data_frame() {
+        let mut data = BytesMut::new();
+        put_string(&mut data, "@setDataFrame");
+        put_string(&mut data, "onMetaData");
+        data.put_u8(3);
+        put_number(&mut data, "fps", 30.0);
+        put_number(&mut data, "width", 1920.0);
+        put_number(&mut data, "level", 4.0);
+        put_number(&mut data, "duration", 0.0);
+        data.put_slice(&[0, 0, 9]);
+
+        let FlvTagData::OnMetaData(meta) = round_trip(&data) else {
+            panic!("not onMetaData");
+        };
+
+        assert_eq!(meta.wrapper.as_deref(), Some(&b"@setDataFrame"[..]));
+        assert_eq!(meta.container, MetaContainer::Object);
+        assert_eq!(meta.width, Some(1920.0));
+        assert_eq!(meta.extra.len(), 2);
+    }
+
+    #[test]
+    fn on_metadata_ecma_array() {
+        let mut data = BytesMut::new();
+        put_string(&mut data, "onMetaData");
+        data.put_u8(8);
+        data.put_u32(7);
+        put_number(&mut data, "height", 1080.0);
+        put_number(&mut data, "custom", 1.0);
+        put_number(&mut data, "videocodecid", 7.0);
+        data.put_slice(&[0, 0, 9]);
+
+        let FlvTagData::OnMetaData(meta) = round_trip(&data) else {
+            panic!("not onMetaData");
+        };
+
+        assert_eq!(meta.wrapper, None);
+        assert_eq!(meta.container, MetaContainer::ECMAArray(Some(7)));
+    }
+
+    #[test]
+    fn meta_with_set_data_frame() {
+        let mut data = BytesMut::new();
+        put_string(&mut data, "@setDataFrame");
+        put_string(&mut data, "onCuePoint");
+        put_string(&mut data, "cue");
+
+        let FlvTagData::Meta(meta) = round_trip(&data) else {
+            panic!("not a script tag");
+        };
+
+        assert_eq!(meta.name, &b"onCuePoint"[..]);
+        assert_eq!(meta.value, MetaDataValue::String("cue".into()));
+    }
+}
@@ -0,0 +1,272 @@
+use bytes::{BufMut, BytesMut};
+
+use crate::{
+    error::Error,
+    tag::{
+        audio::{
+            AACPacketType, AudioChannelOrder, AudioPacketType, AudioTag, AudioTagBody,
+            AudioTagHeader, SoundFormat,
+        },
+        video::AvMultitrackType,
+    },
+    writer::{FlvWriter, Writer},
+};
+
+impl<E> Writer<E, AudioTagHeader> for FlvWriter {
+    type Error = Error<E>;
+
+    /// Write audio tag data header.
+    ///
+    /// For legacy AAC the `AACPacketType` byte is written as well.
+    fn write(&mut self, buf: &mut BytesMut, value: &AudioTagHeader) -> Result<(), Self::Error> {
+        if !value.is_enhanced() {
+            buf.put_u8(
+                (u8::from(value.sound_format) << 4)
+                    | (u8::from(value.sound_rate) << 2)
+                    | (u8::from(value.sound_size) << 1)
+                    | u8::from(value.sound_type),
+            );
+
+            if value.sound_format == SoundFormat::AAC {
+                buf.put_u8(match value.pkt_type {
+                    AudioPacketType::SequenceStart => AACPacketType::SequenceHeader.into(),
+                    _ => AACPacketType::Raw.into(),
+                });
+            }
+
+            return Ok(());
+        }
+
+        let pkt_type = if value.multitrack {
+            AudioPacketType::Multitrack
+        } else {
+            value.pkt_type
+        };
+
+        if value.dts_offset_ns > 0 {
+            buf.put_u8((u8::from(SoundFormat::ExHeader) << 4) | u8::from(AudioPacketType::ModEx));
+
+            // 3 bytes of TimestampOffsetNano, modExDataSize is stored minus one
+            buf.put_u8(2);
+            buf.put_uint(value.dts_offset_ns as u64, 3);
+
+            // AudioPacketModExType.TimestampOffsetNano followed by AudioPacketType
+            buf.put_u8(u8::from(pkt_type) & 0x0F);
+        } else {
+            buf.put_u8((u8::from(SoundFormat::ExHeader) << 4) | (u8::from(pkt_type) & 0x0F));
+        }
+
+        if value.multitrack {
+            buf.put_u8((u8::from(value.multitrack_type) << 4) | (u8::from(value.pkt_type) & 0x0F));
+
+            if value.multitrack_type != AvMultitrackType::ManyTracksManyCodecs {
+                buf.put_u32(value.fourcc.into());
+            }
+        } else {
+            buf.put_u32(value.fourcc.into());
+        }
+
+        Ok(())
+    }
+}
+
+impl<E> Writer<E, AudioTagBody> for FlvWriter {
+    type Error = Error<E>;
+
+    /// Write audio tag data body.
+    fn write(&mut self, buf: &mut BytesMut, value: &AudioTagBody) -> Result<(), Self::Error> {
+        if let Some(config) = &value.multichannel_config {
+            buf.put_u8(config.channel_order.order_type());
+            buf.put_u8(config.channel_count);
+
+            match &config.channel_order {
+                AudioChannelOrder::Native(flags) => buf.put_u32(*flags),
+                AudioChannelOrder::Custom(mapping) => buf.put_slice(mapping),
+                _ => (),
+            }
+        }
+
+        buf.put_slice(&value.data);
+
+        Ok(())
+    }
+}
+
+impl<E> Writer<E, AudioTag> for FlvWriter {
+    type Error = Error<E>;
+
+    /// Write audio tag data.
+    fn write(&mut self, buf: &mut BytesMut, value: &AudioTag) -> Result<(), Self::Error> {
+        let header = &value.header;
+
+        Writer::<E, _>::write(self, buf, header)?;
+
+        if !header.multitrack {
+            if let Some(track) = value.tracks.first() {
+                Writer::<E, _>::write(self, buf, &track.body)?;
+            }
+
+            return Ok(());
+        }
+
+        for track in &value.tracks {
+            if header.multitrack_type == AvMultitrackType::ManyTracksManyCodecs {
+                buf.put_u32(track.fourcc.into());
+            }
+
+            buf.put_u8(track.track_id);
+
+            if header.multitrack_type == AvMultitrackType::OneTrack {
+                Writer::<E, _>::write(self, buf, &track.body)?;
+                break;
+            }
+
+            // reserving space for `sizeOfAudioTrack`
+            buf.put_uint(0, 3);
+            let start = buf.len();
+
+            Writer::<E, _>::write(self, buf, &track.body)?;
+
+            let size = (buf.len() - start) as u32;
+            buf[start - 3..start].copy_from_slice(&size.to_be_bytes()[1..]);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{BufMut, Bytes, BytesMut};
+    use flowly::Fourcc;
+
+    use crate::{
+        parser::{FlvParser, Parser},
+        tag::{
+            audio::{AudioChannelOrder, AudioPacketType, AudioTag, SoundFormat},
+            video::AvMultitrackType,
+        },
+        writer::{FlvWriter, Writer},
+    };
+
+    /// Parse the tag data, write it back and compare with the source.
+    fn round_trip(data: &[u8]) -> AudioTag {
+        let tag: AudioTag = Parser::<flowly::Void, _>::parse(
+            &mut FlvParser::default(),
+            &mut Bytes::copy_from_slice(data),
+        )
+        .unwrap();
+
+        let mut buf = BytesMut::new();
+        Writer::<flowly::Void, _>::write(&mut FlvWriter::default(), &mut buf, &tag).unwrap();
+        assert_eq!(buf, data);
+
+        tag
+    }
+
+    #[test]
+    fn legacy_aac() {
+        let tag = round_trip(&[0xaf, 0x01, 0x21, 0x10]);
+
+        assert_eq!(tag.header.sound_format, SoundFormat::AAC);
+        assert_eq!(tag.header.pkt_type, AudioPacketType::CodedFrames);
+        assert_eq!(tag.header.fourcc, Fourcc::AUDIO_AAC);
+        assert_eq!(tag.tracks[0].body.data.as_ref(), &[0x21, 0x10]);
+    }
+
+    #[test]
+    fn enhanced_single_track() {
+        let mut data = BytesMut::new();
+        data.put_u8(0x90 | 1);
+        data.put_slice(b"Opus");
+        data.put_slice(&[0xfc, 0xff, 0xfe]);
+
+        let tag = round_trip(&data);
+
+        assert!(tag.header.is_enhanced());
+        assert!(!tag.header.multitrack);
+        assert_eq!(tag.header.pkt_type, AudioPacketType::CodedFrames);
+        assert_eq!(tag.tracks.len(), 1);
+        assert_eq!(tag.tracks[0].fourcc, Fourcc::AUDIO_OPUS);
+        assert_eq!(tag.tracks[0].body.data.as_ref(), &[0xfc, 0xff, 0xfe]);
+    }
+
+    #[test]
+    fn multichannel_config() {
+        let mut data = BytesMut::new();
+        data.put_u8(0x90 | 4);
+        data.put_slice(b"Opus");
+        data.put_slice(&[1, 2, 0, 0, 0, 3]);
+        let tag = round_trip(&data);
+
+        let config = tag.tracks[0].body.multichannel_config.as_ref().unwrap();
+        assert_eq!(config.channel_count, 2);
+        assert_eq!(config.channel_order, AudioChannelOrder::Native(3));
+
+        let mut data = BytesMut::new();
+        data.put_u8(0x90 | 4);
+        data.put_slice(b"Opus");
+        data.put_slice(&[2, 3, 0, 2, 1]);
+        let tag = round_trip(&data);
+
+        let config = tag.tracks[0].body.multichannel_config.as_ref().unwrap();
+        assert_eq!(
+            config.channel_order,
+            AudioChannelOrder::Custom(vec![0, 2, 1])
+        );
+    }
+
+    #[test]
+    fn multitrack() {
+        let mut data = BytesMut::new();
+        data.put_u8(0x90 | 5);
+        data.put_u8((1 << 4) | 1);
+        data.put_slice(b"Opus");
+        data.put_slice(&[1, 0, 0, 2, 0xaa, 0xbb]);
+        data.put_slice(&[2, 0, 0, 1, 0xcc]);
+
+        let tag = round_trip(&data);
+
+        assert_eq!(tag.header.multitrack_type, AvMultitrackType::ManyTracks);
+        assert_eq!(tag.tracks.len(), 2);
+        assert_eq!(tag.tracks[0].track_id, 1);
+        assert_eq!(tag.tracks[0].body.data.as_ref(), &[0xaa, 0xbb]);
+        assert_eq!(tag.tracks[1].track_id, 2);
+        assert_eq!(tag.tracks[1].fourcc, Fourcc::AUDIO_OPUS);
+        assert_eq!(tag.tracks[1].body.data.as_ref(), &[0xcc]);
+
+        let mut data = BytesMut::new();
+        data.put_u8(0x90 | 5);
+        data.put_u8((2 << 4) | 1);
+        data.put_slice(b"Opus");
+        data.put_slice(&[0, 0, 0, 1, 0xaa]);
+        data.put_slice(b"ac-3");
+        data.put_slice(&[1, 0, 0, 1, 0xbb]);
+
+        let tag = round_trip(&data);
+
+        assert_eq!(
+            tag.header.multitrack_type,
+            AvMultitrackType::ManyTracksManyCodecs
+        );
+        assert_eq!(tag.tracks[0].fourcc, Fourcc::AUDIO_OPUS);
+        assert_eq!(tag.tracks[1].fourcc, Fourcc::AUDIO_AC3);
+    }
+
+    #[test]
+    fn mod_ex_timestamp_offset_nano() {
+        let mut data = BytesMut::new();
+        data.put_u8(0x90 | 7);
+        data.put_u8(2);
+        data.put_uint(250_000, 3);
+        data.put_u8(1);
+        data.put_slice(b"Opus");
+        data.put_u8(0xfc);
+
+        let tag = round_trip(&data);
+
+        assert_eq!(tag.header.dts_offset_ns, 250_000);
+        assert_eq!(tag.header.pkt_type, AudioPacketType::CodedFrames);
+        assert_eq!(tag.tracks[0].fourcc, Fourcc::AUDIO_OPUS);
+    }
+}
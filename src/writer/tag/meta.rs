@@ -0,0 +1,268 @@
+use bytes::{BufMut, Bytes, BytesMut};
+use indexmap::IndexMap;
+
+use crate::{
+    error::Error,
+    tag::meta::{MetaDataValue, MetaTag},
+    writer::{FlvWriter, Writer},
+};
+
+const OBJECT_END_MARKER: [u8; 3] = [0x00, 0x00, 0x09];
+
+impl<E> Writer<E, MetaTag> for FlvWriter {
+    type Error = Error<E>;
+
+    /// Write script tag data.
+    fn write(&mut self, buf: &mut BytesMut, value: &MetaTag) -> Result<(), Self::Error> {
+        if let Some(wrapper) = &value.wrapper {
+            buf.put_u8(2);
+            self.write_meta_string(buf, wrapper)?;
+        }
+
+        buf.put_u8(2);
+        self.write_meta_string(buf, &value.name)?;
+
+        // AMF arguments or object properties.
+        self.write(buf, &value.value)
+    }
+}
+
+impl FlvWriter {
+    #[inline]
+    fn write_meta_string<E>(&mut self, buf: &mut BytesMut, value: &Bytes) -> Result<(), Error<E>> {
+        let len = u16::try_from(value.len()).map_err(|_| Error::UnsupportedMetaValue(2))?;
+
+        buf.put_u16(len);
+        buf.put_slice(value);
+
+        Ok(())
+    }
+
+    fn write_meta_object<E>(
+        &mut self,
+        buf: &mut BytesMut,
+        props: &IndexMap<Bytes, MetaDataValue>,
+    ) -> Result<(), Error<E>> {
+        for (key, value) in props {
+            self.write_meta_string(buf, key)?;
+            self.write(buf, value)?;
+        }
+
+        buf.put_slice(&OBJECT_END_MARKER);
+
+        Ok(())
+    }
+}
+
+impl<E> Writer<E, MetaDataValue> for FlvWriter {
+    type Error = Error<E>;
+
+    /// Write script tag data value.
+    fn write(&mut self, buf: &mut BytesMut, value: &MetaDataValue) -> Result<(), Self::Error> {
+        match value {
+            MetaDataValue::Number(v) => {
+                buf.put_u8(0);
+                buf.put_f64(*v);
+            }
+            MetaDataValue::Boolean(v) => {
+                buf.put_u8(1);
+                buf.put_u8(*v as u8);
+            }
+            MetaDataValue::String(v) | MetaDataValue::LongString(v) => {
+                if v.len() <= u16::MAX as usize {
+                    buf.put_u8(2);
+                    self.write_meta_string(buf, v)?;
+                } else {
+                    buf.put_u8(12);
+                    buf.put_u32(v.len() as u32);
+                    buf.put_slice(v);
+                }
+            }
+            MetaDataValue::Object(props) => {
+                buf.put_u8(3);
+                self.write_meta_object(buf, props)?;
+            }
+            MetaDataValue::Null => buf.put_u8(5),
+            MetaDataValue::Undefined => buf.put_u8(6),
+            MetaDataValue::Reference(idx) => {
+                buf.put_u8(7);
+                buf.put_u16(*idx);
+            }
+            MetaDataValue::ECMAArray(props, len) => {
+                buf.put_u8(8);
+                buf.put_u32(*len);
+                self.write_meta_object(buf, props)?;
+            }
+            MetaDataValue::StrictArray(arr) => {
+                buf.put_u8(10);
+                buf.put_u32(arr.len() as u32);
+                for item in arr {
+                    self.write(buf, item)?;
+                }
+            }
+            MetaDataValue::Date(date) => {
+                buf.put_u8(11);
+                buf.put_f64(date.date_time);
+                buf.put_i16(date.local_date_time_offset);
+            }
+            MetaDataValue::MovieClip => return Err(Error::UnsupportedMetaValue(4)),
+            MetaDataValue::Unknown(id) => return Err(Error::UnsupportedMetaValue(*id)),
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{BufMut, Bytes, BytesMut};
+    use indexmap::IndexMap;
+
+    use crate::{
+        error::Error,
+        parser::{FlvParser, Parser},
+        tag::meta::{MetaDataDate, MetaDataValue, MetaTag},
+        writer::{FlvWriter, Writer},
+    };
+
+    fn write_value(value: &MetaDataValue) -> Result<BytesMut, Error> {
+        let mut buf = BytesMut::new();
+        FlvWriter::default().write(&mut buf, value)?;
+
+        Ok(buf)
+    }
+
+    fn parse_value(data: &[u8]) -> MetaDataValue {
+        Parser::<flowly::Void, _>::parse(
+            &mut FlvParser::default(),
+            &mut Bytes::copy_from_slice(data),
+        )
+        .unwrap()
+    }
+
+    fn put_key(buf: &mut BytesMut, key: &str) {
+        buf.put_u16(key.len() as u16);
+        buf.put_slice(key.as_bytes());
+    }
+
+    fn put_number(buf: &mut BytesMut, key: &str, value: f64) {
+        put_key(buf, key);
+        buf.put_u8(0);
+        buf.put_f64(value);
+    }
+
+    #[test]
+    fn script_data_round_trip() {
+        let mut data = BytesMut::new();
+        data.put_u8(2);
+        put_key(&mut data, "onMetaData");
+
+        // the declared length does not match the number of properties
+        data.put_u8(8);
+        data.put_u32(0);
+        put_number(&mut data, "duration", 12.5);
+        put_number(&mut data, "width", 1280.0);
+        put_number(&mut data, "height", 720.0);
+        put_number(&mut data, "videocodecid", 7.0);
+        put_number(&mut data, "audiocodecid", 10.0);
+        put_key(&mut data, "stereo");
+        data.put_slice(&[1, 1]);
+        put_key(&mut data, "encoder");
+        data.put_u8(2);
+        put_key(&mut data, "Lavf58.76.100");
+        put_key(&mut data, "custom");
+        data.put_u8(3);
+        put_number(&mut data, "b", 2.0);
+        put_number(&mut data, "a", 1.0);
+        data.put_slice(&[0, 0, 9]);
+        data.put_slice(&[0, 0, 9]);
+
+        let tag: MetaTag =
+            Parser::<flowly::Void, _>::parse(&mut FlvParser::default(), &mut data.clone()).unwrap();
+
+        let MetaDataValue::ECMAArray(props, 0) = &tag.value else {
+            panic!("unexpected value {:?}", tag.value);
+        };
+        assert_eq!(props.len(), 8);
+
+        let mut buf = BytesMut::new();
+        Writer::<flowly::Void, _>::write(&mut FlvWriter::default(), &mut buf, &tag).unwrap();
+
+        assert_eq!(buf, data);
+    }
+
+    #[test]
+    fn encode_all_types() {
+        let mut object = IndexMap::new();
+        object.insert(Bytes::from_static(b"a"), MetaDataValue::Boolean(false));
+
+        let date = MetaDataDate {
+            date_time: 1.5,
+            local_date_time_offset: -60,
+        };
+
+        let cases = [
+            (
+                MetaDataValue::Number(1.5),
+                vec![0, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0],
+            ),
+            (MetaDataValue::Boolean(true), vec![1, 1]),
+            (
+                MetaDataValue::String(Bytes::from_static(b"ab")),
+                vec![2, 0, 2, b'a', b'b'],
+            ),
+            (
+                MetaDataValue::Object(object.clone()),
+                vec![3, 0, 1, b'a', 1, 0, 0, 0, 9],
+            ),
+            (MetaDataValue::Null, vec![5]),
+            (MetaDataValue::Undefined, vec![6]),
+            (MetaDataValue::Reference(3), vec![7, 0, 3]),
+            (
+                MetaDataValue::ECMAArray(object, 1),
+                vec![8, 0, 0, 0, 1, 0, 1, b'a', 1, 0, 0, 0, 9],
+            ),
+            (
+                MetaDataValue::StrictArray(vec![MetaDataValue::Null, MetaDataValue::Boolean(true)]),
+                vec![10, 0, 0, 0, 2, 5, 1, 1],
+            ),
+            (
+                MetaDataValue::Date(date),
+                vec![11, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0, 0xff, 0xc4],
+            ),
+        ];
+
+        for (value, data) in cases {
+            assert_eq!(write_value(&value).unwrap(), data, "{value:?}");
+            assert_eq!(parse_value(&data), value);
+        }
+    }
+
+    #[test]
+    fn long_string_selection() {
+        let short = MetaDataValue::LongString(Bytes::from_static(b"ab"));
+        assert_eq!(
+            write_value(&short).unwrap(),
+            [2, 0, 2, b'a', b'b'].as_slice()
+        );
+
+        let long = Bytes::from(vec![b'x'; u16::MAX as usize + 1]);
+        let data = write_value(&MetaDataValue::String(long.clone())).unwrap();
+
+        assert_eq!(data[..5], [12, 0, 1, 0, 0]);
+        assert_eq!(data.len(), 5 + long.len());
+        assert_eq!(parse_value(&data), MetaDataValue::LongString(long));
+    }
+
+    #[test]
+    fn unsupported_types() {
+        assert!(matches!(
+            write_value(&MetaDataValue::MovieClip),
+            Err(Error::UnsupportedMetaValue(4))
+        ));
+        assert!(matches!(
+            write_value(&MetaDataValue::Unknown(13)),
+            Err(Error::UnsupportedMetaValue(13))
+        ));
+    }
+}
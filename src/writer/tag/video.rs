@@ -0,0 +1,438 @@
+use bytes::{BufMut, BytesMut};
+use flowly::Fourcc;
+
+use crate::{
+    error::Error,
+    tag::video::{
+        AvMultitrackType, AvcPacketType, CodecID, VideoPacketType, VideoTag, VideoTagHeader,
+        VideoTrack, av1::Av1CodecConfigurationRecord, hevc::HevcDecoderConfigurationRecord,
+        mpeg4_avc::Mpeg4AvcRecord,
+    },
+    writer::{FlvWriter, Writer},
+};
+
+impl<E> Writer<E, VideoTagHeader> for FlvWriter {
+    type Error = Error<E>;
+
+    fn write(&mut self, buf: &mut BytesMut, value: &VideoTagHeader) -> Result<(), Self::Error> {
+        let frame_type = (u8::from(value.frame_type) & 0x07) << 4;
+
+        if !value.enhanced {
+            buf.put_u8(frame_type | (u8::from(CodecID::from(value.fourcc)) & 0x0F));
+
+            if let Some(command) = value.video_command {
+                buf.put_u8(command.into());
+            }
+
+            return Ok(());
+        }
+
+        let pkt_type = if value.multitrack {
+            VideoPacketType::Multitrack
+        } else {
+            value.pkt_type
+        };
+
+        if value.dts_offset_ns > 0 {
+            buf.put_u8(0x80 | frame_type | u8::from(VideoPacketType::ModEx));
+
+            // 3 bytes of TimestampOffsetNano, modExDataSize is stored minus one
+            buf.put_u8(2);
+            buf.put_uint(value.dts_offset_ns as u64, 3);
+
+            // VideoPacketModExType.TimestampOffsetNano followed by VideoPacketType
+            buf.put_u8(u8::from(pkt_type) & 0x0F);
+        } else {
+            buf.put_u8(0x80 | frame_type | (u8::from(pkt_type) & 0x0F));
+        }
+
+        if let Some(command) = value.video_command {
+            buf.put_u8(command.into());
+        } else if value.multitrack {
+            buf.put_u8((u8::from(value.multitrack_type) << 4) | (u8::from(value.pkt_type) & 0x0F));
+
+            if value.multitrack_type != AvMultitrackType::ManyTracksManyCodecs {
+                buf.put_u32(value.fourcc.into());
+            }
+        } else {
+            buf.put_u32(value.fourcc.into());
+        }
+
+        Ok(())
+    }
+}
+
+impl<E> Writer<E, VideoTag> for FlvWriter {
+    type Error = Error<E>;
+
+    fn write(&mut self, buf: &mut BytesMut, value: &VideoTag) -> Result<(), Self::Error> {
+        let header = &value.header;
+
+        Writer::<E, _>::write(self, buf, header)?;
+
+        if !header.multitrack {
+            if let Some(track) = value.tracks.first() {
+                if header.enhanced {
+                    self.write_video_body(buf, track)?;
+                } else {
+                    self.write_legacy_video_body(buf, track)?;
+                }
+            }
+
+            return Ok(());
+        }
+
+        for track in &value.tracks {
+            if header.multitrack_type == AvMultitrackType::ManyTracksManyCodecs {
+                buf.put_u32(track.fourcc.into());
+            }
+
+            buf.put_u8(track.track_id);
+
+            if header.multitrack_type == AvMultitrackType::OneTrack {
+                self.write_video_body(buf, track)?;
+                break;
+            }
+
+            // reserving space for `sizeOfVideoTrack`
+            buf.put_uint(0, 3);
+            let start = buf.len();
+
+            self.write_video_body(buf, track)?;
+
+            let size = (buf.len() - start) as u32;
+            buf[start - 3..start].copy_from_slice(&size.to_be_bytes()[1..]);
+        }
+
+        Ok(())
+    }
+}
+
+impl FlvWriter {
+    /// Write Enhanced RTMP video tag body of the track.
+    fn write_video_body<E>(
+        &mut self,
+        buf: &mut BytesMut,
+        track: &VideoTrack,
+    ) -> Result<(), Error<E>> {
+        let body = &track.body;
+
+        match (track.pkt_type, track.fourcc) {
+            (VideoPacketType::SequenceStart, Fourcc::VIDEO_AVC | Fourcc::VIDEO_HEVC) => {
+                self.write_video_config(buf, track)?;
+            }
+            (VideoPacketType::SequenceStart, Fourcc::VIDEO_AV1) => {
+                let record = Av1CodecConfigurationRecord::from_params(body.nalus.iter().cloned());
+
+                self.write(buf, &record)?;
+            }
+            (VideoPacketType::CodedFrames, Fourcc::VIDEO_AVC | Fourcc::VIDEO_HEVC) => {
+                buf.put_int(body.pts_offset as i64, 3);
+                self.write_nalus(buf, track.track_id, body.nalus.iter().map(|x| x.as_ref()));
+            }
+            (VideoPacketType::CodedFramesX, Fourcc::VIDEO_AVC | Fourcc::VIDEO_HEVC) => {
+                self.write_nalus(buf, track.track_id, body.nalus.iter().map(|x| x.as_ref()));
+            }
+            (
+                VideoPacketType::SequenceStart
+                | VideoPacketType::CodedFrames
+                | VideoPacketType::CodedFramesX
+                | VideoPacketType::Metadata,
+                _,
+            ) => body.nalus.iter().for_each(|x| buf.put_slice(x)),
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    /// Write legacy video tag body of the track.
+    fn write_legacy_video_body<E>(
+        &mut self,
+        buf: &mut BytesMut,
+        track: &VideoTrack,
+    ) -> Result<(), Error<E>> {
+        let body = &track.body;
+
+        if !matches!(track.fourcc, Fourcc::VIDEO_AVC | Fourcc::VIDEO_HEVC) {
+            body.nalus.iter().for_each(|x| buf.put_slice(x));
+
+            return Ok(());
+        }
+
+        let packet_type = match track.pkt_type {
+            VideoPacketType::SequenceStart => AvcPacketType::SequenceHeader,
+            VideoPacketType::SequenceEnd => AvcPacketType::EndOfSequence,
+            _ => AvcPacketType::NALU,
+        };
+
+        buf.put_u8(packet_type.into());
+        buf.put_int(body.pts_offset as i64, 3);
+
+        match packet_type {
+            AvcPacketType::SequenceHeader => {
+                self.write_video_config(buf, track)?;
+            }
+            AvcPacketType::NALU => {
+                self.write_nalus(buf, track.track_id, body.nalus.iter().map(|x| x.as_ref()));
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    /// Write AVC/HEVC configuration record of the track, the parsed record is written as is and
+    /// its NALU length size is used for the following coded frames of the track.
+    fn write_video_config<E>(
+        &mut self,
+        buf: &mut BytesMut,
+        track: &VideoTrack,
+    ) -> Result<(), Error<E>> {
+        let nalus = track.body.nalus.iter().cloned();
+
+        match (&track.body.config, track.fourcc) {
+            (Some(config), fourcc) => {
+                // `lengthSizeMinusOne` is the 5th byte of AVC and the 22nd byte of HEVC record
+                let offset = if fourcc == Fourcc::VIDEO_HEVC { 21 } else { 4 };
+
+                if let Some(x) = config.get(offset) {
+                    self.track_nalu_length
+                        .insert(track.track_id, (x & 0x03) + 1);
+                }
+
+                buf.put_slice(config);
+            }
+            (None, Fourcc::VIDEO_HEVC) => {
+                self.track_nalu_length.remove(&track.track_id);
+                let record = HevcDecoderConfigurationRecord::from_params(nalus, self.nalu_length);
+
+                self.write(buf, &record)?;
+            }
+            (None, _) => {
+                self.track_nalu_length.remove(&track.track_id);
+                let record = Mpeg4AvcRecord::from_params(nalus, self.nalu_length);
+
+                self.write(buf, &record)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{BufMut, Bytes, BytesMut};
+    use flowly::Fourcc;
+
+    use crate::{
+        parser::{FlvParser, Parser},
+        tag::video::{AvMultitrackType, VideoFrameType, VideoPacketType, VideoTag},
+        writer::{FlvWriter, Writer},
+    };
+
+    /// AV1 temporal unit of a key frame.
+    const AV1_KEY_FRAME: &[u8] = &[0x12, 0x00, 0x32, 0x02, 0x10, 0x00];
+
+    /// AVC parameter sets of 1080p stream.
+    const AVC_SPS: &[u8] = &[
+        0x67, 0x64, 0x00, 0x2a, 0xac, 0xd9, 0x40, 0x78, 0x02, 0x27, 0xe5, 0xc0, 0x44, 0x00, 0x00,
+        0x03, 0x00, 0x04, 0x00, 0x00, 0x03, 0x00, 0x78, 0x3c, 0x60, 0xc6, 0x58,
+    ];
+    const AVC_PPS: &[u8] = &[0x68, 0xeb, 0xe3, 0xcb, 0x22, 0xc0];
+
+    /// VP9 profile 0 key and inter frames.
+    const VP9_KEY_FRAME: &[u8] = &[0x82, 0x49, 0x83, 0x42, 0x00, 0x4f, 0xf0, 0x2c, 0xf0];
+    const VP9_INTER_FRAME: &[u8] = &[0x86, 0x00, 0x40];
+
+    fn round_trip(data: &[u8]) -> VideoTag {
+        let tag: VideoTag = Parser::<flowly::Void, _>::parse(
+            &mut FlvParser::default(),
+            &mut Bytes::copy_from_slice(data),
+        )
+        .unwrap();
+
+        let mut buf = BytesMut::new();
+        Writer::<flowly::Void, _>::write(&mut FlvWriter::default(), &mut buf, &tag).unwrap();
+        assert_eq!(buf, data);
+
+        tag
+    }
+
+    fn put_track(buf: &mut BytesMut, fourcc: Option<&[u8; 4]>, track_id: u8, body: &[u8]) {
+        if let Some(fourcc) = fourcc {
+            buf.put_slice(fourcc);
+        }
+
+        buf.put_u8(track_id);
+        buf.put_uint(body.len() as u64, 3);
+        buf.put_slice(body);
+    }
+
+    #[test]
+    fn multitrack_many_codecs() {
+        let mut data = BytesMut::new();
+        data.put_u8(0x80 | (1 << 4) | 6);
+        data.put_u8((2 << 4) | 1);
+        put_track(&mut data, Some(b"av01"), 0, AV1_KEY_FRAME);
+        put_track(&mut data, Some(b"vp09"), 1, VP9_KEY_FRAME);
+
+        let tag = round_trip(&data);
+
+        assert!(tag.header.multitrack);
+        assert_eq!(
+            tag.header.multitrack_type,
+            AvMultitrackType::ManyTracksManyCodecs
+        );
+        assert_eq!(tag.header.pkt_type, VideoPacketType::CodedFrames);
+        assert_eq!(tag.tracks.len(), 2);
+
+        assert_eq!(tag.tracks[0].track_id, 0);
+        assert_eq!(tag.tracks[0].fourcc, Fourcc::VIDEO_AV1);
+        assert_eq!(tag.tracks[0].body.nalus.len(), 2);
+        assert_eq!(tag.tracks[0].frame_type, VideoFrameType::Key);
+
+        assert_eq!(tag.tracks[1].track_id, 1);
+        assert_eq!(tag.tracks[1].fourcc, Fourcc::VIDEO_VP9);
+        assert_eq!(tag.tracks[1].body.nalus, [VP9_KEY_FRAME]);
+    }
+
+    #[test]
+    fn multitrack_many_tracks() {
+        let mut data = BytesMut::new();
+        data.put_u8(0x80 | (1 << 4) | 6);
+        data.put_u8((1 << 4) | 1);
+        data.put_slice(b"vp09");
+        put_track(&mut data, None, 0, VP9_KEY_FRAME);
+        put_track(&mut data, None, 5, VP9_INTER_FRAME);
+
+        let tag = round_trip(&data);
+
+        assert_eq!(tag.header.multitrack_type, AvMultitrackType::ManyTracks);
+        assert_eq!(tag.header.fourcc, Fourcc::VIDEO_VP9);
+
+        let tracks: Vec<_> = tag
+            .tracks
+            .iter()
+            .map(|x| (x.track_id, x.fourcc, x.frame_type))
+            .collect();
+
+        assert_eq!(
+            tracks,
+            [
+                (0, Fourcc::VIDEO_VP9, VideoFrameType::Key),
+                (5, Fourcc::VIDEO_VP9, VideoFrameType::Inter),
+            ]
+        );
+    }
+
+    /// AVC record of 1080p stream with `length_size` bytes NALU length.
+    fn avc_record(length_size: u8) -> Vec<u8> {
+        let mut data = vec![1, 0x64, 0x00, 0x2a, 0xfc | (length_size - 1), 0xe1];
+        data.put_u16(AVC_SPS.len() as u16);
+        data.put_slice(AVC_SPS);
+        data.put_u8(1);
+        data.put_u16(AVC_PPS.len() as u16);
+        data.put_slice(AVC_PPS);
+        data
+    }
+
+    #[test]
+    fn multitrack_nalu_length_per_track() {
+        let mut parser = FlvParser::default();
+        let mut writer = FlvWriter::default();
+
+        let mut tags = Vec::new();
+
+        let mut data = BytesMut::new();
+        data.put_u8(0x80 | (1 << 4) | 6);
+        data.put_u8(1 << 4);
+        data.put_slice(b"avc1");
+        put_track(&mut data, None, 0, &avc_record(4));
+        put_track(&mut data, None, 1, &avc_record(2));
+        tags.push(data);
+
+        let mut data = BytesMut::new();
+        data.put_u8(0x80 | (1 << 4) | 6);
+        data.put_u8((1 << 4) | 3);
+        data.put_slice(b"avc1");
+        put_track(
+            &mut data,
+            None,
+            0,
+            &[0, 0, 0, 2, 0x65, 0x88, 0, 0, 0, 1, 0x06],
+        );
+        put_track(&mut data, None, 1, &[0, 2, 0x65, 0x88, 0, 1, 0x06]);
+        tags.push(data);
+
+        for data in &tags {
+            let tag: VideoTag =
+                Parser::<flowly::Void, _>::parse(&mut parser, &mut data.clone().freeze()).unwrap();
+
+            if tag.header.pkt_type == VideoPacketType::CodedFramesX {
+                for track in &tag.tracks {
+                    assert_eq!(track.body.nalus, [&[0x65, 0x88][..], &[0x06]]);
+                }
+            }
+
+            let mut buf = BytesMut::new();
+            Writer::<flowly::Void, _>::write(&mut writer, &mut buf, &tag).unwrap();
+            assert_eq!(buf, data);
+        }
+    }
+
+    #[test]
+    fn multitrack_one_track() {
+        // the track has no size field and takes the rest of the tag
+        let mut data = BytesMut::new();
+        data.put_u8(0x80 | (1 << 4) | 6);
+        data.put_u8(1);
+        data.put_slice(b"vp09");
+        data.put_u8(3);
+        data.put_slice(VP9_KEY_FRAME);
+
+        let tag = round_trip(&data);
+
+        assert_eq!(tag.header.multitrack_type, AvMultitrackType::OneTrack);
+        assert_eq!(tag.tracks.len(), 1);
+        assert_eq!(tag.tracks[0].track_id, 3);
+    }
+
+    #[test]
+    fn mod_ex_timestamp_offset_nano() {
+        let mut data = BytesMut::new();
+        data.put_u8(0x80 | (1 << 4) | 7);
+
+        // modExDataSize minus one, 500000 ns, TimestampOffsetNano and CodedFrames
+        data.put_u8(2);
+        data.put_uint(500_000, 3);
+        data.put_u8(1);
+        data.put_slice(b"vp09");
+        data.put_slice(VP9_KEY_FRAME);
+
+        let tag = round_trip(&data);
+
+        assert_eq!(tag.header.dts_offset_ns, 500_000);
+        assert_eq!(tag.header.pkt_type, VideoPacketType::CodedFrames);
+        assert_eq!(tag.header.fourcc, Fourcc::VIDEO_VP9);
+        assert_eq!(tag.tracks[0].body.nalus, [VP9_KEY_FRAME]);
+
+        // TimestampOffsetNano of unexpected size is ignored
+        let mut data = BytesMut::new();
+        data.put_u8(0x80 | (1 << 4) | 7);
+        data.put_u8(0);
+        data.put_u8(0xff);
+        data.put_u8(1);
+        data.put_slice(b"vp09");
+        data.put_slice(VP9_KEY_FRAME);
+
+        let tag: VideoTag =
+            Parser::<flowly::Void, _>::parse(&mut FlvParser::default(), &mut data.freeze())
+                .unwrap();
+
+        assert_eq!(tag.header.dts_offset_ns, 0);
+        assert_eq!(tag.header.pkt_type, VideoPacketType::CodedFrames);
+        assert_eq!(tag.tracks[0].body.nalus, [VP9_KEY_FRAME]);
+    }
+}